@@ -0,0 +1,998 @@
+use bytes::{Buf, BufMut};
+
+pub use rocket_protocol::config::{FlightSetting, FLIGHT_SETTINGS};
+
+use crate::{
+    command::Command,
+    storage::{KeyValueStore, StorageError},
+};
+
+const STORAGE_KEY: &str = "config";
+const FLIGHT_STORAGE_KEY: &str = "flight";
+/// Where the sea level pressure was kept, as an `f64` in Pa, before it
+/// joined the [`FlightConfig`]
+const LEGACY_SEA_LEVEL_PRESSURE_KEY: &str = "psl";
+/// Where the basestation keeps the sea level pressure last entered on it,
+/// as an `f64` in Pa
+const BASESTATION_SEA_LEVEL_PRESSURE_KEY: &str = "psl";
+
+/// Sea level pressure used until another is set, in Pa.
+pub const DEFAULT_SEA_LEVEL_PRESSURE: f64 = 102030.0;
+
+/// Layout of the stored [`BasestationConfig`].  Fields are only ever
+/// appended, each new one bumping the version, so older records are read
+/// with defaults for what they lack and newer ones as far as understood.
+pub const CONFIG_VERSION: u8 = 2;
+/// Layout of the stored [`FlightConfig`], versioned the same way.
+pub const FLIGHT_CONFIG_VERSION: u8 = 1;
+/// Settings stored by each version of the [`FlightConfig`] from 1, which
+/// are the first that many of [`FLIGHT_SETTINGS`].
+const FLIGHT_SETTINGS_BY_VERSION: [usize; FLIGHT_CONFIG_VERSION as usize] = [6];
+
+/// Longest encoded config: the fixed fields and both strings at their
+/// longest.
+const MAX_ENCODED_SIZE: usize = 1 + 1 + 4 + (1 + 32) + (1 + 63) + 1 + 6 + (1 + 64);
+
+/// How a config field is edited and what it accepts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FieldKind {
+    /// `on` or `off`
+    Flag,
+    Number {
+        min: u32,
+        max: u32,
+    },
+    /// Some number of characters, or none at all if `optional`
+    Text {
+        min_len: usize,
+        max_len: usize,
+        optional: bool,
+        masked: bool,
+    },
+    /// Six hex bytes separated by colons, e.g. `D4:D4:DA:AA:27:5C`
+    Mac,
+}
+
+impl FieldKind {
+    /// What the field accepts, for error messages.
+    pub fn describe(&self) -> String {
+        match self {
+            FieldKind::Flag => "on or off".to_string(),
+            FieldKind::Number { min, max } => format!("{} to {}", min, max),
+            FieldKind::Text {
+                min_len,
+                max_len,
+                optional: true,
+                ..
+            } => format!("nothing, or {} to {} characters", min_len, max_len),
+            FieldKind::Text {
+                min_len, max_len, ..
+            } => format!("{} to {} characters", min_len, max_len),
+            FieldKind::Mac => "a MAC address like D4:D4:DA:AA:27:5C".to_string(),
+        }
+    }
+}
+
+/// A field of [`BasestationConfig`], in the order of [`FIELDS`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigKey {
+    WebServices,
+    HttpStackSize,
+    ApSsid,
+    ApPassword,
+    Channel,
+    RocketMac,
+    ApiToken,
+}
+
+impl ConfigKey {
+    /// The key named `name` on the serial console.
+    pub fn from_name(name: &str) -> Result<Self, ConfigError> {
+        field(name)
+            .map(|field| field.key)
+            .ok_or(ConfigError::UnknownKey)
+    }
+
+    pub fn name(self) -> &'static str {
+        self.field().name
+    }
+
+    pub fn field(self) -> &'static ConfigField {
+        &FIELDS[self as usize]
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ConfigField {
+    pub key: ConfigKey,
+    /// Name on the serial console
+    pub name: &'static str,
+    /// Name on the settings screen
+    pub label: &'static str,
+    pub kind: FieldKind,
+}
+
+/// Every field of [`BasestationConfig`], in the order shown.
+pub static FIELDS: [ConfigField; 7] = [
+    ConfigField {
+        key: ConfigKey::WebServices,
+        name: "web",
+        label: "WEB",
+        kind: FieldKind::Flag,
+    },
+    ConfigField {
+        key: ConfigKey::HttpStackSize,
+        name: "stack",
+        label: "HTTP STACK",
+        kind: FieldKind::Number {
+            min: 4096,
+            max: 65536,
+        },
+    },
+    ConfigField {
+        key: ConfigKey::ApSsid,
+        name: "ssid",
+        label: "AP SSID",
+        kind: FieldKind::Text {
+            min_len: 1,
+            max_len: 32,
+            optional: false,
+            masked: false,
+        },
+    },
+    ConfigField {
+        key: ConfigKey::ApPassword,
+        name: "password",
+        label: "AP PASSWORD",
+        kind: FieldKind::Text {
+            min_len: 8,
+            max_len: 63,
+            optional: true,
+            masked: true,
+        },
+    },
+    ConfigField {
+        key: ConfigKey::Channel,
+        name: "channel",
+        label: "CHANNEL",
+        kind: FieldKind::Number { min: 1, max: 13 },
+    },
+    ConfigField {
+        key: ConfigKey::RocketMac,
+        name: "rocket",
+        label: "ROCKET MAC",
+        kind: FieldKind::Mac,
+    },
+    ConfigField {
+        key: ConfigKey::ApiToken,
+        name: "token",
+        label: "API TOKEN",
+        kind: FieldKind::Text {
+            min_len: 8,
+            max_len: 64,
+            optional: true,
+            masked: true,
+        },
+    },
+];
+
+/// The field named `name` on the serial console.
+pub fn field(name: &str) -> Option<&'static ConfigField> {
+    FIELDS.iter().find(|field| field.name == name)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    UnknownKey,
+    /// The value is not what the field accepts
+    Invalid(&'static ConfigField),
+    /// Not a config command the console knows
+    Usage,
+    /// Not a flight computer setting
+    UnknownSetting,
+    /// Outside what the flight computer accepts for the setting
+    OutOfRange(FlightSetting),
+    /// Not a rocket command the console knows
+    RocketUsage,
+}
+
+impl ConfigError {
+    pub fn describe(&self) -> String {
+        match self {
+            ConfigError::UnknownKey => format!(
+                "unknown key, expected one of {}",
+                FIELDS.map(|field| field.name).join(", ")
+            ),
+            ConfigError::Invalid(field) => {
+                format!("{} must be {}", field.name, field.kind.describe())
+            }
+            ConfigError::Usage => {
+                "usage: config [get <key> | set <key> <value> | reset]".to_string()
+            }
+            ConfigError::UnknownSetting => format!(
+                "unknown setting, expected one of {}",
+                FLIGHT_SETTINGS.map(|setting| setting.name()).join(", ")
+            ),
+            ConfigError::RocketUsage => {
+                "usage: rocket [get <setting> | set <setting> <value> confirm | factory-reset confirm]"
+                    .to_string()
+            }
+            ConfigError::OutOfRange(setting) => format!(
+                "{} must be {} to {}",
+                setting.name(),
+                setting.range().start(),
+                setting.range().end()
+            ),
+        }
+    }
+}
+
+/// Basestation settings, read from NVS at boot.
+///
+/// Changes are saved straight away but only take effect after a restart.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasestationConfig {
+    /// Serve the web dashboard and telemetry websocket
+    pub web_services: bool,
+    /// Stack of each HTTP server task, in bytes
+    pub http_stack_size: u32,
+    pub ap_ssid: String,
+    /// Empty for an open access point
+    pub ap_password: String,
+    /// WiFi channel, which the rocket must also use for ESP-NOW
+    pub channel: u8,
+    pub rocket_mac: [u8; 6],
+    /// Accepted by the web API as well as the AP password; empty for none
+    pub api_token: String,
+}
+
+impl Default for BasestationConfig {
+    fn default() -> Self {
+        Self {
+            web_services: false,
+            http_stack_size: 10240,
+            ap_ssid: "omega9".to_string(),
+            ap_password: "knock it off".to_string(),
+            channel: 1,
+            rocket_mac: [0xD4, 0xD4, 0xDA, 0xAA, 0x27, 0x5C],
+            api_token: String::new(),
+        }
+    }
+}
+
+fn format_mac(mac: &[u8; 6]) -> String {
+    mac.map(|byte| format!("{:02X}", byte)).join(":")
+}
+
+fn parse_mac(text: &str) -> Option<[u8; 6]> {
+    let mut mac = [0_u8; 6];
+    let mut bytes = text.split(':');
+
+    for byte in &mut mac {
+        let hex = bytes.next()?;
+        if hex.len() != 2 {
+            return None;
+        }
+        *byte = u8::from_str_radix(hex, 16).ok()?;
+    }
+
+    match bytes.next() {
+        Some(_) => None,
+        None => Some(mac),
+    }
+}
+
+impl BasestationConfig {
+    /// Value of `key`, as shown on the console.
+    pub fn get(&self, key: ConfigKey) -> String {
+        match key {
+            ConfigKey::WebServices if self.web_services => "on".to_string(),
+            ConfigKey::WebServices => "off".to_string(),
+            ConfigKey::HttpStackSize => self.http_stack_size.to_string(),
+            ConfigKey::ApSsid => self.ap_ssid.clone(),
+            ConfigKey::ApPassword => self.ap_password.clone(),
+            ConfigKey::Channel => self.channel.to_string(),
+            ConfigKey::RocketMac => format_mac(&self.rocket_mac),
+            ConfigKey::ApiToken => self.api_token.clone(),
+        }
+    }
+
+    /// Value of `field` as shown on the settings screen, hiding passwords.
+    pub fn display(&self, field: &ConfigField) -> String {
+        let value = self.get(field.key);
+
+        match field.kind {
+            FieldKind::Text { masked: true, .. } => "*".repeat(value.len()),
+            _ => value,
+        }
+    }
+
+    /// Set `key` from `value`, which is checked against what the field
+    /// accepts.
+    pub fn set(&mut self, key: ConfigKey, value: &str) -> Result<(), ConfigError> {
+        let field = key.field();
+        let invalid = ConfigError::Invalid(field);
+
+        match field.kind {
+            FieldKind::Flag => {
+                let flag = match value {
+                    "on" | "1" | "true" => true,
+                    "off" | "0" | "false" => false,
+                    _ => return Err(invalid),
+                };
+                match key {
+                    ConfigKey::WebServices => self.web_services = flag,
+                    _ => return Err(invalid),
+                }
+            }
+            FieldKind::Number { min, max } => {
+                let number = value.parse::<u32>().map_err(|_| invalid)?;
+                if !(min..=max).contains(&number) {
+                    return Err(invalid);
+                }
+                match key {
+                    ConfigKey::HttpStackSize => self.http_stack_size = number,
+                    ConfigKey::Channel => self.channel = number as u8,
+                    _ => return Err(invalid),
+                }
+            }
+            FieldKind::Text {
+                min_len,
+                max_len,
+                optional,
+                ..
+            } => {
+                let len = value.len();
+                let accepted = (min_len..=max_len).contains(&len) || (optional && len == 0);
+                if !accepted {
+                    return Err(invalid);
+                }
+                match key {
+                    ConfigKey::ApSsid => self.ap_ssid = value.to_string(),
+                    ConfigKey::ApPassword => self.ap_password = value.to_string(),
+                    ConfigKey::ApiToken => self.api_token = value.to_string(),
+                    _ => return Err(invalid),
+                }
+            }
+            FieldKind::Mac => self.rocket_mac = parse_mac(value).ok_or(invalid)?,
+        }
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(MAX_ENCODED_SIZE);
+
+        buffer.put_u8(CONFIG_VERSION);
+        buffer.put_u8(self.web_services as u8);
+        buffer.put_u32_le(self.http_stack_size);
+        for text in [&self.ap_ssid, &self.ap_password] {
+            buffer.put_u8(text.len() as u8);
+            buffer.put_slice(text.as_bytes());
+        }
+        buffer.put_u8(self.channel);
+        buffer.put_slice(&self.rocket_mac);
+        // version 2
+        buffer.put_u8(self.api_token.len() as u8);
+        buffer.put_slice(self.api_token.as_bytes());
+
+        buffer
+    }
+
+    /// Read a config written by any version, checking every field.
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, StorageError> {
+        fn text(bytes: &mut &[u8]) -> Result<String, StorageError> {
+            let len = take(bytes, 1)?[0] as usize;
+            let text = take(bytes, len)?;
+            String::from_utf8(text.to_vec()).map_err(|_| StorageError::Malformed)
+        }
+
+        fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], StorageError> {
+            if bytes.len() < len {
+                return Err(StorageError::Malformed);
+            }
+            let (taken, rest) = bytes.split_at(len);
+            *bytes = rest;
+            Ok(taken)
+        }
+
+        let version = take(&mut bytes, 1)?[0];
+        if version == 0 {
+            return Err(StorageError::Malformed);
+        }
+
+        // version 1
+        let web_services = take(&mut bytes, 1)?[0] != 0;
+        let http_stack_size = take(&mut bytes, 4)?.get_u32_le();
+        let ap_ssid = text(&mut bytes)?;
+        let ap_password = text(&mut bytes)?;
+        let channel = take(&mut bytes, 1)?[0];
+        let rocket_mac = take(&mut bytes, 6)?.try_into().unwrap();
+
+        let mut config = Self {
+            web_services,
+            rocket_mac,
+            ..Self::default()
+        };
+
+        // the same checks as when editing
+        let checked = [
+            (ConfigKey::HttpStackSize, http_stack_size.to_string()),
+            (ConfigKey::ApSsid, ap_ssid),
+            (ConfigKey::ApPassword, ap_password),
+            (ConfigKey::Channel, channel.to_string()),
+        ];
+        for (key, value) in checked {
+            config
+                .set(key, &value)
+                .map_err(|_| StorageError::Malformed)?;
+        }
+
+        // fields added in later versions are read here when present, and
+        // otherwise keep their defaults
+        if version >= 2 {
+            let api_token = text(&mut bytes)?;
+            config
+                .set(ConfigKey::ApiToken, &api_token)
+                .map_err(|_| StorageError::Malformed)?;
+        }
+
+        Ok(config)
+    }
+
+    /// The saved config, if there is one.
+    pub fn load<S>(store: &S) -> Result<Option<Self>, StorageError>
+    where
+        S: KeyValueStore + ?Sized,
+    {
+        // leaves room for fields from newer versions
+        let mut buffer = [0_u8; 256];
+
+        match store.get(STORAGE_KEY, &mut buffer)? {
+            Some(len) => Self::from_bytes(&buffer[..len]).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save<S>(&self, store: &mut S) -> Result<(), StorageError>
+    where
+        S: KeyValueStore + ?Sized,
+    {
+        store.set(STORAGE_KEY, &self.to_bytes())
+    }
+
+    /// Run the arguments of a `config` console command, returning the
+    /// reply:
+    ///
+    /// - `config` lists every field
+    /// - `config get <key>` shows one
+    /// - `config set <key> <value>` changes one, the value running to the
+    ///   end of the line
+    /// - `config reset` goes back to the defaults
+    ///
+    /// The caller saves the config if it changed.
+    pub fn console(&mut self, args: &str) -> Result<String, ConfigError> {
+        let mut words = args.trim_start().splitn(3, ' ');
+
+        match (words.next(), words.next(), words.next()) {
+            (Some(""), None, None) => Ok(FIELDS
+                .iter()
+                .map(|field| format!("{} = {}", field.name, self.display(field)))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            (Some("get"), Some(name), None) => Ok(self.get(ConfigKey::from_name(name)?)),
+            (Some("set"), Some(name), value) => {
+                let key = ConfigKey::from_name(name)?;
+                self.set(key, value.unwrap_or(""))?;
+                Ok(format!("{} = {}", key.name(), self.get(key)))
+            }
+            (Some("reset"), None, None) => {
+                *self = Self::default();
+                Ok("config reset".to_string())
+            }
+            _ => Err(ConfigError::Usage),
+        }
+    }
+}
+
+/// Noise the altimeter's Kalman filter assumes, as variances in Pa².
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FilterNoise {
+    /// Of each pressure reading
+    pub measurement: f64,
+    /// Of the true pressure, between readings
+    pub process: f64,
+}
+
+impl Default for FilterNoise {
+    fn default() -> Self {
+        Self {
+            measurement: 0.16,
+            process: 0.025,
+        }
+    }
+}
+
+/// Flight computer settings, kept in NVS and changed from the basestation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FlightConfig {
+    pub filter_noise: FilterNoise,
+    /// In Pa
+    pub sea_level_pressure: f64,
+    /// Time between beeps, in ms
+    pub buzzer_period: u32,
+    /// Samples kept in memory for retransmission
+    pub recording_capacity: u32,
+    /// Most samples sent in each telemetry batch
+    pub batch_samples: u32,
+}
+
+impl Default for FlightConfig {
+    fn default() -> Self {
+        Self {
+            filter_noise: FilterNoise::default(),
+            sea_level_pressure: DEFAULT_SEA_LEVEL_PRESSURE,
+            buzzer_period: 100,
+            recording_capacity: 900,
+            batch_samples: 5,
+        }
+    }
+}
+
+impl FlightConfig {
+    pub fn get(&self, setting: FlightSetting) -> f64 {
+        match setting {
+            FlightSetting::MeasurementNoise => self.filter_noise.measurement,
+            FlightSetting::ProcessNoise => self.filter_noise.process,
+            FlightSetting::SeaLevelPressure => self.sea_level_pressure,
+            FlightSetting::BuzzerPeriod => self.buzzer_period as f64,
+            FlightSetting::RecordingCapacity => self.recording_capacity as f64,
+            FlightSetting::BatchSamples => self.batch_samples as f64,
+        }
+    }
+
+    /// Set `setting` to `value`, leaving it unchanged if the value is out
+    /// of range.
+    pub fn set(&mut self, setting: FlightSetting, value: f64) -> Result<(), ConfigError> {
+        // NaN is in no range
+        if !setting.range().contains(&value) || (setting.is_whole() && value.fract() != 0.0) {
+            return Err(ConfigError::OutOfRange(setting));
+        }
+
+        match setting {
+            FlightSetting::MeasurementNoise => self.filter_noise.measurement = value,
+            FlightSetting::ProcessNoise => self.filter_noise.process = value,
+            FlightSetting::SeaLevelPressure => self.sea_level_pressure = value,
+            FlightSetting::BuzzerPeriod => self.buzzer_period = value as u32,
+            FlightSetting::RecordingCapacity => self.recording_capacity = value as u32,
+            FlightSetting::BatchSamples => self.batch_samples = value as u32,
+        }
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(1 + 8 * FLIGHT_SETTINGS.len());
+
+        buffer.put_u8(FLIGHT_CONFIG_VERSION);
+        for setting in FLIGHT_SETTINGS {
+            buffer.put_f64_le(self.get(setting));
+        }
+
+        buffer
+    }
+
+    /// Read a config written by any version, checking every setting.
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, StorageError> {
+        if !bytes.has_remaining() {
+            return Err(StorageError::Malformed);
+        }
+
+        // older versions lack the settings added since, which keep their
+        // defaults, and newer ones append settings not known here
+        let stored = match bytes.get_u8() {
+            0 => return Err(StorageError::Malformed),
+            version => FLIGHT_SETTINGS_BY_VERSION
+                .get(version as usize - 1)
+                .copied()
+                .unwrap_or(FLIGHT_SETTINGS.len()),
+        };
+
+        let mut config = Self::default();
+        for setting in FLIGHT_SETTINGS.into_iter().take(stored) {
+            if bytes.remaining() < 8 {
+                return Err(StorageError::Malformed);
+            }
+            config
+                .set(setting, bytes.get_f64_le())
+                .map_err(|_| StorageError::Malformed)?;
+        }
+
+        Ok(config)
+    }
+
+    /// The saved config, if there is one.  Before there was a config only
+    /// the sea level pressure was saved, and that is carried over.
+    pub fn load<S>(store: &S) -> Result<Option<Self>, StorageError>
+    where
+        S: KeyValueStore + ?Sized,
+    {
+        let mut buffer = [0_u8; 128];
+
+        if let Some(len) = store.get(FLIGHT_STORAGE_KEY, &mut buffer)? {
+            return Self::from_bytes(&buffer[..len]).map(Some);
+        }
+
+        let mut buffer = [0_u8; 8];
+        match store.get(LEGACY_SEA_LEVEL_PRESSURE_KEY, &mut buffer)? {
+            Some(8) => {
+                let mut config = Self::default();
+                config
+                    .set(FlightSetting::SeaLevelPressure, f64::from_le_bytes(buffer))
+                    .map_err(|_| StorageError::Malformed)?;
+                Ok(Some(config))
+            }
+            Some(_) => Err(StorageError::Malformed),
+            None => Ok(None),
+        }
+    }
+
+    /// Save the config, replacing any legacy sea level pressure.
+    pub fn save<S>(&self, store: &mut S) -> Result<(), StorageError>
+    where
+        S: KeyValueStore + ?Sized,
+    {
+        store.set(FLIGHT_STORAGE_KEY, &self.to_bytes())?;
+        store.remove(LEGACY_SEA_LEVEL_PRESSURE_KEY)
+    }
+
+    /// Command for the arguments of a `rocket` console command:
+    ///
+    /// - `rocket get <setting>` reads a setting
+    /// - `rocket set <setting> <value>` changes one, if in range
+    /// - `rocket factory-reset` forgets them all
+    pub fn console_command(args: &str) -> Result<Command, ConfigError> {
+        let mut words = args.split_whitespace();
+
+        match (words.next(), words.next(), words.next(), words.next()) {
+            (Some("get"), Some(name), None, None) => Ok(Command::GetConfig(
+                FlightSetting::from_name(name).ok_or(ConfigError::UnknownSetting)?,
+            )),
+            (Some("set"), Some(name), Some(value), None) => {
+                let setting = FlightSetting::from_name(name).ok_or(ConfigError::UnknownSetting)?;
+                // parsed as sent, so it is checked as the rocket will check it
+                let value = value
+                    .parse::<f32>()
+                    .map_err(|_| ConfigError::OutOfRange(setting))?;
+                // checked here too, to answer without waiting on the rocket
+                Self::default().set(setting, value as f64)?;
+                Ok(Command::SetConfig(setting, value))
+            }
+            (Some("factory-reset"), None, None, None) => Ok(Command::FactoryReset),
+            _ => Err(ConfigError::RocketUsage),
+        }
+    }
+
+    /// Forget the saved config, so the defaults are used from now on.
+    pub fn factory_reset<S>(store: &mut S) -> Result<(), StorageError>
+    where
+        S: KeyValueStore + ?Sized,
+    {
+        store.remove(FLIGHT_STORAGE_KEY)?;
+        store.remove(LEGACY_SEA_LEVEL_PRESSURE_KEY)
+    }
+}
+
+/// The sea level pressure last entered on the basestation, in Pa, if one
+/// was saved by [`save_sea_level_pressure`].
+pub fn load_sea_level_pressure<S>(store: &S) -> Result<Option<f64>, StorageError>
+where
+    S: KeyValueStore + ?Sized,
+{
+    let mut buffer = [0_u8; 8];
+
+    match store.get(BASESTATION_SEA_LEVEL_PRESSURE_KEY, &mut buffer)? {
+        Some(8) => {
+            let pascals = f64::from_le_bytes(buffer);
+            FlightConfig::default()
+                .set(FlightSetting::SeaLevelPressure, pascals)
+                .map_err(|_| StorageError::Malformed)?;
+            Ok(Some(pascals))
+        }
+        Some(_) => Err(StorageError::Malformed),
+        None => Ok(None),
+    }
+}
+
+pub fn save_sea_level_pressure<S>(store: &mut S, pascals: f64) -> Result<(), StorageError>
+where
+    S: KeyValueStore + ?Sized,
+{
+    store.set(BASESTATION_SEA_LEVEL_PRESSURE_KEY, &pascals.to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStore;
+
+    #[test]
+    fn saves_and_loads() {
+        let mut store = MemoryStore::new();
+        assert_eq!(BasestationConfig::load(&store).unwrap(), None);
+
+        let mut config = BasestationConfig::default();
+        config.set(ConfigKey::ApSsid, "ground control").unwrap();
+        config.set(ConfigKey::ApPassword, "").unwrap();
+        config
+            .set(ConfigKey::RocketMac, "24:0a:c4:00:01:10")
+            .unwrap();
+        config.save(&mut store).unwrap();
+
+        let loaded = BasestationConfig::load(&store).unwrap().unwrap();
+        assert_eq!(loaded, config);
+        assert_eq!(loaded.get(ConfigKey::RocketMac), "24:0A:C4:00:01:10");
+    }
+
+    #[test]
+    fn reads_other_versions() {
+        let config = BasestationConfig {
+            channel: 6,
+            ..BasestationConfig::default()
+        };
+
+        // a newer version's fields are skipped
+        let mut bytes = config.to_bytes();
+        bytes[0] = CONFIG_VERSION + 1;
+        bytes.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(BasestationConfig::from_bytes(&bytes).unwrap(), config);
+
+        // an older version's are missing, and keep their defaults
+        let with_token = BasestationConfig {
+            api_token: "mission control".to_string(),
+            ..config.clone()
+        };
+        let mut bytes = with_token.to_bytes();
+        bytes[0] = 1;
+        bytes.truncate(bytes.len() - 1 - with_token.api_token.len());
+        assert_eq!(BasestationConfig::from_bytes(&bytes).unwrap(), config);
+
+        // but what this version needs must be there, and valid
+        let bytes = config.to_bytes();
+        assert!(matches!(
+            BasestationConfig::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(StorageError::Malformed)
+        ));
+        let mut bytes = config.to_bytes();
+        // before the MAC and the empty token
+        let channel = bytes.len() - 8;
+        bytes[channel] = 99;
+        assert!(matches!(
+            BasestationConfig::from_bytes(&bytes),
+            Err(StorageError::Malformed)
+        ));
+        assert!(BasestationConfig::from_bytes(&[0]).is_err());
+    }
+
+    #[test]
+    fn checks_values() {
+        let mut config = BasestationConfig::default();
+        let invalid = |key: ConfigKey| Err(ConfigError::Invalid(key.field()));
+
+        for (key, value) in [
+            (ConfigKey::Channel, "14"),
+            (ConfigKey::ApPassword, "short"),
+            (ConfigKey::ApSsid, ""),
+            (ConfigKey::RocketMac, "D4:D4:DA:AA:27"),
+            (ConfigKey::WebServices, "maybe"),
+        ] {
+            assert_eq!(config.set(key, value), invalid(key));
+        }
+        assert_eq!(config, BasestationConfig::default());
+
+        assert_eq!(
+            invalid(ConfigKey::Channel).unwrap_err().describe(),
+            "channel must be 1 to 13"
+        );
+        assert_eq!(
+            config.display(ConfigKey::ApPassword.field()),
+            "************"
+        );
+    }
+
+    #[test]
+    fn names_keys() {
+        for field in FIELDS.iter() {
+            assert_eq!(field.key.field(), field);
+            assert_eq!(ConfigKey::from_name(field.name), Ok(field.key));
+        }
+        assert_eq!(ConfigKey::from_name("volume"), Err(ConfigError::UnknownKey));
+    }
+
+    #[test]
+    fn runs_console_commands() {
+        let mut config = BasestationConfig::default();
+
+        assert!(config.console("").unwrap().starts_with("web = off\n"));
+        assert_eq!(
+            config.console("set password hello world").unwrap(),
+            "password = hello world"
+        );
+        assert_eq!(config.console("get channel").unwrap(), "1");
+        assert_eq!(
+            config.console("set web"),
+            Err(ConfigError::Invalid(&FIELDS[0]))
+        );
+        assert_eq!(config.console("frobnicate"), Err(ConfigError::Usage));
+        assert_eq!(
+            config.console("set volume 11"),
+            Err(ConfigError::UnknownKey)
+        );
+        assert_eq!(config.console("get volume"), Err(ConfigError::UnknownKey));
+
+        config.console("reset").unwrap();
+        assert_eq!(config, BasestationConfig::default());
+    }
+
+    #[test]
+    fn keeps_flight_config() {
+        let mut store = MemoryStore::new();
+        assert_eq!(FlightConfig::load(&store).unwrap(), None);
+
+        // carried over from before there was a config
+        store
+            .set(LEGACY_SEA_LEVEL_PRESSURE_KEY, &101_325.0_f64.to_le_bytes())
+            .unwrap();
+        let mut config = FlightConfig::load(&store).unwrap().unwrap();
+        assert_eq!(config.sea_level_pressure, 101_325.0);
+
+        config.set(FlightSetting::BuzzerPeriod, 250.0).unwrap();
+        config.save(&mut store).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(FlightConfig::load(&store).unwrap(), Some(config));
+
+        FlightConfig::factory_reset(&mut store).unwrap();
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn reads_other_flight_config_versions() {
+        let mut config = FlightConfig::default();
+        config.set(FlightSetting::BuzzerPeriod, 250.0).unwrap();
+        assert_eq!(
+            FLIGHT_SETTINGS_BY_VERSION[FLIGHT_CONFIG_VERSION as usize - 1],
+            FLIGHT_SETTINGS.len()
+        );
+
+        // each older version keeps defaults for what it lacks
+        for (version, stored) in (1..).zip(FLIGHT_SETTINGS_BY_VERSION) {
+            let mut bytes = config.to_bytes();
+            bytes[0] = version;
+            bytes.truncate(1 + 8 * stored);
+
+            let mut expected = FlightConfig::default();
+            for setting in FLIGHT_SETTINGS.into_iter().take(stored) {
+                expected.set(setting, config.get(setting)).unwrap();
+            }
+            assert_eq!(FlightConfig::from_bytes(&bytes).unwrap(), expected);
+        }
+
+        // a newer version's settings are skipped
+        let mut bytes = config.to_bytes();
+        bytes[0] = FLIGHT_CONFIG_VERSION + 1;
+        bytes.extend_from_slice(&1.0_f64.to_le_bytes());
+        assert_eq!(FlightConfig::from_bytes(&bytes).unwrap(), config);
+
+        // but what a version has must be there
+        let bytes = config.to_bytes();
+        assert!(FlightConfig::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(FlightConfig::from_bytes(&[0]).is_err());
+        assert!(FlightConfig::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn checks_flight_settings() {
+        let mut config = FlightConfig::default();
+
+        for (setting, value) in [
+            (FlightSetting::SeaLevelPressure, 30.01),
+            (FlightSetting::RecordingCapacity, 900.5),
+            (FlightSetting::BatchSamples, 0.0),
+            (FlightSetting::MeasurementNoise, f64::NAN),
+        ] {
+            assert_eq!(
+                config.set(setting, value),
+                Err(ConfigError::OutOfRange(setting))
+            );
+        }
+        assert_eq!(config, FlightConfig::default());
+
+        for setting in FLIGHT_SETTINGS {
+            assert_eq!(FlightSetting::from_u8(setting as u8), Some(setting));
+            assert_eq!(FlightSetting::from_name(setting.name()), Some(setting));
+        }
+        assert_eq!(
+            ConfigError::OutOfRange(FlightSetting::BuzzerPeriod).describe(),
+            "buzzer_period must be 60 to 10000"
+        );
+
+        // a newer version's settings are skipped, but these must be valid
+        let mut bytes = config.to_bytes();
+        bytes[0] = FLIGHT_CONFIG_VERSION + 1;
+        bytes.extend_from_slice(&[0; 8]);
+        assert_eq!(FlightConfig::from_bytes(&bytes).unwrap(), config);
+        bytes[1..9].copy_from_slice(&(-1.0_f64).to_le_bytes());
+        assert!(FlightConfig::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn keeps_basestation_sea_level_pressure() {
+        let mut store = MemoryStore::new();
+        assert_eq!(load_sea_level_pressure(&store).unwrap(), None);
+
+        save_sea_level_pressure(&mut store, 101_000.0).unwrap();
+        assert_eq!(load_sea_level_pressure(&store).unwrap(), Some(101_000.0));
+
+        save_sea_level_pressure(&mut store, -5.0).unwrap();
+        assert!(matches!(
+            load_sea_level_pressure(&store),
+            Err(StorageError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn parses_rocket_commands() {
+        assert_eq!(
+            FlightConfig::console_command(" get batch"),
+            Ok(Command::GetConfig(FlightSetting::BatchSamples))
+        );
+        assert_eq!(
+            FlightConfig::console_command("set buzzer_period 500"),
+            Ok(Command::SetConfig(FlightSetting::BuzzerPeriod, 500.0))
+        );
+        assert_eq!(
+            FlightConfig::console_command("set buzzer_period loud"),
+            Err(ConfigError::OutOfRange(FlightSetting::BuzzerPeriod))
+        );
+        assert_eq!(
+            FlightConfig::console_command("get volume"),
+            Err(ConfigError::UnknownSetting)
+        );
+        assert_eq!(
+            FlightConfig::console_command("factory-reset"),
+            Ok(Command::FactoryReset)
+        );
+        assert_eq!(
+            FlightConfig::console_command("set psl"),
+            Err(ConfigError::RocketUsage)
+        );
+    }
+
+    #[test]
+    fn checks_rocket_settings_as_sent() {
+        for setting in FLIGHT_SETTINGS {
+            let range = setting.range();
+            for bound in [range.start(), range.end()] {
+                // whatever the console sends, the rocket accepts
+                if let Ok(Command::SetConfig(sent, value)) =
+                    FlightConfig::console_command(&format!("set {} {}", setting.name(), bound))
+                {
+                    assert_eq!(sent, setting);
+                    assert!(
+                        FlightConfig::default().set(setting, value as f64).is_ok(),
+                        "{} {}",
+                        setting.name(),
+                        bound
+                    );
+                }
+            }
+        }
+
+        // 0.0001 as an f32 is just below the least process noise
+        assert_eq!(
+            FlightConfig::console_command("set process_noise 0.0001"),
+            Err(ConfigError::OutOfRange(FlightSetting::ProcessNoise))
+        );
+        assert!(FlightConfig::console_command("set process_noise 0.00011").is_ok());
+    }
+}
@@ -0,0 +1,696 @@
+use std::collections::VecDeque;
+
+use rocket_protocol::serial::SerialLine;
+
+use crate::{
+    command::{valid_sea_level_pressure, Command},
+    config::{FlightConfig, FIELDS, FLIGHT_SETTINGS},
+    control_panel::{command_safety, safety_of, CommandSafety},
+};
+
+const PROMPT: &str = "> ";
+/// Lines kept for recall with the up and down arrows.
+const HISTORY_SIZE: usize = 16;
+/// Longest line, in characters; anything typed past it is refused.
+const MAX_LINE: usize = 256;
+const BELL: char = '\u{7}';
+
+const ESC: u8 = 0x1B;
+const CTRL_A: u8 = 0x01;
+const CTRL_C: u8 = 0x03;
+const CTRL_E: u8 = 0x05;
+const CTRL_U: u8 = 0x15;
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7F;
+const TAB: u8 = b'\t';
+
+/// How the console talks to whoever is on the other end of the UART.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConsoleMode {
+    /// Echo, a prompt, line editing and plain text replies, for a terminal
+    Human,
+    /// No echo or prompt, and a [`SerialLine`] for each reply, event and
+    /// frame received, for a program such as `rocket-ground`
+    Machine,
+}
+
+pub struct CommandHelp {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+}
+
+const fn help(name: &'static str, usage: &'static str, help: &'static str) -> CommandHelp {
+    CommandHelp { name, usage, help }
+}
+
+pub const COMMANDS: [CommandHelp; 12] = [
+    help("help", "help [command]", "list commands, or describe one"),
+    help(
+        "mode",
+        "mode human|machine",
+        "switch to JSON lines with telemetry frames and no echo, or back",
+    ),
+    help(
+        "config",
+        "config [get <key> | set <key> <value> | reset]",
+        "basestation settings, applied after a restart",
+    ),
+    help(
+        "rocket",
+        "rocket [get <setting> | set <setting> <value> confirm | factory-reset confirm]",
+        "flight computer settings",
+    ),
+    help(
+        "psl",
+        "psl <pascals>",
+        "set the rocket's sea level pressure",
+    ),
+    help("ton", "ton", "start streaming telemetry"),
+    help("toff", "toff", "stop streaming telemetry"),
+    help("tone", "tone", "sound the rocket's buzzer"),
+    help("reset", "reset confirm", "reset the maximum altitude"),
+    help("erase", "erase confirm", "erase the flight log"),
+    help("arm", "arm confirm", "arm the rocket"),
+    help("send", "send <text>", "send any text command to the rocket"),
+];
+
+/// What a console line asks for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConsoleCommand<'a> {
+    Empty,
+    /// Reply with this text
+    Help(String),
+    Mode(ConsoleMode),
+    /// Arguments of a `config` command, for [`BasestationConfig::console`]
+    ///
+    /// [`BasestationConfig::console`]: crate::config::BasestationConfig::console
+    Config(&'a str),
+    /// Send to the rocket, as the touch UI would
+    Send(Command),
+}
+
+/// Read a console line.  Errors are the reply to show.
+pub fn parse(line: &str) -> Result<ConsoleCommand<'_>, String> {
+    let line = line.trim();
+    let (name, args) = line.split_once(' ').unwrap_or((line, ""));
+    let args = args.trim();
+
+    let usage = || {
+        let command = COMMANDS.iter().find(|command| command.name == name);
+        format!("usage: {}", command.map_or("", |command| command.usage))
+    };
+
+    match name {
+        "" => Ok(ConsoleCommand::Empty),
+        "help" if args.is_empty() => Ok(ConsoleCommand::Help(
+            COMMANDS
+                .iter()
+                .map(|command| format!("{:<8}{}", command.name, command.help))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )),
+        "help" => COMMANDS
+            .iter()
+            .find(|command| command.name == args)
+            .map(|command| ConsoleCommand::Help(format!("{}\n{}", command.usage, command.help)))
+            .ok_or_else(|| format!("no command {}", args)),
+        "mode" => match args {
+            "human" => Ok(ConsoleCommand::Mode(ConsoleMode::Human)),
+            "machine" => Ok(ConsoleCommand::Mode(ConsoleMode::Machine)),
+            _ => Err(usage()),
+        },
+        "config" => Ok(ConsoleCommand::Config(args)),
+        "rocket" => {
+            // changes need confirming, as the touch UI's guarded commands do
+            let (args, confirmed) = match args.strip_suffix("confirm") {
+                Some(rest) if rest.is_empty() || rest.ends_with(' ') => (rest.trim_end(), true),
+                _ => (args, false),
+            };
+            let command = FlightConfig::console_command(args).map_err(|e| e.describe())?;
+            if (safety_of(&command) != CommandSafety::Safe) == confirmed {
+                Ok(ConsoleCommand::Send(command))
+            } else {
+                Err(usage())
+            }
+        }
+        "psl" => args
+            .parse::<f32>()
+            .ok()
+            .filter(|pascals| valid_sea_level_pressure(*pascals))
+            .map(|pascals| ConsoleCommand::Send(Command::SetSeaLevelPressure(pascals)))
+            .ok_or_else(usage),
+        "send" if !args.is_empty() => {
+            let command = Command::Text(args.to_string());
+            match safety_of(&command) {
+                CommandSafety::Safe => Ok(ConsoleCommand::Send(command)),
+                // not around the confirmation the command itself asks for
+                _ => Err(format!("use {} confirm", args.to_ascii_lowercase())),
+            }
+        }
+        "ton" | "toff" | "tone" | "reset" | "erase" | "arm" => {
+            // as a tap on the touch UI would ask
+            let confirmed = match command_safety(name) {
+                CommandSafety::Safe => args.is_empty(),
+                _ => args == "confirm",
+            };
+            if confirmed {
+                Ok(ConsoleCommand::Send(Command::Text(name.to_string())))
+            } else {
+                Err(usage())
+            }
+        }
+        "send" => Err(usage()),
+        _ => Err(format!("unknown command {}, try help", name)),
+    }
+}
+
+/// Words that could complete the last word of `line`.
+pub fn completions(line: &str) -> Vec<&'static str> {
+    let mut words: Vec<&str> = line.split(' ').collect();
+    let partial = words.pop().unwrap_or_default();
+
+    let candidates: Vec<&'static str> = match words.as_slice() {
+        [] => COMMANDS.iter().map(|command| command.name).collect(),
+        ["help"] => COMMANDS.iter().map(|command| command.name).collect(),
+        ["mode"] => vec!["human", "machine"],
+        ["config"] => vec!["get", "set", "reset"],
+        ["config", "get" | "set"] => FIELDS.iter().map(|field| field.name).collect(),
+        ["rocket"] => vec!["get", "set", "factory-reset"],
+        ["rocket", "get" | "set"] => FLIGHT_SETTINGS
+            .iter()
+            .map(|setting| setting.name())
+            .collect(),
+        ["rocket", "set", _, _] | ["rocket", "factory-reset"] => vec!["confirm"],
+        [name] if command_safety(name) != CommandSafety::Safe => vec!["confirm"],
+        _ => Vec::new(),
+    };
+
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(partial))
+        .collect()
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Escape {
+    None,
+    /// After ESC
+    Started,
+    /// After ESC [, with any number so far
+    Sequence(u8),
+}
+
+/// Line editor and reply formatting for the UART console.
+///
+/// Bytes from the UART go to [`Self::feed`], which returns what to echo
+/// and any lines entered.  Replies and events go out through
+/// [`Self::reply`] and [`Self::event`], which keep the line being typed
+/// intact around them.
+pub struct Console {
+    mode: ConsoleMode,
+    line: Vec<char>,
+    cursor: usize,
+    history: VecDeque<String>,
+    /// Position while recalling history, from 0 for the latest line
+    recalled: Option<usize>,
+    /// Bytes of an incomplete UTF-8 character
+    partial: Vec<u8>,
+    escape: Escape,
+    /// The previous byte ended a line with `\r`, so a following `\n` is
+    /// part of the same line end
+    after_cr: bool,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            mode: ConsoleMode::Human,
+            line: Vec::new(),
+            cursor: 0,
+            history: VecDeque::with_capacity(HISTORY_SIZE),
+            recalled: None,
+            partial: Vec::new(),
+            escape: Escape::None,
+            after_cr: false,
+        }
+    }
+
+    pub fn mode(&self) -> ConsoleMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: ConsoleMode) {
+        self.mode = mode;
+    }
+
+    /// Greeting and first prompt.
+    pub fn banner(&self) -> String {
+        match self.mode {
+            ConsoleMode::Human => format!("basestation console, try help\r\n{}", PROMPT),
+            ConsoleMode::Machine => String::new(),
+        }
+    }
+
+    /// Take bytes from the UART, returning what to echo and the lines
+    /// entered.  Bytes that are not UTF-8 are decoded lossily, each invalid
+    /// sequence becoming U+FFFD.
+    pub fn feed(&mut self, bytes: &[u8]) -> (String, Vec<String>) {
+        let mut echo = String::new();
+        let mut lines = Vec::new();
+
+        for &byte in bytes {
+            if let Some(line) = self.byte(byte, &mut echo) {
+                lines.push(line);
+            }
+        }
+
+        if self.mode == ConsoleMode::Machine {
+            echo.clear();
+        }
+        (echo, lines)
+    }
+
+    fn byte(&mut self, byte: u8, echo: &mut String) -> Option<String> {
+        let after_cr = std::mem::replace(&mut self.after_cr, false);
+
+        match (self.escape, byte) {
+            (Escape::Started, b'[') => {
+                self.escape = Escape::Sequence(0);
+                return None;
+            }
+            (Escape::Sequence(number), b'0'..=b'9') => {
+                self.escape =
+                    Escape::Sequence(number.saturating_mul(10).saturating_add(byte - b'0'));
+                return None;
+            }
+            (Escape::Sequence(number), _) => {
+                self.escape = Escape::None;
+                self.escape_sequence(number, byte, echo);
+                return None;
+            }
+            (Escape::Started, _) => self.escape = Escape::None,
+            (Escape::None, _) => (),
+        }
+
+        match byte {
+            ESC => self.escape = Escape::Started,
+            b'\n' if after_cr => (),
+            b'\r' | b'\n' => {
+                self.after_cr = byte == b'\r';
+                return Some(self.enter(echo));
+            }
+            BACKSPACE | DELETE if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.line.remove(self.cursor);
+                echo.push('\u{8}');
+                self.redraw_tail(echo, 1);
+            }
+            CTRL_A => self.move_to(0, echo),
+            CTRL_E => self.move_to(self.line.len(), echo),
+            CTRL_C => {
+                echo.push_str("^C\r\n");
+                echo.push_str(PROMPT);
+                self.set_line(String::new());
+            }
+            CTRL_U => self.replace_line(String::new(), echo),
+            TAB => self.complete(echo),
+            byte if byte < 0x20 => (),
+            byte => {
+                self.partial.push(byte);
+                // holds at most one character, so any error is at the start
+                while !self.partial.is_empty() {
+                    match std::str::from_utf8(&self.partial) {
+                        Ok(text) => {
+                            let c = text.chars().next().unwrap();
+                            self.partial.clear();
+                            self.insert(c, echo);
+                        }
+                        // what follows the invalid bytes may start a character
+                        Err(e) => match e.error_len() {
+                            Some(len) => {
+                                self.partial.drain(..len);
+                                self.insert(char::REPLACEMENT_CHARACTER, echo);
+                            }
+                            None => break,
+                        },
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn escape_sequence(&mut self, number: u8, last: u8, echo: &mut String) {
+        match (last, number) {
+            (b'A', _) => self.recall(true, echo),
+            (b'B', _) => self.recall(false, echo),
+            (b'C', _) if self.cursor < self.line.len() => {
+                self.cursor += 1;
+                echo.push_str("\x1b[C");
+            }
+            (b'D', _) if self.cursor > 0 => {
+                self.cursor -= 1;
+                echo.push_str("\x1b[D");
+            }
+            (b'H', _) | (b'~', 1 | 7) => self.move_to(0, echo),
+            (b'F', _) | (b'~', 4 | 8) => self.move_to(self.line.len(), echo),
+            (b'~', 3) if self.cursor < self.line.len() => {
+                self.line.remove(self.cursor);
+                self.redraw_tail(echo, 1);
+            }
+            _ => (),
+        }
+    }
+
+    fn enter(&mut self, echo: &mut String) -> String {
+        let line: String = self.line.iter().collect();
+        echo.push_str("\r\n");
+
+        let repeated = self.history.front().is_some_and(|last| *last == line);
+        if !line.trim().is_empty() && !repeated {
+            if self.history.len() == HISTORY_SIZE {
+                self.history.pop_back();
+            }
+            self.history.push_front(line.clone());
+        }
+
+        self.set_line(String::new());
+        line
+    }
+
+    fn insert(&mut self, c: char, echo: &mut String) {
+        if self.line.len() >= MAX_LINE {
+            echo.push(BELL);
+            return;
+        }
+
+        self.line.insert(self.cursor, c);
+        self.cursor += 1;
+        echo.push(c);
+        self.redraw_tail(echo, 0);
+    }
+
+    /// Rewrite the line after the cursor, blanking `erased` characters
+    /// beyond its end, and put the cursor back.
+    fn redraw_tail(&self, echo: &mut String, erased: usize) {
+        let tail: String = self.line[self.cursor..].iter().collect();
+        echo.push_str(&tail);
+        echo.push_str(&" ".repeat(erased));
+        let back = tail.chars().count() + erased;
+        if back > 0 {
+            echo.push_str(&format!("\x1b[{}D", back));
+        }
+    }
+
+    fn move_to(&mut self, cursor: usize, echo: &mut String) {
+        if cursor < self.cursor {
+            echo.push_str(&format!("\x1b[{}D", self.cursor - cursor));
+        } else if cursor > self.cursor {
+            echo.push_str(&format!("\x1b[{}C", cursor - self.cursor));
+        }
+        self.cursor = cursor;
+    }
+
+    fn set_line(&mut self, line: String) {
+        self.line = line.chars().collect();
+        self.cursor = self.line.len();
+        self.recalled = None;
+    }
+
+    /// Replace the whole line, redrawing it from the prompt.
+    fn replace_line(&mut self, line: String, echo: &mut String) {
+        self.set_line(line);
+        echo.push_str("\r\x1b[K");
+        echo.push_str(PROMPT);
+        echo.extend(self.line.iter());
+    }
+
+    fn recall(&mut self, older: bool, echo: &mut String) {
+        let recalled = match (self.recalled, older) {
+            (None, true) if !self.history.is_empty() => Some(0),
+            (Some(i), true) if i + 1 < self.history.len() => Some(i + 1),
+            (Some(i), true) => Some(i),
+            (Some(0), false) => None,
+            (Some(i), false) => Some(i - 1),
+            (None, _) => return,
+        };
+
+        let line = recalled.map_or(String::new(), |i| self.history[i].clone());
+        self.replace_line(line, echo);
+        self.recalled = recalled;
+    }
+
+    /// Complete the word before the cursor as far as it is unambiguous,
+    /// listing the choices if there are several.
+    fn complete(&mut self, echo: &mut String) {
+        let before: String = self.line[..self.cursor].iter().collect();
+        let candidates = completions(&before);
+        let partial = before.rsplit(' ').next().unwrap_or_default().len();
+
+        let Some(first) = candidates.first() else {
+            return;
+        };
+        let common = candidates.iter().fold(first.len(), |common, candidate| {
+            common.min(
+                first
+                    .bytes()
+                    .zip(candidate.bytes())
+                    .take_while(|(a, b)| a == b)
+                    .count(),
+            )
+        });
+
+        for c in first[partial..common].chars() {
+            self.insert(c, echo);
+        }
+        if candidates.len() == 1 {
+            self.insert(' ', echo);
+        } else if common == partial {
+            let line: String = self.line.iter().collect();
+            echo.push_str("\r\n");
+            echo.push_str(&candidates.join("  "));
+            echo.push_str("\r\n");
+            echo.push_str(PROMPT);
+            echo.push_str(&line);
+            let back = self.line.len() - self.cursor;
+            if back > 0 {
+                echo.push_str(&format!("\x1b[{}D", back));
+            }
+        }
+    }
+
+    /// Output for the result of a line, followed by the next prompt.
+    pub fn reply(&self, result: Result<String, String>) -> String {
+        match self.mode {
+            ConsoleMode::Human => {
+                let text = match result {
+                    Ok(text) => text,
+                    Err(e) => format!("error: {}", e),
+                };
+                let mut output = text.replace('\n', "\r\n");
+                if !output.is_empty() {
+                    output.push_str("\r\n");
+                }
+                output.push_str(PROMPT);
+                output
+            }
+            ConsoleMode::Machine => SerialLine::Reply(result).to_line(),
+        }
+    }
+
+    /// Output for news that arrives between lines, such as an ack from the
+    /// rocket, redrawing the line being typed after it.
+    pub fn event(&self, text: &str) -> String {
+        match self.mode {
+            ConsoleMode::Human => {
+                let mut output = format!("\r\x1b[K{}\r\n{}", text.replace('\n', "\r\n"), PROMPT);
+                output.extend(self.line.iter());
+                let back = self.line.len() - self.cursor;
+                if back > 0 {
+                    output.push_str(&format!("\x1b[{}D", back));
+                }
+                output
+            }
+            ConsoleMode::Machine => SerialLine::Event(text.to_string()).to_line(),
+        }
+    }
+
+    /// Output for a telemetry or link status frame, which only programs
+    /// are sent.
+    pub fn frame(&self, frame: &[u8]) -> Option<String> {
+        match self.mode {
+            ConsoleMode::Human => None,
+            ConsoleMode::Machine => Some(SerialLine::Frame(frame.to_vec()).to_line()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FlightSetting;
+
+    fn type_in(console: &mut Console, text: &str) -> (String, Vec<String>) {
+        console.feed(text.as_bytes())
+    }
+
+    #[test]
+    fn edits_lines() {
+        let mut console = Console::new();
+
+        // typed, then fixed up with the arrows and backspace
+        let (echo, lines) = type_in(&mut console, "tne\x1b[D\x1b[Do\x1b[F\x7f\x7fe");
+        assert!(lines.is_empty());
+        assert!(echo.starts_with("tne\x1b[D\x1b[Done\x1b[2D"));
+        assert_eq!(type_in(&mut console, "\r\n").1, ["toe"]);
+
+        // \r\n is one line end, and bytes that are not UTF-8 are replaced
+        let (_, lines) = console.feed(b"ton\r\n\xff\xfe\rtoff\n\xe2\x82ok\n");
+        assert_eq!(lines, ["ton", "\u{fffd}\u{fffd}", "toff", "\u{fffd}ok"]);
+
+        // Ctrl-C abandons the line
+        assert_eq!(type_in(&mut console, "arm\x03\r").1, [""]);
+
+        // °C is two bytes
+        let (echo, lines) = console.feed("send 5°C\r".as_bytes());
+        assert!(echo.contains("5°C"));
+        assert_eq!(lines, ["send 5°C"]);
+
+        // long lines stop growing
+        let (echo, lines) = type_in(&mut console, &format!("{}\r", "x".repeat(MAX_LINE + 4)));
+        assert_eq!(echo.matches(BELL).count(), 4);
+        assert_eq!(lines, ["x".repeat(MAX_LINE)]);
+    }
+
+    #[test]
+    fn recalls_history() {
+        let mut console = Console::new();
+        type_in(&mut console, "ton\rtone\rtone\r");
+
+        type_in(&mut console, "\x1b[A");
+        assert_eq!(console.line.iter().collect::<String>(), "tone");
+        let (echo, _) = type_in(&mut console, "\x1b[A");
+        assert_eq!(echo, "\r\x1b[K> ton");
+        // no older lines, then back down to an empty line
+        type_in(&mut console, "\x1b[A\x1b[B\x1b[B");
+        assert!(console.line.is_empty());
+
+        let (_, lines) = type_in(&mut console, "\x1b[A\x1b[A\r");
+        assert_eq!(lines, ["ton"]);
+    }
+
+    #[test]
+    fn completes_words() {
+        let mut console = Console::new();
+
+        assert_eq!(type_in(&mut console, "ro\t").0, "rocket ");
+        assert_eq!(type_in(&mut console, "s\t").0, "set ");
+        type_in(&mut console, "buz\t");
+        assert_eq!(
+            console.line.iter().collect::<String>(),
+            "rocket set buzzer_period "
+        );
+        console.feed(b"\r");
+
+        // ambiguous, so the choices are listed
+        let (echo, _) = type_in(&mut console, "to\t");
+        assert_eq!(echo, "to\r\nton  toff  tone\r\n> to");
+        assert_eq!(completions("to"), ["ton", "toff", "tone"]);
+        assert_eq!(completions("reset "), ["confirm"]);
+        assert_eq!(completions("rocket set batch 2 c"), ["confirm"]);
+        assert_eq!(completions("rocket factory-reset "), ["confirm"]);
+        assert!(completions("ton ").is_empty());
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            parse("psl 101325"),
+            Ok(ConsoleCommand::Send(Command::SetSeaLevelPressure(
+                101_325.0
+            )))
+        );
+        assert_eq!(
+            parse(" rocket get batch "),
+            Ok(ConsoleCommand::Send(Command::GetConfig(
+                FlightSetting::BatchSamples
+            )))
+        );
+        assert_eq!(
+            parse("config get ssid"),
+            Ok(ConsoleCommand::Config("get ssid"))
+        );
+        assert_eq!(
+            parse("ton"),
+            Ok(ConsoleCommand::Send(Command::Text("ton".to_string())))
+        );
+        assert_eq!(
+            parse("send re_tx 4"),
+            Ok(ConsoleCommand::Send(Command::Text("re_tx 4".to_string())))
+        );
+
+        // destructive commands need confirming, as on the touch UI
+        assert_eq!(parse("reset"), Err("usage: reset confirm".to_string()));
+        assert!(parse("reset confirm").is_ok());
+        assert!(parse("rocket factory-reset").is_err());
+        assert_eq!(
+            parse("rocket factory-reset confirm"),
+            Ok(ConsoleCommand::Send(Command::FactoryReset))
+        );
+        assert!(parse("rocket set batch 2").is_err());
+        assert_eq!(
+            parse("rocket set batch 2 confirm"),
+            Ok(ConsoleCommand::Send(Command::SetConfig(
+                FlightSetting::BatchSamples,
+                2.0
+            )))
+        );
+        assert!(parse("rocket get batch confirm").is_err());
+        assert!(parse("psl 30.01").is_err());
+        // nor can send get around that
+        assert_eq!(parse("send RESET"), Err("use reset confirm".to_string()));
+        assert!(parse("send arm").is_err());
+        assert!(parse("launch").is_err());
+        assert!(
+            matches!(parse("help psl"), Ok(ConsoleCommand::Help(text)) if text.starts_with("psl <pascals>"))
+        );
+    }
+
+    #[test]
+    fn formats_for_machines() {
+        let mut console = Console::new();
+        console.set_mode(ConsoleMode::Machine);
+
+        let (echo, lines) = type_in(&mut console, "tone\r\n");
+        assert!(echo.is_empty());
+        assert_eq!(lines, ["tone"]);
+
+        assert_eq!(
+            console.reply(Ok("a\nb".to_string())),
+            "{\"ok\":true,\"reply\":\"a\\u000ab\"}\r\n"
+        );
+        assert_eq!(
+            console.reply(Err("no".to_string())),
+            "{\"ok\":false,\"error\":\"no\"}\r\n"
+        );
+        assert_eq!(console.event("acked"), "{\"event\":\"acked\"}\r\n");
+        assert_eq!(
+            console.frame(&[0xD0, 0x01]).as_deref(),
+            Some("{\"frame\":\"d001\"}\r\n")
+        );
+
+        console.set_mode(ConsoleMode::Human);
+        type_in(&mut console, "to");
+        assert_eq!(console.event("acked"), "\r\x1b[Kacked\r\n> to");
+        assert_eq!(console.frame(&[0xD0, 0x01]), None);
+        assert_eq!(console.reply(Err("no".to_string())), "error: no\r\n> ");
+    }
+}
@@ -0,0 +1,292 @@
+use std::{fmt::Debug, sync::mpsc::Sender};
+
+use embedded_graphics::{draw_target::DrawTarget, pixelcolor::Rgb565, primitives::Rectangle};
+
+use crate::{
+    command::Command,
+    ui::{
+        button::Button,
+        dialog::ConfirmDialog,
+        layout::{column, row, Insets},
+        screen::Navigation,
+        ui::{Ui, UiDimension},
+    },
+};
+
+const BUTTON_SIZE: i16 = 25;
+
+/// Called when a button is tapped.
+type Action = Box<dyn Fn()>;
+
+/// How much care a command needs before it is sent to the rocket.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CommandSafety {
+    /// Sent on a tap
+    Safe,
+    /// Loses data, such as the maximum altitude or the flight log
+    Destructive,
+    /// Changes what the rocket will do in flight
+    SafetyCritical,
+}
+
+/// Commands that are not [`CommandSafety::Safe`], with what they do.
+const GUARDED_COMMANDS: [(&str, CommandSafety, &str); 3] = [
+    ("reset", CommandSafety::Destructive, "RESET MAX ALTITUDE?"),
+    ("erase", CommandSafety::Destructive, "ERASE FLIGHT LOG?"),
+    ("arm", CommandSafety::SafetyCritical, "ARM THE ROCKET?"),
+];
+
+/// Safety and confirmation question of the text command `command`, read as
+/// the rocket reads it, whatever its case, or `None` if it is safe.
+fn guarded(command: &str) -> Option<(CommandSafety, &'static str)> {
+    GUARDED_COMMANDS
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(command.trim()))
+        .map(|(_, safety, question)| (*safety, *question))
+}
+
+/// How much care the text command `command` needs.
+pub fn command_safety(command: &str) -> CommandSafety {
+    guarded(command).map_or(CommandSafety::Safe, |(safety, _)| safety)
+}
+
+/// How much care a command of any kind needs, as [`command_safety`] for
+/// text commands.
+pub fn safety_of(command: &Command) -> CommandSafety {
+    match command {
+        Command::Text(text) => command_safety(text),
+        Command::FactoryReset => CommandSafety::Destructive,
+        // changes how the rocket tracks and records its flight
+        Command::SetConfig(..) => CommandSafety::SafetyCritical,
+        Command::SetSeaLevelPressure(_) | Command::GetConfig(_) => CommandSafety::Safe,
+    }
+}
+
+/// Button labelled `label` that sends `command`.
+///
+/// Unless the command is [`CommandSafety::Safe`], it asks for confirmation
+/// first, whether tapped or long pressed.
+pub fn command_button<D>(
+    label: &str,
+    command: &'static str,
+    area: Rectangle,
+    command_sender: Sender<Command>,
+    navigation: &Navigation<D>,
+) -> Box<Button>
+where
+    D: DrawTarget<Color = Rgb565> + 'static,
+    D::Error: Debug,
+{
+    let (title, question) = match guarded(command) {
+        None | Some((CommandSafety::Safe, _)) => {
+            return make_button(
+                label.to_string(),
+                area,
+                command_handler(command, command_sender),
+            )
+        }
+        Some((CommandSafety::SafetyCritical, question)) => {
+            (format!("{}: SAFETY CRITICAL", label), question)
+        }
+        Some((CommandSafety::Destructive, question)) => {
+            (format!("{}: CANNOT BE UNDONE", label), question)
+        }
+    };
+    let navigation = navigation.clone();
+
+    // without a long press action, a long press asks too
+    make_button(
+        label.to_string(),
+        area,
+        Box::new(move || {
+            navigation.modal(ConfirmDialog::new(
+                &title,
+                question,
+                command_handler(command, command_sender.clone()),
+            ))
+        }),
+    )
+}
+
+fn make_button(name: String, area: Rectangle, on_click: Action) -> Box<Button> {
+    Box::new(Button::new(area.top_left, area.size, name, on_click))
+}
+
+fn command_handler(cmd: &'static str, cs: Sender<Command>) -> Action {
+    Box::new(move || {
+        cs.send(Command::Text(cmd.to_string())).unwrap();
+    })
+}
+
+pub fn init_control_panel<D>(
+    command_sender: Sender<Command>,
+    ui: &mut Ui<D>,
+    navigation: &Navigation<D>,
+    on_clear: Action,
+    on_psl: Action,
+    on_settings: Action,
+) where
+    D: DrawTarget<Color = Rgb565> + 'static,
+    D::Error: Debug,
+{
+    let commands = [
+        ("TON", "ton"),
+        ("TOFF", "toff"),
+        ("TONE", "tone"),
+        ("RST", "reset"),
+    ];
+    let buttons: Vec<(&str, Action)> =
+        vec![("CLR", on_clear), ("PSL", on_psl), ("SET", on_settings)];
+
+    // a strip of square buttons along the bottom of the display
+    let strip = column([UiDimension::Auto, UiDimension::Fixed(BUTTON_SIZE)]).layout(ui.bounds())[1];
+    let cells = row((0..commands.len() + buttons.len()).map(|_| UiDimension::Fixed(BUTTON_SIZE)))
+        .gap(1)
+        .padding(Insets {
+            left: 1,
+            ..Insets::default()
+        })
+        .layout(strip);
+    let (command_cells, button_cells) = cells.split_at(commands.len());
+
+    for ((label, command), area) in commands.into_iter().zip(command_cells) {
+        ui.add_element(command_button(
+            label,
+            command,
+            *area,
+            command_sender.clone(),
+            navigation,
+        ));
+    }
+    for ((label, on_click), area) in buttons.into_iter().zip(button_cells) {
+        ui.add_element(make_button(label.to_string(), *area, on_click));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, sync::mpsc};
+
+    use super::*;
+    use crate::{
+        config::FlightSetting,
+        ui::{
+            calibration::TouchCalibration,
+            framebuffer::{assert_snapshot, Framebuffer, PALETTE},
+            gesture::LONG_PRESS,
+            screen::{Navigator, Screen},
+        },
+    };
+
+    fn text(command: &str) -> Command {
+        Command::Text(command.to_string())
+    }
+
+    struct PanelScreen {
+        command_sender: Sender<Command>,
+    }
+
+    impl Screen<Framebuffer> for PanelScreen {
+        fn build(&mut self, ui: &mut Ui<Framebuffer>, navigation: &Navigation<Framebuffer>) {
+            init_control_panel(
+                self.command_sender.clone(),
+                ui,
+                navigation,
+                Box::new(|| ()),
+                Box::new(|| ()),
+                Box::new(|| ()),
+            );
+        }
+    }
+
+    #[test]
+    fn renders_and_sends_commands() {
+        let (command_sender, command_receiver) = mpsc::channel();
+        let mut display = Framebuffer::new(320, 240);
+        let mut ui = Ui::new(320, 240);
+        // raw touches map straight to screen coordinates
+        ui.touch_calibration(TouchCalibration::IDENTITY);
+
+        let cleared = Rc::new(Cell::new(false));
+        let cleared2 = cleared.clone();
+        init_control_panel(
+            command_sender,
+            &mut ui,
+            &Navigator::new(320, 240).navigation(),
+            Box::new(move || cleared2.set(true)),
+            Box::new(|| ()),
+            Box::new(|| ()),
+        );
+        ui.draw(&mut display);
+
+        let buttons = display
+            .to_ascii(&PALETTE)
+            .lines()
+            .skip(213)
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_snapshot("control_panel", &buttons);
+
+        // tap TON, then CLR
+        for x in [10, 115] {
+            ui.tap(x, 225);
+        }
+
+        assert_eq!(command_receiver.try_recv(), Ok(text("ton")));
+        assert!(command_receiver.try_recv().is_err());
+        assert!(cleared.get());
+    }
+
+    #[test]
+    fn guards_dangerous_commands() {
+        assert_eq!(command_safety("ton"), CommandSafety::Safe);
+        assert_eq!(command_safety("reset"), CommandSafety::Destructive);
+        assert_eq!(command_safety(" Reset "), CommandSafety::Destructive);
+        assert_eq!(command_safety("arm"), CommandSafety::SafetyCritical);
+        assert_eq!(
+            guarded(" Erase"),
+            Some((CommandSafety::Destructive, "ERASE FLIGHT LOG?"))
+        );
+        assert_eq!(guarded("ton"), None);
+        assert_eq!(safety_of(&text("arm")), CommandSafety::SafetyCritical);
+        assert_eq!(
+            safety_of(&Command::FactoryReset),
+            CommandSafety::Destructive
+        );
+        assert_eq!(
+            safety_of(&Command::SetConfig(FlightSetting::BatchSamples, 2.0)),
+            CommandSafety::SafetyCritical
+        );
+        assert_eq!(
+            safety_of(&Command::GetConfig(FlightSetting::BatchSamples)),
+            CommandSafety::Safe
+        );
+
+        let (command_sender, command_receiver) = mpsc::channel();
+        let mut navigator = Navigator::<Framebuffer>::new(320, 240);
+        navigator.touch_calibration(TouchCalibration::IDENTITY);
+        navigator.navigation().push(PanelScreen { command_sender });
+        navigator.update();
+
+        // RST asks first, and nothing is sent if cancelled
+        navigator.tap(90, 225);
+        assert_eq!(navigator.depth(), 2);
+        navigator.tap(170, 150);
+        assert_eq!(navigator.depth(), 1);
+        assert!(command_receiver.try_recv().is_err());
+
+        // or sent once confirmed
+        navigator.tap(90, 225);
+        navigator.tap(230, 150);
+        assert_eq!(navigator.depth(), 1);
+        assert_eq!(command_receiver.try_recv(), Ok(text("reset")));
+
+        // a long press asks too
+        navigator.touch(90, 225, &LONG_PRESS);
+        assert_eq!(navigator.depth(), 2);
+        assert!(command_receiver.try_recv().is_err());
+        navigator.tap(230, 150);
+        assert_eq!(command_receiver.try_recv(), Ok(text("reset")));
+        assert!(command_receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,454 @@
+use rocket_protocol::telemetry::export::{write_json_string, LogFormat};
+
+use crate::{
+    command::{valid_sea_level_pressure, Command},
+    config::BasestationConfig,
+    control_panel::{safety_of, CommandSafety},
+};
+
+use super::LinkStatus;
+
+/// Paths of the HTTP API start here, followed by the action, e.g.
+/// `POST /api/stream/start` or `POST /api/psl?value=101325`.
+///
+/// Every request carries the AP password or the API token, either as
+/// `Authorization: Bearer <secret>` or as a `token` query parameter.  On
+/// the telemetry WebSocket the same actions are sent as text, `psl 101325`,
+/// after an `auth <secret>` message.  Replies are JSON with an `ok` member,
+/// apart from flight log downloads, which are files.
+///
+/// Commands the touch UI asks about first, such as `reset`, need a value of
+/// `confirm`: `POST /api/reset?value=confirm`, or `reset confirm`.
+pub const API_PREFIX: &str = "/api/";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ApiMethod {
+    Get,
+    Post,
+}
+
+/// What an API request asks for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApiAction {
+    /// Pass a command on to the rocket
+    Send(Command),
+    /// Report the link to the rocket
+    Status,
+    /// Download the samples received
+    Log(LogFormat),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ApiError {
+    /// The password or token is missing or wrong
+    Unauthorized,
+    /// Neither an AP password nor a token is set, so nothing is accepted
+    NoSecret,
+    NotFound,
+    MethodNotAllowed,
+    /// A value is missing or not what the action accepts
+    BadRequest(&'static str),
+}
+
+impl ApiError {
+    /// HTTP status of the reply.
+    pub fn status(&self) -> u16 {
+        match self {
+            ApiError::BadRequest(_) => 400,
+            ApiError::Unauthorized => 401,
+            ApiError::NoSecret => 403,
+            ApiError::NotFound => 404,
+            ApiError::MethodNotAllowed => 405,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let message = match self {
+            ApiError::Unauthorized => "password or token required",
+            ApiError::NoSecret => "set an AP password or API token to use the API",
+            ApiError::NotFound => "no such action",
+            ApiError::MethodNotAllowed => "wrong method for action",
+            ApiError::BadRequest(message) => message,
+        };
+
+        let mut json = String::from("{\"ok\":false,\"error\":");
+        write_json_string(&mut json, message).unwrap();
+        json.push('}');
+        json
+    }
+}
+
+/// A text message on the telemetry WebSocket.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WsRequest<'a> {
+    /// Authenticate the rest of the session
+    Auth(&'a str),
+    Action(ApiAction),
+}
+
+/// Action named `name`, with the method it is requested by.
+fn action(name: &str, value: Option<&str>) -> Result<(ApiMethod, ApiAction), ApiError> {
+    let text = |command: &str| {
+        Ok((
+            ApiMethod::Post,
+            ApiAction::Send(Command::Text(command.to_string())),
+        ))
+    };
+
+    match name {
+        "stream/start" => text("ton"),
+        "stream/stop" => text("toff"),
+        "tone" => text("tone"),
+        "reset" => text("reset"),
+        "psl" => {
+            let pascals = value
+                .and_then(|value| value.parse::<f32>().ok())
+                .filter(|pascals| valid_sea_level_pressure(*pascals))
+                .ok_or(ApiError::BadRequest(PSL_RANGE))?;
+            Ok((
+                ApiMethod::Post,
+                ApiAction::Send(Command::SetSeaLevelPressure(pascals)),
+            ))
+        }
+        "status" => Ok((ApiMethod::Get, ApiAction::Status)),
+        "log" | "log.json" => Ok((ApiMethod::Get, ApiAction::Log(LogFormat::Json))),
+        "log.csv" => Ok((ApiMethod::Get, ApiAction::Log(LogFormat::Csv))),
+        _ => Err(ApiError::NotFound),
+    }
+}
+
+/// `action`, unless it sends a command needing a `confirm` value without one.
+fn confirmed(action: ApiAction, value: Option<&str>) -> Result<ApiAction, ApiError> {
+    match &action {
+        ApiAction::Send(command)
+            if safety_of(command) != CommandSafety::Safe && value != Some("confirm") =>
+        {
+            Err(ApiError::BadRequest("needs a value of confirm"))
+        }
+        _ => Ok(action),
+    }
+}
+
+/// Spelled out to be a `&'static str`, and tested against the limits.
+const PSL_RANGE: &str = "value must be a sea level pressure in Pa, 84600 to 108400";
+
+/// Action of an HTTP request for `uri`, which includes any query.
+pub fn route(method: ApiMethod, uri: &str) -> Result<ApiAction, ApiError> {
+    let path = uri.split('?').next().unwrap_or_default();
+    let name = path.strip_prefix(API_PREFIX).ok_or(ApiError::NotFound)?;
+    let value = query_param(uri, "value");
+
+    let (expected, action) = action(name.trim_end_matches('/'), value.as_deref())?;
+    if method != expected {
+        return Err(ApiError::MethodNotAllowed);
+    }
+
+    confirmed(action, value.as_deref())
+}
+
+pub fn ws_request(text: &str) -> Result<WsRequest<'_>, ApiError> {
+    let mut words = text.split_whitespace();
+
+    match (words.next(), words.next(), words.next()) {
+        (Some("auth"), Some(secret), None) => Ok(WsRequest::Auth(secret)),
+        (Some(name), value, None) => match action(name, value)? {
+            // too big for one message
+            (_, ApiAction::Log(_)) => Err(ApiError::BadRequest("download the log over HTTP")),
+            (_, action) => confirmed(action, value).map(WsRequest::Action),
+        },
+        _ => Err(ApiError::BadRequest(
+            "expected an action and at most one value",
+        )),
+    }
+}
+
+/// WebSocket sessions that have sent the password or token.
+#[derive(Debug, Default)]
+pub struct WsSessions {
+    authorized: Vec<i32>,
+}
+
+impl WsSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle a text message from `session`, returning the action to run,
+    /// or `None` once the session is authorized.
+    pub fn message(
+        &mut self,
+        config: &BasestationConfig,
+        session: i32,
+        text: &str,
+    ) -> Result<Option<ApiAction>, ApiError> {
+        match ws_request(text)? {
+            WsRequest::Auth(secret) => {
+                authorize(config, Some(secret))?;
+                // a session may authorize again, but is only listed once
+                if !self.authorized.contains(&session) {
+                    self.authorized.push(session);
+                }
+                Ok(None)
+            }
+            WsRequest::Action(action) if self.authorized.contains(&session) => Ok(Some(action)),
+            WsRequest::Action(_) => Err(ApiError::Unauthorized),
+        }
+    }
+
+    /// Forget `session` once its socket has closed.
+    pub fn end(&mut self, session: i32) {
+        self.authorized.retain(|authorized| *authorized != session);
+    }
+}
+
+/// Secret presented with an HTTP request, from its `Authorization` header
+/// or else its `token` query parameter.
+pub fn request_secret(uri: &str, authorization: Option<&str>) -> Option<String> {
+    authorization
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(|secret| secret.trim().to_string())
+        .or_else(|| query_param(uri, "token"))
+}
+
+/// Whether `secret` is the AP password or the API token.
+pub fn authorize(config: &BasestationConfig, secret: Option<&str>) -> Result<(), ApiError> {
+    let accepted = [&config.ap_password, &config.api_token];
+    if accepted.iter().all(|accepted| accepted.is_empty()) {
+        return Err(ApiError::NoSecret);
+    }
+
+    let secret = secret.ok_or(ApiError::Unauthorized)?;
+    // every secret is compared in full, so timing says nothing of a near miss
+    let matched = accepted
+        .iter()
+        .filter(|accepted| !accepted.is_empty())
+        .fold(false, |matched, accepted| {
+            matched | same_bytes(secret, accepted)
+        });
+
+    if matched {
+        Ok(())
+    } else {
+        Err(ApiError::Unauthorized)
+    }
+}
+
+fn same_bytes(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/// Decoded value of query parameter `name` in `uri`.
+fn query_param(uri: &str, name: &str) -> Option<String> {
+    let (_, query) = uri.split_once('?')?;
+
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .and_then(|(_, value)| percent_decode(value))
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = std::str::from_utf8(rest.get(..2)?).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &rest[2..];
+            }
+            byte => bytes.push(byte),
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+/// Reply to a command passed on to the rocket.  It is only queued; typed
+/// commands report their ack on the basestation.
+pub fn sent_json(command: &Command) -> String {
+    match command {
+        Command::Text(text) => {
+            let mut json = String::from("{\"ok\":true,\"command\":");
+            write_json_string(&mut json, text).unwrap();
+            json.push('}');
+            json
+        }
+        Command::SetSeaLevelPressure(pascals) => {
+            format!("{{\"ok\":true,\"command\":\"psl\",\"value\":{}}}", pascals)
+        }
+        _ => "{\"ok\":true}".to_string(),
+    }
+}
+
+pub fn status_json(status: &LinkStatus) -> String {
+    let last_sample_ms = status
+        .last_sample_age
+        .map_or("null".to_string(), |age| age.as_millis().to_string());
+
+    format!(
+        "{{\"ok\":true,\"receiving\":{},\"last_sample_ms\":{},\"received\":{},\"missed\":{}}}",
+        status.receiving, last_sample_ms, status.received, status.missed
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::command::{SEA_LEVEL_PRESSURE_MAX, SEA_LEVEL_PRESSURE_MIN};
+
+    fn text(command: &str) -> ApiAction {
+        ApiAction::Send(Command::Text(command.to_string()))
+    }
+
+    #[test]
+    fn routes_requests() {
+        assert_eq!(route(ApiMethod::Post, "/api/stream/start"), Ok(text("ton")));
+        assert_eq!(
+            route(ApiMethod::Post, "/api/tone/?token=x"),
+            Ok(text("tone"))
+        );
+        assert_eq!(
+            route(ApiMethod::Post, "/api/psl?value=101325&token=x"),
+            Ok(ApiAction::Send(Command::SetSeaLevelPressure(101_325.0)))
+        );
+        assert_eq!(
+            route(ApiMethod::Get, "/api/log"),
+            Ok(ApiAction::Log(LogFormat::Json))
+        );
+        assert_eq!(
+            route(ApiMethod::Get, "/api/log.csv?token=x"),
+            Ok(ApiAction::Log(LogFormat::Csv))
+        );
+
+        assert_eq!(
+            route(ApiMethod::Post, "/api/psl?value=30.01"),
+            Err(ApiError::BadRequest(PSL_RANGE))
+        );
+        let limits = format!("{} to {}", SEA_LEVEL_PRESSURE_MIN, SEA_LEVEL_PRESSURE_MAX);
+        assert!(PSL_RANGE.ends_with(&limits));
+        assert_eq!(
+            route(ApiMethod::Get, "/api/reset"),
+            Err(ApiError::MethodNotAllowed)
+        );
+        assert_eq!(route(ApiMethod::Post, "/api/arm"), Err(ApiError::NotFound));
+
+        // as the touch UI asks first
+        assert_eq!(
+            route(ApiMethod::Post, "/api/reset"),
+            Err(ApiError::BadRequest("needs a value of confirm"))
+        );
+        assert_eq!(
+            route(ApiMethod::Post, "/api/reset?value=confirm"),
+            Ok(text("reset"))
+        );
+        assert!(ws_request("reset").is_err());
+        assert_eq!(
+            ws_request("reset confirm"),
+            Ok(WsRequest::Action(text("reset")))
+        );
+
+        assert_eq!(ws_request("auth hunter22"), Ok(WsRequest::Auth("hunter22")));
+        assert_eq!(
+            ws_request("stream/stop"),
+            Ok(WsRequest::Action(text("toff")))
+        );
+        assert_eq!(
+            ws_request("status"),
+            Ok(WsRequest::Action(ApiAction::Status))
+        );
+        assert!(ws_request("log.csv").is_err());
+    }
+
+    #[test]
+    fn accepts_password_or_token() {
+        let mut config = BasestationConfig {
+            api_token: "mission control".to_string(),
+            ..BasestationConfig::default()
+        };
+
+        let secret = request_secret("/api/tone", Some("Bearer knock it off"));
+        assert_eq!(authorize(&config, secret.as_deref()), Ok(()));
+        let secret = request_secret("/api/tone?token=mission%20control", None);
+        assert_eq!(authorize(&config, secret.as_deref()), Ok(()));
+
+        assert_eq!(
+            authorize(&config, Some("knock it of")),
+            Err(ApiError::Unauthorized)
+        );
+        assert_eq!(authorize(&config, None), Err(ApiError::Unauthorized));
+
+        // an open AP without a token accepts nothing
+        config.ap_password.clear();
+        config.api_token.clear();
+        assert_eq!(authorize(&config, Some("")), Err(ApiError::NoSecret));
+    }
+
+    #[test]
+    fn authorizes_websocket_sessions() {
+        let config = BasestationConfig {
+            api_token: "hunter22".to_string(),
+            ..BasestationConfig::default()
+        };
+        let mut sessions = WsSessions::new();
+
+        assert_eq!(
+            sessions.message(&config, 1, "status"),
+            Err(ApiError::Unauthorized)
+        );
+        assert_eq!(
+            sessions.message(&config, 1, "auth hunter2"),
+            Err(ApiError::Unauthorized)
+        );
+
+        assert_eq!(sessions.message(&config, 1, "auth hunter22"), Ok(None));
+        assert_eq!(sessions.message(&config, 1, "auth hunter22"), Ok(None));
+        assert_eq!(sessions.authorized, [1]);
+        assert_eq!(
+            sessions.message(&config, 1, "status"),
+            Ok(Some(ApiAction::Status))
+        );
+        // other sessions authorize for themselves
+        assert_eq!(
+            sessions.message(&config, 2, "status"),
+            Err(ApiError::Unauthorized)
+        );
+
+        sessions.end(1);
+        assert_eq!(
+            sessions.message(&config, 1, "status"),
+            Err(ApiError::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn writes_json() {
+        assert_eq!(
+            sent_json(&Command::Text("say \"hi\"".to_string())),
+            "{\"ok\":true,\"command\":\"say \\\"hi\\\"\"}"
+        );
+        assert_eq!(
+            status_json(&LinkStatus {
+                receiving: false,
+                last_sample_age: Some(Duration::from_millis(2500)),
+                received: 10,
+                missed: 1,
+            }),
+            "{\"ok\":true,\"receiving\":false,\"last_sample_ms\":2500,\"received\":10,\"missed\":1}"
+        );
+        assert_eq!(
+            ApiError::NotFound.to_json(),
+            "{\"ok\":false,\"error\":\"no such action\"}"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+
+use rocket_protocol::telemetry::Telemetry;
+
+pub mod api;
+
+pub use rocket_protocol::link::{LinkMonitor, LinkStatus, LINK_STATUS_PERIOD, LINK_TIMEOUT};
+
+/// Page served at [`INDEX_PATH`], kept in flash.  It connects to
+/// [`TELEMETRY_PATH`] and decodes the same tagged frames the rocket sends.
+pub const INDEX_HTML: &str = include_str!("index.html");
+
+pub const INDEX_PATH: &str = "/";
+/// WebSocket carrying a [`PacketKind::Telemetry`] frame for each sample and
+/// a [`LinkStatus`] frame every [`LINK_STATUS_PERIOD`].
+///
+/// [`PacketKind::Telemetry`]: rocket_protocol::datalink::packet::PacketKind::Telemetry
+pub const TELEMETRY_PATH: &str = "/ws/telemetry";
+
+/// The latest samples received, oldest first, for download.
+#[derive(Clone, Debug)]
+pub struct FlightLog {
+    samples: VecDeque<Telemetry>,
+    capacity: usize,
+}
+
+impl FlightLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Keep at most `capacity` samples, dropping the oldest if there are
+    /// more.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        let excess = self.samples.len().saturating_sub(capacity);
+        self.samples.drain(..excess);
+        self.samples.shrink_to(capacity);
+        self.capacity = capacity;
+    }
+
+    /// Add a sample, dropping the oldest once full.
+    pub fn push(&mut self, telemetry: Telemetry) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(telemetry);
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &Telemetry> {
+        self.samples.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(sequence: u32) -> Telemetry {
+        Telemetry {
+            sequence,
+            ..Telemetry::default()
+        }
+    }
+
+    #[test]
+    fn keeps_latest_samples() {
+        let mut log = FlightLog::new(3);
+        for sequence in 0..5 {
+            log.push(sample(sequence));
+        }
+
+        let kept: Vec<u32> = log.samples().map(|telemetry| telemetry.sequence).collect();
+        assert_eq!(kept, [2, 3, 4]);
+
+        // following the rocket's recording capacity
+        log.set_capacity(2);
+        log.push(sample(5));
+        let kept: Vec<u32> = log.samples().map(|telemetry| telemetry.sequence).collect();
+        assert_eq!(kept, [4, 5]);
+        log.set_capacity(4);
+        for sequence in 6..9 {
+            log.push(sample(sequence));
+        }
+        assert_eq!(log.len(), 4);
+
+        log.clear();
+        assert!(log.is_empty());
+    }
+}
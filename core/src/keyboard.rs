@@ -0,0 +1,708 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    rc::Rc,
+};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    pixelcolor::Rgb565,
+    primitives::Rectangle,
+};
+
+use crate::ui::{
+    button::Button,
+    layout::{column, place, row, Align, Insets},
+    screen::{Navigation, Screen},
+    text::Text,
+    ui::{TouchEvent, Ui, UiDimension, UiElement, UiEvent},
+};
+
+const KEY_WIDTH: i16 = 29;
+/// Width of SHIFT, DEL and the other keys that don't type a character
+const WIDE_KEY_WIDTH: i16 = 44;
+const KEY_HEIGHT: i16 = 30;
+const KEY_GAP: u32 = 2;
+const ROWS: u32 = 4;
+const LINE_HEIGHT: i16 = 14;
+
+/// Characters on the three upper rows of each layer.
+const LETTERS: [&str; 3] = ["qwertyuiop", "asdfghjkl", "zxcvbnm"];
+const SYMBOLS: [&str; 3] = ["1234567890", "-/:;()$&@", ".,?!'\""];
+const MORE_SYMBOLS: [&str; 3] = ["[]{}#%^*+=", "_\\|~<>", "`"];
+
+/// Which characters the keyboard is showing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyboardLayer {
+    Lower,
+    /// Upper case, for the next letter only
+    Upper,
+    Symbols,
+    MoreSymbols,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    /// Upper case, or the other page of symbols
+    Shift,
+    /// Between letters and symbols
+    Layer,
+    /// Delete the character before the cursor
+    Backspace,
+    Left,
+    Right,
+    Space,
+    Enter,
+    Exit,
+}
+
+impl Key {
+    fn label(&self, layer: KeyboardLayer) -> String {
+        let letters = matches!(layer, KeyboardLayer::Lower | KeyboardLayer::Upper);
+
+        match self {
+            Key::Char(c) => c.to_string(),
+            Key::Shift if letters => "SHIFT".to_string(),
+            Key::Shift if layer == KeyboardLayer::Symbols => "#+=".to_string(),
+            Key::Shift => "123".to_string(),
+            Key::Layer if letters => "?123".to_string(),
+            Key::Layer => "ABC".to_string(),
+            Key::Backspace => "DEL".to_string(),
+            Key::Left => "<".to_string(),
+            Key::Right => ">".to_string(),
+            Key::Space => "SPACE".to_string(),
+            Key::Enter => "ENT".to_string(),
+            Key::Exit => "ESC".to_string(),
+        }
+    }
+
+    fn width(&self) -> UiDimension {
+        match self {
+            Key::Char(_) | Key::Left | Key::Right => UiDimension::Fixed(KEY_WIDTH),
+            Key::Space => UiDimension::Auto,
+            _ => UiDimension::Fixed(WIDE_KEY_WIDTH),
+        }
+    }
+}
+
+/// Keys of `layer`, a row at a time from the top.
+fn layer_rows(layer: KeyboardLayer) -> [Vec<Key>; ROWS as usize] {
+    let chars = match layer {
+        KeyboardLayer::Lower | KeyboardLayer::Upper => LETTERS,
+        KeyboardLayer::Symbols => SYMBOLS,
+        KeyboardLayer::MoreSymbols => MORE_SYMBOLS,
+    };
+    let keys = |row: &str| {
+        row.chars()
+            .map(|c| match layer {
+                KeyboardLayer::Upper => Key::Char(c.to_ascii_uppercase()),
+                _ => Key::Char(c),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut third = vec![Key::Shift];
+    third.extend(keys(chars[2]));
+    third.push(Key::Backspace);
+
+    [
+        keys(chars[0]),
+        keys(chars[1]),
+        third,
+        vec![
+            Key::Layer,
+            Key::Left,
+            Key::Space,
+            Key::Right,
+            Key::Exit,
+            Key::Enter,
+        ],
+    ]
+}
+
+/// Each key of `layer` and its area, with the rows centred in `area`.
+fn key_areas(layer: KeyboardLayer, area: Rectangle) -> Vec<(Key, Rectangle)> {
+    let height = ROWS * (KEY_HEIGHT as u32 + KEY_GAP) - KEY_GAP;
+    let keyboard = place(
+        area,
+        Size::new(area.size.width, height),
+        Align::Center,
+        Align::End,
+    );
+    let rows = column([UiDimension::Fixed(KEY_HEIGHT); ROWS as usize])
+        .gap(KEY_GAP)
+        .layout(keyboard);
+
+    layer_rows(layer)
+        .into_iter()
+        .zip(rows)
+        .flat_map(|(keys, area)| {
+            let areas = row(keys.iter().map(Key::width))
+                .gap(KEY_GAP)
+                .padding(Insets {
+                    left: 6,
+                    right: 6,
+                    ..Insets::default()
+                })
+                .justify(Align::Center)
+                .layout(area);
+            keys.into_iter().zip(areas)
+        })
+        .collect()
+}
+
+/// QWERTY keyboard, calling `on_key` for every key but those changing the
+/// layer, which it handles itself.
+///
+/// SHIFT gives upper case for one letter, or the second page of symbols.
+pub struct Keyboard {
+    area: Rectangle,
+    layer: KeyboardLayer,
+    keys: Vec<(Key, Rectangle)>,
+    buttons: Vec<Button>,
+    /// Key under the touch in progress
+    touched: Option<usize>,
+    /// Set by a button when tapped
+    pressed: Rc<Cell<Option<Key>>>,
+    on_key: Box<dyn Fn(Key)>,
+    dirty: bool,
+}
+
+impl Keyboard {
+    pub fn new(area: Rectangle, on_key: Box<dyn Fn(Key)>) -> Self {
+        let mut keyboard = Self {
+            area,
+            layer: KeyboardLayer::Lower,
+            keys: Vec::new(),
+            buttons: Vec::new(),
+            touched: None,
+            pressed: Rc::new(Cell::new(None)),
+            on_key,
+            dirty: true,
+        };
+        keyboard.set_layer(KeyboardLayer::Lower);
+        keyboard
+    }
+
+    pub fn layer(&self) -> KeyboardLayer {
+        self.layer
+    }
+
+    pub fn set_layer(&mut self, layer: KeyboardLayer) {
+        self.layer = layer;
+        self.keys = key_areas(layer, self.area);
+        self.buttons = self
+            .keys
+            .iter()
+            .map(|(key, area)| {
+                let (key, pressed) = (*key, self.pressed.clone());
+                Button::new(
+                    area.top_left,
+                    area.size,
+                    key.label(layer),
+                    Box::new(move || pressed.set(Some(key))),
+                )
+            })
+            .collect();
+        self.touched = None;
+        self.dirty = true;
+    }
+
+    /// Area of `key` on the current layer.
+    pub fn key_area(&self, key: Key) -> Option<Rectangle> {
+        self.keys
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, area)| *area)
+    }
+
+    fn key_at(&self, (x, y): (i32, i32)) -> Option<usize> {
+        self.keys
+            .iter()
+            .position(|(_, area)| area.contains(Point::new(x, y)))
+    }
+
+    fn press(&mut self, key: Key) {
+        let layer = match (key, self.layer) {
+            (Key::Shift, KeyboardLayer::Lower) => KeyboardLayer::Upper,
+            (Key::Shift, KeyboardLayer::Upper) => KeyboardLayer::Lower,
+            (Key::Shift, KeyboardLayer::Symbols) => KeyboardLayer::MoreSymbols,
+            (Key::Shift, KeyboardLayer::MoreSymbols) => KeyboardLayer::Symbols,
+            (Key::Layer, KeyboardLayer::Lower | KeyboardLayer::Upper) => KeyboardLayer::Symbols,
+            (Key::Layer, _) => KeyboardLayer::Lower,
+            (key, layer) => {
+                (*self.on_key)(key);
+                if layer == KeyboardLayer::Upper && matches!(key, Key::Char(_)) {
+                    KeyboardLayer::Lower
+                } else {
+                    return;
+                }
+            }
+        };
+        self.set_layer(layer);
+    }
+
+    /// Pass `event` on to the button at `index`.
+    fn send<D>(&mut self, index: Option<usize>, event: UiEvent)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        if let Some(button) = index.and_then(|i| self.buttons.get_mut(i)) {
+            UiElement::<D>::handle_event(button, event);
+        }
+    }
+}
+
+impl<D> UiElement<D> for Keyboard
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    fn handle_event(&mut self, event: UiEvent) {
+        match event {
+            UiEvent::TouchEnter(TouchEvent::Down(x, y))
+            | UiEvent::TouchEnter(TouchEvent::Drag { to: (x, y), .. }) => {
+                self.touched = self.key_at((x, y));
+                self.send::<D>(self.touched, event);
+            }
+            // sliding from key to key
+            UiEvent::Drag(TouchEvent::Drag { to, .. }) => {
+                let touched = self.key_at(to);
+                if touched != self.touched {
+                    self.send::<D>(self.touched, UiEvent::TouchLeave(event_of(event)));
+                    self.send::<D>(touched, UiEvent::TouchEnter(event_of(event)));
+                    self.touched = touched;
+                }
+            }
+            UiEvent::TouchLeave(_) => {
+                let touched = self.touched.take();
+                self.send::<D>(touched, event);
+            }
+            UiEvent::Tap(TouchEvent::Up(x, y)) => {
+                self.touched = None;
+                self.send::<D>(self.key_at((x, y)), event);
+                if let Some(key) = self.pressed.take() {
+                    self.press(key);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn dirty(&self) -> bool {
+        self.dirty
+            || self
+                .buttons
+                .iter()
+                .any(|button| UiElement::<D>::dirty(button))
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        self.area
+    }
+
+    fn draw(&mut self, display: &mut D) {
+        for button in &mut self.buttons {
+            button.draw(display);
+        }
+        self.dirty = false;
+    }
+}
+
+fn event_of(event: UiEvent) -> TouchEvent {
+    match event {
+        UiEvent::TouchEnter(e)
+        | UiEvent::TouchLeave(e)
+        | UiEvent::Tap(e)
+        | UiEvent::LongPress(e)
+        | UiEvent::Drag(e) => e,
+    }
+}
+
+/// What a keyboard is for, and the text it accepts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyboardConfig {
+    /// Shown above the text, e.g. `ROCKET NAME`
+    pub title: String,
+    /// Text to start with, e.g. the current name
+    pub initial: String,
+    /// Fewest characters accepted, e.g. 8 for a WPA2 password
+    pub min_len: usize,
+    /// Most characters that can be typed
+    pub max_len: usize,
+    /// Show `*` instead of the characters, for passwords
+    pub masked: bool,
+}
+
+impl KeyboardConfig {
+    /// Up to 32 characters, as in a WiFi SSID.
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            initial: String::new(),
+            min_len: 0,
+            max_len: 32,
+            masked: false,
+        }
+    }
+
+    pub fn length(mut self, min_len: usize, max_len: usize) -> Self {
+        self.min_len = min_len;
+        self.max_len = max_len;
+        self
+    }
+
+    pub fn initial(mut self, text: &str) -> Self {
+        self.initial = text.to_string();
+        self
+    }
+
+    pub fn masked(mut self) -> Self {
+        self.masked = true;
+        self
+    }
+
+    pub fn validate(&self, text: &str) -> Result<(), KeyboardError> {
+        let len = text.chars().count();
+
+        if len < self.min_len {
+            Err(KeyboardError::TooShort)
+        } else if len > self.max_len {
+            Err(KeyboardError::TooLong)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// What is wrong, in terms of this keyboard's limits.
+    pub fn describe(&self, error: KeyboardError) -> String {
+        match error {
+            KeyboardError::TooShort if self.min_len == 1 => "ENTER SOME TEXT".to_string(),
+            KeyboardError::TooShort => format!("AT LEAST {} CHARACTERS", self.min_len),
+            KeyboardError::TooLong => format!("AT MOST {} CHARACTERS", self.max_len),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyboardError {
+    TooShort,
+    TooLong,
+}
+
+/// Text being typed, with a cursor between characters.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Entry {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl Entry {
+    fn new(text: &str) -> Self {
+        let chars = text.chars().collect::<Vec<_>>();
+        let cursor = chars.len();
+        Self { chars, cursor }
+    }
+
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// Apply an editing key, keeping to `max_len` characters, returning
+    /// false for keys that don't edit.
+    fn edit(&mut self, key: Key, max_len: usize) -> bool {
+        match key {
+            Key::Char(_) | Key::Space if self.chars.len() >= max_len => (),
+            Key::Char(c) => {
+                self.chars.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            Key::Space => {
+                self.chars.insert(self.cursor, ' ');
+                self.cursor += 1;
+            }
+            Key::Backspace => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.chars.remove(self.cursor);
+                }
+            }
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(self.chars.len()),
+            _ => return false,
+        }
+        true
+    }
+
+    /// The text with `|` at the cursor, and `*` for each character if
+    /// `masked`.
+    fn display(&self, masked: bool) -> String {
+        let shown = |chars: &[char]| -> String {
+            if masked {
+                "*".repeat(chars.len())
+            } else {
+                chars.iter().collect()
+            }
+        };
+        let (before, after) = self.chars.split_at(self.cursor);
+
+        format!("{}|{}", shown(before), shown(after))
+    }
+}
+
+/// Title, text and error lines above the keys.
+fn areas(bounds: Rectangle) -> Vec<Rectangle> {
+    column([
+        UiDimension::Fixed(LINE_HEIGHT),
+        UiDimension::Fixed(LINE_HEIGHT),
+        UiDimension::Fixed(LINE_HEIGHT),
+        UiDimension::Auto,
+    ])
+    .gap(2)
+    .layout(bounds)
+}
+
+/// Keyboard for typing text, as described by `config`.
+///
+/// ENT calls `on_enter` with the text if it is valid, and otherwise shows
+/// what is wrong beneath it.  ESC calls `on_exit`.
+pub fn init_keyboard<D>(
+    ui: &mut Ui<D>,
+    config: KeyboardConfig,
+    on_enter: Box<dyn Fn(String)>,
+    on_exit: Box<dyn Fn()>,
+) where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    let areas = areas(ui.bounds());
+    let baseline = |area: Rectangle| area.top_left + Point::new(4, LINE_HEIGHT as i32 - 3);
+
+    let entry = RefCell::new(Entry::new(&config.initial));
+    let title = Text::new(config.title.clone(), baseline(areas[0]));
+    let value = Text::new(entry.borrow().display(config.masked), baseline(areas[1]));
+    let error = Text::new("".to_string(), baseline(areas[2]));
+    let (value_text, error_text) = (value.text_ref(), error.text_ref());
+
+    ui.add_element(Box::new(title));
+    ui.add_element(Box::new(value));
+    ui.add_element(Box::new(error));
+
+    let on_key = Box::new(move |key: Key| {
+        if key == Key::Enter {
+            let text = entry.borrow().text();
+            match config.validate(&text) {
+                Ok(()) => on_enter(text),
+                Err(e) => *error_text.borrow_mut() = config.describe(e),
+            }
+        } else if key == Key::Exit {
+            on_exit();
+        } else if entry.borrow_mut().edit(key, config.max_len) {
+            *value_text.borrow_mut() = entry.borrow().display(config.masked);
+            error_text.borrow_mut().clear();
+        }
+    });
+
+    ui.add_element(Box::new(Keyboard::new(areas[3], on_key)));
+}
+
+/// Screen holding a keyboard, closed by entering valid text or by ESC.
+pub struct KeyboardScreen {
+    config: KeyboardConfig,
+    on_enter: Rc<dyn Fn(String)>,
+}
+
+impl KeyboardScreen {
+    pub fn new(config: KeyboardConfig, on_enter: Box<dyn Fn(String)>) -> Self {
+        Self {
+            config,
+            on_enter: on_enter.into(),
+        }
+    }
+}
+
+impl<D> Screen<D> for KeyboardScreen
+where
+    D: DrawTarget<Color = Rgb565> + 'static,
+    D::Error: Debug,
+{
+    fn build(&mut self, ui: &mut Ui<D>, navigation: &Navigation<D>) {
+        let on_enter = self.on_enter.clone();
+        let enter_navigation = navigation.clone();
+        let exit_navigation = navigation.clone();
+
+        init_keyboard(
+            ui,
+            self.config.clone(),
+            Box::new(move |text| {
+                (*on_enter)(text);
+                enter_navigation.pop();
+            }),
+            Box::new(move || exit_navigation.pop()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::{
+        calibration::TouchCalibration,
+        framebuffer::{assert_snapshot, Framebuffer, PALETTE},
+    };
+
+    #[test]
+    fn edits_at_the_cursor() {
+        let mut entry = Entry::new("ac");
+
+        for key in [Key::Left, Key::Char('b'), Key::Right, Key::Space] {
+            assert!(entry.edit(key, 4));
+        }
+        assert_eq!(entry.text(), "abc ");
+
+        // full, so nothing more is typed
+        entry.edit(Key::Char('d'), 4);
+        assert_eq!(entry.display(false), "abc |");
+
+        for key in [Key::Left, Key::Left, Key::Backspace] {
+            entry.edit(key, 4);
+        }
+        assert_eq!(entry.display(false), "a|c ");
+        assert_eq!(entry.display(true), "*|**");
+
+        // past the start
+        for _ in 0..3 {
+            entry.edit(Key::Backspace, 4);
+        }
+        assert_eq!(entry.display(false), "|c ");
+        assert!(!entry.edit(Key::Enter, 4));
+    }
+
+    #[test]
+    fn switches_layers() {
+        let typed = Rc::new(RefCell::new(Vec::new()));
+        let typed2 = typed.clone();
+        let mut keyboard = Keyboard::new(
+            Rectangle::new(Point::zero(), Size::new(320, 240)),
+            Box::new(move |key| typed2.borrow_mut().push(key)),
+        );
+
+        let mut press = |key: Key| {
+            let centre = keyboard.key_area(key).unwrap().center();
+            for event in [
+                TouchEvent::Down(centre.x, centre.y),
+                TouchEvent::Up(centre.x, centre.y),
+            ] {
+                let event = match event {
+                    TouchEvent::Down(..) => UiEvent::TouchEnter(event),
+                    _ => UiEvent::Tap(event),
+                };
+                UiElement::<Framebuffer>::handle_event(&mut keyboard, event);
+            }
+            keyboard.layer()
+        };
+
+        // shift is for one letter
+        assert_eq!(press(Key::Shift), KeyboardLayer::Upper);
+        assert_eq!(press(Key::Char('H')), KeyboardLayer::Lower);
+        press(Key::Char('i'));
+        assert_eq!(press(Key::Layer), KeyboardLayer::Symbols);
+        press(Key::Char('!'));
+        assert_eq!(press(Key::Shift), KeyboardLayer::MoreSymbols);
+        press(Key::Char('#'));
+        assert_eq!(press(Key::Layer), KeyboardLayer::Lower);
+
+        assert_eq!(
+            *typed.borrow(),
+            vec![
+                Key::Char('H'),
+                Key::Char('i'),
+                Key::Char('!'),
+                Key::Char('#')
+            ]
+        );
+    }
+
+    #[test]
+    fn enters_valid_text() {
+        let entered = Rc::new(RefCell::new(None));
+        let entered2 = entered.clone();
+        let mut display = Framebuffer::new(320, 240);
+        let mut ui = Ui::new(320, 240);
+        ui.touch_calibration(TouchCalibration::IDENTITY);
+
+        init_keyboard(
+            &mut ui,
+            KeyboardConfig::new("AP PASSWORD")
+                .length(8, 16)
+                .initial("omega"),
+            Box::new(move |text| *entered2.borrow_mut() = Some(text)),
+            Box::new(|| ()),
+        );
+        ui.draw(&mut display);
+
+        let keys = areas(ui.bounds())[3];
+        let press = |ui: &mut Ui<Framebuffer>, display: &mut Framebuffer, pressed: &[Key]| {
+            let mut layer = KeyboardLayer::Lower;
+            for key in pressed {
+                let (_, area) = key_areas(layer, keys)
+                    .into_iter()
+                    .find(|(k, _)| k == key)
+                    .unwrap();
+                ui.tap(area.center().x, area.center().y);
+                layer = match (key, layer) {
+                    (Key::Shift, KeyboardLayer::Lower) => KeyboardLayer::Upper,
+                    (Key::Layer, KeyboardLayer::Lower) => KeyboardLayer::Symbols,
+                    (Key::Layer, _) | (Key::Char(_), KeyboardLayer::Upper) => KeyboardLayer::Lower,
+                    _ => layer,
+                };
+            }
+            ui.draw(display);
+        };
+
+        // too short, so the error is shown instead
+        press(
+            &mut ui,
+            &mut display,
+            &[Key::Layer, Key::Char('9'), Key::Layer, Key::Enter],
+        );
+        assert_eq!(*entered.borrow(), None);
+        let header = display
+            .to_ascii(&PALETTE)
+            .lines()
+            .take(3 * LINE_HEIGHT as usize)
+            .map(|line| &line[..160])
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_snapshot("keyboard_error", &header);
+
+        let keyboard = display
+            .to_ascii(&PALETTE)
+            .lines()
+            .skip(100)
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_snapshot("keyboard_lower", &keyboard);
+
+        press(
+            &mut ui,
+            &mut display,
+            &[
+                Key::Left,
+                Key::Layer,
+                Key::Char('-'),
+                Key::Layer,
+                Key::Right,
+                Key::Char('q'),
+                Key::Backspace,
+                Key::Shift,
+                Key::Char('X'),
+                Key::Enter,
+            ],
+        );
+        assert_eq!(entered.borrow().as_deref(), Some("omega-9X"));
+    }
+}
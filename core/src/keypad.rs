@@ -0,0 +1,367 @@
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    pixelcolor::Rgb565,
+    primitives::Rectangle,
+};
+
+use crate::ui::{
+    button::Button,
+    layout::{column, place, Align, Grid},
+    screen::{Navigation, Screen},
+    text::Text,
+    ui::{Ui, UiDimension},
+};
+
+const KEYPAD_LABELS: [&str; 16] = [
+    "7", "8", "9", "DEL", "4", "5", "6", "CLR", "1", "2", "3", "x", "+/-", "0", ".", "ENT",
+];
+
+const KEY_SIZE: i16 = 26;
+const KEY_GAP: u32 = 2;
+const COLUMNS: usize = 4;
+const LINE_HEIGHT: i16 = 14;
+
+/// What a keypad is for, and the values it accepts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeypadConfig {
+    /// Shown above the value, e.g. `SEA LEVEL PRESSURE`
+    pub title: String,
+    /// Shown after the value, e.g. `inHg`
+    pub unit: &'static str,
+    pub min: f64,
+    pub max: f64,
+    /// Most digits after the decimal point, 0 for whole numbers
+    pub precision: usize,
+    /// Text to start with, e.g. the current value
+    pub initial: String,
+}
+
+impl KeypadConfig {
+    /// Any whole number.
+    pub fn new(title: &str, unit: &'static str) -> Self {
+        Self {
+            title: title.to_string(),
+            unit,
+            min: f64::MIN,
+            max: f64::MAX,
+            precision: 0,
+            initial: String::new(),
+        }
+    }
+
+    pub fn range(mut self, min: f64, max: f64) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    pub fn precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Start with `value`, to be edited or entered as is.
+    pub fn initial(mut self, value: f64) -> Self {
+        self.initial = format!("{:.*}", self.precision, value);
+        self
+    }
+
+    /// Parse and check `text`, returning the value it holds.
+    pub fn validate(&self, text: &str) -> Result<f64, KeypadError> {
+        if text.is_empty() || text == "-" {
+            return Err(KeypadError::Empty);
+        }
+
+        let value = text.parse::<f64>().map_err(|_| KeypadError::Malformed)?;
+
+        let decimals = text.split_once('.').map_or(0, |(_, d)| d.len());
+        if decimals > self.precision {
+            Err(KeypadError::TooPrecise)
+        } else if value < self.min {
+            Err(KeypadError::TooLow)
+        } else if value > self.max {
+            Err(KeypadError::TooHigh)
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// What is wrong, in terms of this keypad's limits.
+    pub fn describe(&self, error: KeypadError) -> String {
+        match error {
+            KeypadError::Empty => "ENTER A VALUE".to_string(),
+            KeypadError::Malformed => "NOT A NUMBER".to_string(),
+            KeypadError::TooPrecise if self.precision == 0 => "WHOLE NUMBERS ONLY".to_string(),
+            KeypadError::TooPrecise => format!("AT MOST {} DECIMALS", self.precision),
+            KeypadError::TooLow => format!("MIN {:.*} {}", self.precision, self.min, self.unit),
+            KeypadError::TooHigh => format!("MAX {:.*} {}", self.precision, self.max, self.unit),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeypadError {
+    Empty,
+    Malformed,
+    /// More digits after the decimal point than the precision allows
+    TooPrecise,
+    TooLow,
+    TooHigh,
+}
+
+/// Apply an editing key to `text`, returning false for keys that don't edit.
+fn edit(text: &mut String, key: &str) -> bool {
+    match key {
+        "DEL" => {
+            text.pop();
+        }
+        "CLR" => text.clear(),
+        "+/-" => {
+            if text.starts_with('-') {
+                text.remove(0);
+            } else {
+                text.insert(0, '-');
+            }
+        }
+        "." => {
+            if !text.contains('.') {
+                text.push('.');
+            }
+        }
+        digit if digit.len() == 1 && digit.chars().all(|c| c.is_ascii_digit()) => {
+            text.push_str(digit)
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Area of each key, in the order of `KEYPAD_LABELS`, centred in `area`.
+fn key_areas(area: Rectangle) -> Vec<Rectangle> {
+    let rows = KEYPAD_LABELS.len() / COLUMNS;
+    let extent = |count: usize| (KEY_SIZE as u32 + KEY_GAP) * count as u32 - KEY_GAP;
+    let keypad = place(
+        area,
+        Size::new(extent(COLUMNS), extent(rows)),
+        Align::Center,
+        Align::Center,
+    );
+
+    Grid::new(
+        [UiDimension::Fixed(KEY_SIZE); COLUMNS],
+        vec![UiDimension::Fixed(KEY_SIZE); rows],
+    )
+    .gap(KEY_GAP)
+    .layout(keypad)
+}
+
+/// Title, value and error lines above the keys.
+fn areas(bounds: Rectangle) -> Vec<Rectangle> {
+    column([
+        UiDimension::Fixed(LINE_HEIGHT),
+        UiDimension::Fixed(LINE_HEIGHT),
+        UiDimension::Fixed(LINE_HEIGHT),
+        UiDimension::Auto,
+    ])
+    .gap(2)
+    .layout(bounds)
+}
+
+/// Keypad for entering a number, as described by `config`.
+///
+/// ENT calls `on_enter` with the value if it is valid, and otherwise shows
+/// what is wrong beneath it.  `x` calls `on_exit`.
+pub fn init_keypad<D>(
+    ui: &mut Ui<D>,
+    config: KeypadConfig,
+    on_enter: Box<dyn Fn(f64)>,
+    on_exit: Box<dyn Fn()>,
+) where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    let areas = areas(ui.bounds());
+    let keys = key_areas(areas[3]);
+    let baseline = |area: Rectangle| area.top_left + Point::new(4, LINE_HEIGHT as i32 - 3);
+
+    let entry = Rc::new(RefCell::new(config.initial.clone()));
+    let title = Text::new(config.title.clone(), baseline(areas[0]));
+    let value = Text::new(
+        format!("{} {}", config.initial, config.unit),
+        baseline(areas[1]),
+    );
+    let error = Text::new("".to_string(), baseline(areas[2]));
+    let (value_text, error_text) = (value.text_ref(), error.text_ref());
+
+    ui.add_element(Box::new(title));
+    ui.add_element(Box::new(value));
+    ui.add_element(Box::new(error));
+
+    let click_handler = Rc::new(move |label: &str| {
+        if label == "ENT" {
+            let result = config.validate(&entry.borrow());
+            match result {
+                Ok(value) => on_enter(value),
+                Err(e) => *error_text.borrow_mut() = config.describe(e),
+            }
+        } else if label == "x" {
+            on_exit();
+        } else if edit(&mut entry.borrow_mut(), label) {
+            *value_text.borrow_mut() = format!("{} {}", entry.borrow(), config.unit);
+            error_text.borrow_mut().clear();
+        }
+    });
+
+    for (label, area) in KEYPAD_LABELS.iter().zip(keys) {
+        let label = label.to_string();
+        let label2 = label.clone();
+
+        let click_handler = click_handler.clone();
+
+        ui.add_element(Box::new(Button::new(
+            area.top_left,
+            area.size,
+            label2,
+            Box::new(move || {
+                (click_handler)(&label);
+            }),
+        )));
+    }
+}
+
+/// Screen holding a keypad, closed by entering a valid value or by `x`.
+pub struct KeypadScreen {
+    config: KeypadConfig,
+    on_enter: Rc<dyn Fn(f64)>,
+}
+
+impl KeypadScreen {
+    pub fn new(config: KeypadConfig, on_enter: Box<dyn Fn(f64)>) -> Self {
+        Self {
+            config,
+            on_enter: on_enter.into(),
+        }
+    }
+}
+
+impl<D> Screen<D> for KeypadScreen
+where
+    D: DrawTarget<Color = Rgb565> + 'static,
+    D::Error: Debug,
+{
+    fn build(&mut self, ui: &mut Ui<D>, navigation: &Navigation<D>) {
+        let on_enter = self.on_enter.clone();
+        let enter_navigation = navigation.clone();
+        let exit_navigation = navigation.clone();
+
+        init_keypad(
+            ui,
+            self.config.clone(),
+            Box::new(move |value| {
+                (*on_enter)(value);
+                enter_navigation.pop();
+            }),
+            Box::new(move || exit_navigation.pop()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::ui::{
+        calibration::TouchCalibration,
+        framebuffer::{assert_snapshot, Framebuffer, PALETTE},
+    };
+
+    fn psl() -> KeypadConfig {
+        KeypadConfig::new("SEA LEVEL PRESSURE", "inHg")
+            .range(25.0, 32.0)
+            .precision(2)
+    }
+
+    #[test]
+    fn validates_entries() {
+        let config = psl();
+
+        assert_eq!(config.validate("30.01"), Ok(30.01));
+        assert_eq!(config.validate("30."), Ok(30.0));
+        assert_eq!(config.validate(""), Err(KeypadError::Empty));
+        assert_eq!(config.validate("."), Err(KeypadError::Malformed));
+        assert_eq!(config.validate("30.001"), Err(KeypadError::TooPrecise));
+        assert_eq!(config.validate("-30"), Err(KeypadError::TooLow));
+        assert_eq!(config.validate("99"), Err(KeypadError::TooHigh));
+
+        assert_eq!(config.describe(KeypadError::TooHigh), "MAX 32.00 inHg");
+        assert_eq!(
+            KeypadConfig::new("N", "").describe(KeypadError::TooPrecise),
+            "WHOLE NUMBERS ONLY"
+        );
+    }
+
+    #[test]
+    fn edits_entries() {
+        let mut text = String::new();
+
+        for key in ["1", ".", "2", ".", "5", "+/-"] {
+            assert!(edit(&mut text, key));
+        }
+        assert_eq!(text, "-1.25");
+
+        for key in ["DEL", "+/-"] {
+            edit(&mut text, key);
+        }
+        assert_eq!(text, "1.2");
+
+        assert!(!edit(&mut text, "ENT"));
+        edit(&mut text, "CLR");
+        assert_eq!(text, "");
+    }
+
+    #[test]
+    fn enters_valid_values() {
+        let entered = Rc::new(Cell::new(None));
+        let entered2 = entered.clone();
+        let mut display = Framebuffer::new(320, 240);
+        let mut ui = Ui::new(320, 240);
+        ui.touch_calibration(TouchCalibration::IDENTITY);
+
+        init_keypad(
+            &mut ui,
+            psl().initial(29.92),
+            Box::new(move |value| entered2.set(Some(value))),
+            Box::new(|| ()),
+        );
+        ui.draw(&mut display);
+
+        let keys = key_areas(areas(ui.bounds())[3]);
+        let press = |ui: &mut Ui<Framebuffer>, display: &mut Framebuffer, labels: &[&str]| {
+            for label in labels {
+                let i = KEYPAD_LABELS.iter().position(|l| l == label).unwrap();
+                let centre = keys[i].center();
+                ui.tap(centre.x, centre.y);
+            }
+            ui.draw(display);
+        };
+
+        // too high, so the error is shown instead
+        press(&mut ui, &mut display, &["CLR", "3", "3", "ENT"]);
+        assert_eq!(entered.get(), None);
+        let header = display
+            .to_ascii(&PALETTE)
+            .lines()
+            .take(3 * LINE_HEIGHT as usize)
+            .map(|line| &line[..160])
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_snapshot("keypad_error", &header);
+
+        press(&mut ui, &mut display, &["DEL", "0", ".", "0", "1", "ENT"]);
+        assert_eq!(entered.get(), Some(30.01));
+    }
+}
@@ -0,0 +1,11 @@
+pub mod config;
+pub mod console;
+pub mod control_panel;
+pub mod dashboard;
+pub mod keyboard;
+pub mod keypad;
+pub mod settings;
+pub mod storage;
+pub mod ui;
+
+pub use rocket_protocol::command;
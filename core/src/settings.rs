@@ -0,0 +1,303 @@
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+use embedded_graphics::{
+    draw_target::DrawTarget, geometry::Point, pixelcolor::Rgb565, primitives::Rectangle,
+};
+
+use crate::{
+    config::{BasestationConfig, ConfigField, FieldKind, FIELDS},
+    keyboard::{KeyboardConfig, KeyboardScreen},
+    keypad::{KeypadConfig, KeypadScreen},
+    storage::KeyValueStore,
+    ui::{
+        button::Button,
+        layout::{column, row, Insets},
+        list::List,
+        screen::{Navigation, Screen},
+        text::Text,
+        theme::{self, Theme, THEMES},
+        ui::{Ui, UiDimension},
+    },
+};
+
+const BUTTON_HEIGHT: i16 = 25;
+const BUTTON_WIDTH: i16 = 50;
+const TITLE_HEIGHT: i16 = 14;
+
+/// Title, content and button row of a settings screen.
+fn areas(bounds: Rectangle) -> Vec<Rectangle> {
+    column([
+        UiDimension::Fixed(TITLE_HEIGHT),
+        UiDimension::Auto,
+        UiDimension::Fixed(BUTTON_HEIGHT),
+    ])
+    .gap(4)
+    .padding(Insets::all(4))
+    .layout(bounds)
+}
+
+/// Settings screen: a list of themes, and buttons to go back, to
+/// calibrate the touch panel and to edit the [`BasestationConfig`].
+///
+/// Choosing a theme selects it straight away and calls `on_theme`, which
+/// should save it and redraw the display.
+pub fn init_settings<D>(
+    ui: &mut Ui<D>,
+    on_theme: Box<dyn Fn(&'static Theme)>,
+    on_calibrate: Box<dyn Fn()>,
+    on_config: Box<dyn Fn()>,
+    on_exit: Box<dyn Fn()>,
+) where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    let areas = areas(ui.bounds());
+
+    ui.add_element(Box::new(Text::new(
+        "THEME".to_string(),
+        areas[0].top_left + Point::new(0, TITLE_HEIGHT as i32 - 4),
+    )));
+
+    let mut themes = List::new(
+        areas[1],
+        Box::new(move |i| {
+            let theme = &THEMES[i];
+            theme::select(theme.name);
+            on_theme(theme);
+        }),
+    );
+    themes
+        .items_ref()
+        .borrow_mut()
+        .extend(THEMES.iter().map(|theme| theme.name.to_string()));
+    themes.select(THEMES.iter().position(|t| t.name == theme::current().name));
+    ui.add_element(Box::new(themes));
+
+    let buttons = [("BACK", on_exit), ("CAL", on_calibrate), ("CFG", on_config)];
+    let cells = row(buttons.iter().map(|_| UiDimension::Fixed(BUTTON_WIDTH)))
+        .gap(4)
+        .layout(areas[2]);
+
+    for ((label, on_click), area) in buttons.into_iter().zip(cells) {
+        ui.add_element(Box::new(Button::new(
+            area.top_left,
+            area.size,
+            label.to_string(),
+            on_click,
+        )));
+    }
+}
+
+/// The [`BasestationConfig`] a field at a time, saved to `store` as each is
+/// changed.
+///
+/// Flags toggle when tapped, numbers open a keypad and text a keyboard.
+/// Changes take effect after a restart, which the screen reminds you of.
+pub struct ConfigScreen<S> {
+    config: Rc<RefCell<BasestationConfig>>,
+    store: Rc<RefCell<S>>,
+    items: Option<Rc<RefCell<Vec<String>>>>,
+}
+
+impl<S> ConfigScreen<S> {
+    pub fn new(config: Rc<RefCell<BasestationConfig>>, store: Rc<RefCell<S>>) -> Self {
+        Self {
+            config,
+            store,
+            items: None,
+        }
+    }
+}
+
+fn config_items(config: &BasestationConfig) -> Vec<String> {
+    FIELDS
+        .iter()
+        .map(|field| format!("{}: {}", field.label, config.display(field)))
+        .collect()
+}
+
+/// Set `field` to `value` and save the config, describing the outcome in
+/// `status`.
+fn edit_config<S>(
+    config: &RefCell<BasestationConfig>,
+    store: &RefCell<S>,
+    status: &RefCell<String>,
+    field: &ConfigField,
+    value: &str,
+) where
+    S: KeyValueStore,
+{
+    let result = config.borrow_mut().set(field.key, value);
+
+    *status.borrow_mut() = match result {
+        Ok(()) => match config.borrow().save(&mut *store.borrow_mut()) {
+            Ok(()) => "RESTART TO APPLY".to_string(),
+            Err(e) => {
+                log::error!("unable to save config: {:?}", e);
+                "UNABLE TO SAVE".to_string()
+            }
+        },
+        Err(_) => format!("INVALID {}", field.label),
+    };
+}
+
+impl<D, S> Screen<D> for ConfigScreen<S>
+where
+    D: DrawTarget<Color = Rgb565> + 'static,
+    D::Error: Debug,
+    S: KeyValueStore + 'static,
+{
+    fn build(&mut self, ui: &mut Ui<D>, navigation: &Navigation<D>) {
+        let areas = areas(ui.bounds());
+        let cells = row([UiDimension::Fixed(BUTTON_WIDTH), UiDimension::Auto])
+            .gap(8)
+            .layout(areas[2]);
+
+        ui.add_element(Box::new(Text::new(
+            "CONFIG".to_string(),
+            areas[0].top_left + Point::new(0, TITLE_HEIGHT as i32 - 4),
+        )));
+
+        // beside BACK
+        let status = Text::new(
+            String::new(),
+            cells[1].top_left + Point::new(0, BUTTON_HEIGHT as i32 / 2 + 4),
+        );
+        let status_text = status.text_ref();
+        ui.add_element(Box::new(status));
+
+        let (config, store) = (self.config.clone(), self.store.clone());
+        let select_navigation = navigation.clone();
+        let list = List::new(
+            areas[1],
+            Box::new(move |i| {
+                let field = &FIELDS[i];
+                let value = config.borrow().get(field.key);
+                let (config, store, status) = (config.clone(), store.clone(), status_text.clone());
+                let on_enter =
+                    move |value: &str| edit_config(&config, &store, &status, field, value);
+
+                match field.kind {
+                    FieldKind::Flag => on_enter(if value == "on" { "off" } else { "on" }),
+                    FieldKind::Number { min, max } => {
+                        let keypad = KeypadConfig::new(field.label, "")
+                            .range(min as f64, max as f64)
+                            .initial(value.parse().unwrap_or(min as f64));
+                        select_navigation.push(KeypadScreen::new(
+                            keypad,
+                            Box::new(move |number| on_enter(&number.to_string())),
+                        ));
+                    }
+                    FieldKind::Text {
+                        min_len,
+                        max_len,
+                        optional,
+                        masked,
+                    } => {
+                        let min_len = if optional { 0 } else { min_len };
+                        let mut keyboard = KeyboardConfig::new(field.label)
+                            .length(min_len, max_len)
+                            .initial(&value);
+                        if masked {
+                            keyboard = keyboard.masked();
+                        }
+                        select_navigation.push(KeyboardScreen::new(
+                            keyboard,
+                            Box::new(move |text| on_enter(&text)),
+                        ));
+                    }
+                    FieldKind::Mac => {
+                        let keyboard = KeyboardConfig::new(field.label)
+                            .length(17, 17)
+                            .initial(&value);
+                        select_navigation.push(KeyboardScreen::new(
+                            keyboard,
+                            Box::new(move |text| on_enter(&text)),
+                        ));
+                    }
+                }
+            }),
+        );
+        *list.items_ref().borrow_mut() = config_items(&self.config.borrow());
+        self.items = Some(list.items_ref());
+        ui.add_element(Box::new(list));
+
+        let exit_navigation = navigation.clone();
+        ui.add_element(Box::new(Button::new(
+            cells[0].top_left,
+            cells[0].size,
+            "BACK".to_string(),
+            Box::new(move || exit_navigation.pop()),
+        )));
+    }
+
+    fn update(&mut self, _ui: &mut Ui<D>) {
+        // flags change in place, without leaving the screen
+        if let Some(items) = &self.items {
+            let current = config_items(&self.config.borrow());
+            if *items.borrow() != current {
+                *items.borrow_mut() = current;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{
+        storage::MemoryStore,
+        ui::{calibration::TouchCalibration, framebuffer::Framebuffer, screen::Navigator},
+    };
+
+    #[test]
+    fn selects_theme() {
+        let chosen = Rc::new(RefCell::new(None));
+        let chosen2 = chosen.clone();
+        let mut display = Framebuffer::new(320, 240);
+        let mut ui = Ui::new(320, 240);
+        ui.touch_calibration(TouchCalibration::IDENTITY);
+
+        init_settings(
+            &mut ui,
+            Box::new(move |theme: &'static Theme| *chosen2.borrow_mut() = Some(theme.name)),
+            Box::new(|| ()),
+            Box::new(|| ()),
+            Box::new(|| ()),
+        );
+        ui.draw(&mut display);
+
+        // the second row of the list, below the title
+        ui.tap(20, 40);
+
+        assert_eq!(*chosen.borrow(), Some("DAYLIGHT"));
+        assert_eq!(theme::current().name, "DAYLIGHT");
+
+        theme::select(THEMES[0].name);
+    }
+
+    #[test]
+    fn edits_and_saves_config() {
+        let config = Rc::new(RefCell::new(BasestationConfig::default()));
+        let store = Rc::new(RefCell::new(MemoryStore::new()));
+        let mut navigator = Navigator::<Framebuffer>::new(320, 240);
+        navigator.touch_calibration(TouchCalibration::IDENTITY);
+        navigator
+            .navigation()
+            .push(ConfigScreen::new(config.clone(), store.clone()));
+        navigator.update();
+
+        // WEB, the first row, toggles in place
+        navigator.tap(20, 26);
+        assert_eq!(navigator.depth(), 1);
+        assert!(config.borrow().web_services);
+        let saved = BasestationConfig::load(&*store.borrow()).unwrap().unwrap();
+        assert_eq!(saved, *config.borrow());
+
+        // CHANNEL opens a keypad
+        navigator.tap(20, 74);
+        assert_eq!(navigator.depth(), 2);
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "nvs")]
+pub mod nvs;
+
+/// Longest key NVS accepts.
+pub const MAX_KEY_LENGTH: usize = 15;
+
+#[derive(Copy, Clone, Debug)]
+pub enum StorageError {
+    /// Keys are limited to [`MAX_KEY_LENGTH`] bytes
+    KeyTooLong,
+    /// The stored value does not fit the buffer it is read into
+    BufferTooSmall,
+    /// The stored value is not in the expected format
+    Malformed,
+    /// Error code from the underlying store
+    Backend(i32),
+}
+
+/// Small values kept across restarts, such as settings and calibration.
+///
+/// Implemented for NVS on the device and by [`MemoryStore`] on the host.
+pub trait KeyValueStore {
+    /// Read the value stored under `key` into `buffer`, returning its
+    /// length, or `None` if nothing is stored.
+    fn get(&self, key: &str, buffer: &mut [u8]) -> Result<Option<usize>, StorageError>;
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), StorageError>;
+
+    /// Remove `key`, which need not be present.
+    fn remove(&mut self, key: &str) -> Result<(), StorageError>;
+}
+
+fn check_key(key: &str) -> Result<(), StorageError> {
+    if key.len() > MAX_KEY_LENGTH {
+        Err(StorageError::KeyTooLong)
+    } else {
+        Ok(())
+    }
+}
+
+/// Store held in memory, for tests and running screens on the host.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    values: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl KeyValueStore for MemoryStore {
+    fn get(&self, key: &str, buffer: &mut [u8]) -> Result<Option<usize>, StorageError> {
+        check_key(key)?;
+
+        match self.values.get(key) {
+            Some(value) if value.len() > buffer.len() => Err(StorageError::BufferTooSmall),
+            Some(value) => {
+                buffer[..value.len()].copy_from_slice(value);
+                Ok(Some(value.len()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), StorageError> {
+        check_key(key)?;
+        self.values.insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), StorageError> {
+        check_key(key)?;
+        self.values.remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_store_round_trip() {
+        let mut store = MemoryStore::new();
+        let mut buffer = [0_u8; 4];
+
+        assert!(matches!(store.get("theme", &mut buffer), Ok(None)));
+
+        store.set("theme", b"DAY").unwrap();
+        assert!(matches!(store.get("theme", &mut buffer), Ok(Some(3))));
+        assert_eq!(&buffer[..3], b"DAY");
+
+        assert!(matches!(
+            store.get("theme", &mut [0_u8; 2]),
+            Err(StorageError::BufferTooSmall)
+        ));
+        assert!(matches!(
+            store.set("a_key_that_is_too_long", b""),
+            Err(StorageError::KeyTooLong)
+        ));
+
+        store.remove("theme").unwrap();
+        assert!(store.is_empty());
+    }
+}
@@ -0,0 +1,39 @@
+use esp_idf_svc::{
+    nvs::{EspNvs, NvsPartitionId},
+    sys::{EspError, ESP_ERR_NVS_INVALID_LENGTH},
+};
+
+use super::{check_key, KeyValueStore, StorageError};
+
+impl From<EspError> for StorageError {
+    fn from(e: EspError) -> Self {
+        if e.code() == ESP_ERR_NVS_INVALID_LENGTH as i32 {
+            StorageError::BufferTooSmall
+        } else {
+            StorageError::Backend(e.code())
+        }
+    }
+}
+
+/// Values are stored as NVS blobs in the namespace the `EspNvs` was opened with.
+impl<T> KeyValueStore for EspNvs<T>
+where
+    T: NvsPartitionId,
+{
+    fn get(&self, key: &str, buffer: &mut [u8]) -> Result<Option<usize>, StorageError> {
+        check_key(key)?;
+        Ok(self.get_blob(key, buffer)?.map(|value| value.len()))
+    }
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), StorageError> {
+        check_key(key)?;
+        self.set_blob(key, value)?;
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), StorageError> {
+        check_key(key)?;
+        EspNvs::remove(self, key)?;
+        Ok(())
+    }
+}
@@ -1,59 +1,65 @@
+use std::fmt::Debug;
+
 use embedded_graphics::{
+    draw_target::DrawTarget,
     geometry::{Dimensions, Point, Size},
-    mono_font::{ascii::FONT_6X10, MonoTextStyle},
-    pixelcolor::{Rgb565, RgbColor},
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb565,
     primitives::{Primitive, PrimitiveStyleBuilder, Rectangle},
     text::Text,
     Drawable,
 };
-use ez_cyd_rs::CydDisplay;
 
-use super::ui::{ColorTheme, UiElement, UiEvent};
+use super::{
+    theme,
+    ui::{UiElement, UiEvent},
+};
 
 pub struct Button {
     point: Point,
     size: Size,
     text: String,
-    color_theme: ColorTheme,
-    hover_color_theme: ColorTheme,
     dirty: bool,
     hover: bool,
-    on_click: Box<dyn Fn() -> ()>,
+    on_click: Box<dyn Fn()>,
+    on_long_press: Option<Box<dyn Fn()>>,
 }
 
 impl Button {
-    pub fn new(point: Point, size: Size, text: String, on_click: Box<dyn Fn() -> ()>) -> Self {
+    pub fn new(point: Point, size: Size, text: String, on_click: Box<dyn Fn()>) -> Self {
         Self {
             point,
             size,
             text,
             dirty: true,
             hover: false,
-            color_theme: ColorTheme {
-                text_color: Rgb565::GREEN,
-                outline: Rgb565::GREEN,
-                ..ColorTheme::default()
-            },
-            hover_color_theme: ColorTheme {
-                fill: Rgb565::GREEN,
-                outline: Rgb565::GREEN,
-                ..ColorTheme::default()
-            },
             on_click,
+            on_long_press: None,
         }
     }
+
+    /// Also run `on_long_press` when held, instead of waiting for a tap.
+    pub fn with_long_press(mut self, on_long_press: Box<dyn Fn()>) -> Self {
+        self.on_long_press = Some(on_long_press);
+        self
+    }
 }
 
-impl UiElement for Button {
+impl<D> UiElement<D> for Button
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
     fn dirty(&self) -> bool {
         self.dirty
     }
 
-    fn draw(&mut self, display: &mut CydDisplay) {
+    fn draw(&mut self, display: &mut D) {
+        let current = theme::current();
         let theme = if self.hover {
-            &self.hover_color_theme
+            current.inverted()
         } else {
-            &self.color_theme
+            current.normal()
         };
 
         let style = PrimitiveStyleBuilder::new()
@@ -67,7 +73,7 @@ impl UiElement for Button {
             .draw(display)
             .unwrap();
 
-        let text_style = MonoTextStyle::new(&FONT_6X10, theme.text_color);
+        let text_style = MonoTextStyle::new(current.font, theme.text_color);
 
         let mut text = Text::new(&self.text, self.point, text_style);
 
@@ -91,9 +97,12 @@ impl UiElement for Button {
         }
     }
 
+    fn handles_long_press(&self) -> bool {
+        self.on_long_press.is_some()
+    }
+
     fn handle_event(&mut self, event: UiEvent) {
         // log::info!("Ui Event: {:?}", event);
-        self.dirty = true;
         match event {
             UiEvent::TouchEnter(_) => {
                 self.hover = true;
@@ -105,6 +114,15 @@ impl UiElement for Button {
                 self.hover = false;
                 (*self.on_click)();
             }
+            UiEvent::LongPress(_) => match &self.on_long_press {
+                Some(on_long_press) => {
+                    self.hover = false;
+                    (*on_long_press)();
+                }
+                None => return,
+            },
+            UiEvent::Drag(_) => return,
         }
+        self.dirty = true;
     }
 }
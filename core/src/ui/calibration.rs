@@ -0,0 +1,686 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    rc::Rc,
+};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    pixelcolor::Rgb565,
+    primitives::{Circle, Line, Primitive, PrimitiveStyle, Rectangle},
+    Drawable,
+};
+
+use super::{
+    compositor::union,
+    gesture::{Z_RELEASE_THRESHOLD, Z_THRESHOLD},
+    screen::{Navigation, Screen},
+    text::Text,
+    theme,
+    ui::{Ui, UiElement, UiEvent},
+};
+use crate::storage::{KeyValueStore, StorageError};
+
+/// Storage key of the calibration.
+const STORAGE_KEY: &str = "touch_cal";
+/// Distance of the outer targets from the edges of the screen.
+const TARGET_INSET: i32 = 30;
+/// Samples averaged for each target, so a brushed target is not taken.
+const MIN_SAMPLES: usize = 4;
+const CROSSHAIR_SIZE: i32 = 10;
+/// Furthest a target may be, in pixels, from where its reading maps to.
+pub const MAX_RESIDUAL: f64 = 12.0;
+/// Most one axis may be stretched against the other, as a panel is
+/// never that far from square to the display.
+const MAX_ASPECT: f64 = 4.0;
+/// Furthest the confirming touch may land from its target, in pixels.
+pub const CONFIRM_RADIUS: f64 = 15.0;
+
+const TARGETS_PROMPT: &str = "TOUCH THE CENTRE OF EACH TARGET";
+const CONFIRM_PROMPT: &str = "TOUCH THE TARGET AGAIN TO CONFIRM";
+
+/// A raw reading and the screen position it should map to.
+pub type CalibrationSample = ((f64, f64), (f64, f64));
+
+/// Map from raw touch panel readings to screen coordinates, with
+/// `x = a tx + b ty + c` and `y = d tx + e ty + f`.
+///
+/// Being affine, it allows for a panel that is rotated or skewed against
+/// the display as well as scaled and offset.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TouchCalibration {
+    pub x: [f64; 3],
+    pub y: [f64; 3],
+}
+
+impl TouchCalibration {
+    /// Readings taken as screen coordinates, as in tests.
+    pub const IDENTITY: Self = Self {
+        x: [1.0, 0.0, 0.0],
+        y: [0.0, 1.0, 0.0],
+    };
+
+    /// Screen position of the reading `tx`, `ty`.
+    pub fn apply(&self, tx: f64, ty: f64) -> (f64, f64) {
+        let [a, b, c] = self.x;
+        let [d, e, f] = self.y;
+        (a * tx + b * ty + c, d * tx + e * ty + f)
+    }
+
+    /// Least squares fit to `samples`.
+    ///
+    /// Needs at least three samples not in a line; returns `None` otherwise.
+    pub fn fit(samples: &[CalibrationSample]) -> Option<Self> {
+        // normal equations: sum(r r') k = sum(r s) for r = (tx, ty, 1)
+        let mut m = [[0.0; 3]; 3];
+        let mut vx = [0.0; 3];
+        let mut vy = [0.0; 3];
+
+        for ((tx, ty), (x, y)) in samples {
+            let r = [*tx, *ty, 1.0];
+            for i in 0..3 {
+                for j in 0..3 {
+                    m[i][j] += r[i] * r[j];
+                }
+                vx[i] += r[i] * x;
+                vy[i] += r[i] * y;
+            }
+        }
+
+        Some(Self {
+            x: solve(m, vx)?,
+            y: solve(m, vy)?,
+        })
+    }
+
+    /// Whether the map could be a real panel: finite, not folding the
+    /// screen onto a line and not stretching one axis far beyond the other.
+    pub fn is_plausible(&self) -> bool {
+        let [a, b, _] = self.x;
+        let [d, e, _] = self.y;
+        let (x_scale, y_scale) = (a.hypot(d), b.hypot(e));
+
+        self.x.iter().chain(self.y.iter()).all(|k| k.is_finite())
+            && (a * e - b * d).abs() > 1e-9
+            && x_scale.max(y_scale) <= x_scale.min(y_scale) * MAX_ASPECT
+    }
+
+    /// Furthest distance, in pixels, from a sample's screen position to
+    /// where its reading maps to.
+    pub fn residual(&self, samples: &[CalibrationSample]) -> f64 {
+        samples
+            .iter()
+            .map(|((tx, ty), (x, y))| {
+                let (mx, my) = self.apply(*tx, *ty);
+                (mx - x).hypot(my - y)
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// The calibration saved by [`TouchCalibration::save`], if there is one.
+    /// One that is not [plausible](Self::is_plausible) is
+    /// [`StorageError::Malformed`], so the screen is calibrated again.
+    pub fn load<S>(store: &S) -> Result<Option<Self>, StorageError>
+    where
+        S: KeyValueStore + ?Sized,
+    {
+        let mut buffer = [0_u8; 48];
+
+        match store.get(STORAGE_KEY, &mut buffer)? {
+            Some(48) => {
+                let mut values = buffer
+                    .chunks_exact(8)
+                    .map(|b| f64::from_le_bytes(b.try_into().unwrap()));
+                let mut next = || values.next().unwrap();
+
+                let calibration = Self {
+                    x: [next(), next(), next()],
+                    y: [next(), next(), next()],
+                };
+                if calibration.is_plausible() {
+                    Ok(Some(calibration))
+                } else {
+                    Err(StorageError::Malformed)
+                }
+            }
+            Some(_) => Err(StorageError::Malformed),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save<S>(&self, store: &mut S) -> Result<(), StorageError>
+    where
+        S: KeyValueStore + ?Sized,
+    {
+        let mut buffer = [0_u8; 48];
+
+        for (chunk, value) in buffer
+            .chunks_exact_mut(8)
+            .zip(self.x.iter().chain(self.y.iter()))
+        {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+
+        store.set(STORAGE_KEY, &buffer)
+    }
+}
+
+fn determinant(m: [[f64; 3]; 3]) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+/// Solve `m k = v` by Cramer's rule, or `None` if `m` is singular.
+fn solve(m: [[f64; 3]; 3], v: [f64; 3]) -> Option<[f64; 3]> {
+    let det = determinant(m);
+
+    if det.abs() < 1e-12 {
+        return None;
+    }
+
+    let mut k = [0.0; 3];
+    for (col, k) in k.iter_mut().enumerate() {
+        let mut replaced = m;
+        for row in 0..3 {
+            replaced[row][col] = v[row];
+        }
+        *k = determinant(replaced) / det;
+    }
+
+    Some(k)
+}
+
+/// Where the targets are shown on a `width` by `height` screen: near each
+/// corner and in the middle.
+pub fn targets(width: u16, height: u16) -> [Point; 5] {
+    let (right, bottom) = (width as i32 - TARGET_INSET, height as i32 - TARGET_INSET);
+
+    [
+        Point::new(TARGET_INSET, TARGET_INSET),
+        Point::new(right, TARGET_INSET),
+        Point::new(right, bottom),
+        Point::new(TARGET_INSET, bottom),
+        Point::new(width as i32 / 2, height as i32 / 2),
+    ]
+}
+
+/// Where the target confirming a calibration is shown, away from the
+/// [`targets`] it was fitted to.
+pub fn confirm_target(width: u16, height: u16) -> Point {
+    Point::new(width as i32 / 4, height as i32 * 3 / 4)
+}
+
+/// Collects a reading for each target in turn from raw touch samples, then
+/// one more through the fitted calibration to confirm it.
+pub struct Calibrator {
+    targets: Vec<Point>,
+    confirm: Point,
+    readings: Vec<(f64, f64)>,
+    /// Fitted to the readings, waiting for the confirming touch
+    fitted: Option<TouchCalibration>,
+    /// Sum of the readings of the press in progress, and their count
+    press: Option<(f64, f64, usize)>,
+}
+
+impl Calibrator {
+    pub fn new(targets: &[Point], confirm: Point) -> Self {
+        Self {
+            targets: targets.to_vec(),
+            confirm,
+            readings: Vec::new(),
+            fitted: None,
+            press: None,
+        }
+    }
+
+    /// The target to touch next.
+    pub fn target(&self) -> Option<Point> {
+        match self.fitted {
+            Some(_) => Some(self.confirm),
+            None => self.targets.get(self.readings.len()).copied(),
+        }
+    }
+
+    /// Whether the next touch confirms the fitted calibration.
+    pub fn confirming(&self) -> bool {
+        self.fitted.is_some()
+    }
+
+    /// Take a raw sample, returning the calibration once the last target
+    /// has been touched and the confirming touch lands on its target
+    /// through it.
+    ///
+    /// If the readings can't be fitted, as when the same spot was touched
+    /// each time, fit too loosely or the confirming touch misses, they are
+    /// discarded and the targets start over.
+    pub fn sample(&mut self, touch: (f64, f64, f64)) -> Option<TouchCalibration> {
+        let (tx, ty, tz) = touch;
+
+        match self.press.as_mut() {
+            None if tz >= Z_THRESHOLD => self.press = Some((tx, ty, 1)),
+            Some((sx, sy, n)) if tz >= Z_RELEASE_THRESHOLD => {
+                *sx += tx;
+                *sy += ty;
+                *n += 1;
+            }
+            Some((sx, sy, n)) => {
+                let (sx, sy, n) = (*sx, *sy, *n);
+                self.press = None;
+
+                if n < MIN_SAMPLES {
+                    return None;
+                }
+                let reading = (sx / n as f64, sy / n as f64);
+
+                if let Some(calibration) = self.fitted.take() {
+                    return self.confirm(calibration, reading);
+                }
+
+                self.readings.push(reading);
+                if self.readings.len() == self.targets.len() {
+                    self.fitted = self.fit();
+                }
+            }
+            None => (),
+        }
+
+        None
+    }
+
+    fn fit(&mut self) -> Option<TouchCalibration> {
+        let samples: Vec<_> = self
+            .readings
+            .iter()
+            .zip(self.targets.iter())
+            .map(|(reading, target)| (*reading, (target.x as f64, target.y as f64)))
+            .collect();
+        self.readings.clear();
+
+        let calibration = TouchCalibration::fit(&samples);
+        match calibration {
+            Some(calibration)
+                if calibration.is_plausible() && calibration.residual(&samples) <= MAX_RESIDUAL =>
+            {
+                Some(calibration)
+            }
+            _ => {
+                log::warn!("touch calibration failed, starting over");
+                None
+            }
+        }
+    }
+
+    fn confirm(
+        &mut self,
+        calibration: TouchCalibration,
+        reading: (f64, f64),
+    ) -> Option<TouchCalibration> {
+        let (x, y) = calibration.apply(reading.0, reading.1);
+        let (cx, cy) = (self.confirm.x as f64, self.confirm.y as f64);
+
+        if (x - cx).hypot(y - cy) <= CONFIRM_RADIUS {
+            Some(calibration)
+        } else {
+            log::warn!("touch calibration not confirmed, starting over");
+            None
+        }
+    }
+}
+
+/// Crosshair drawn over the target to touch.
+struct Crosshair {
+    area: Rectangle,
+    target: Rc<Cell<Option<Point>>>,
+    last_drawn: Option<Option<Point>>,
+}
+
+impl<D> UiElement<D> for Crosshair
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    fn handle_event(&mut self, _event: UiEvent) {}
+
+    fn dirty(&self) -> bool {
+        self.last_drawn != Some(self.target.get())
+    }
+
+    /// Covers the crosshair and where it was last drawn.
+    fn bounding_box(&self) -> Rectangle {
+        let around = |target: Option<Point>| {
+            target.map_or(Rectangle::zero(), |target| {
+                Rectangle::with_center(target, Size::new_equal(2 * CROSSHAIR_SIZE as u32 + 1))
+            })
+        };
+
+        union(
+            &around(self.target.get()),
+            &around(self.last_drawn.flatten()),
+        )
+        .intersection(&self.area)
+    }
+
+    fn draw(&mut self, display: &mut D) {
+        let theme = theme::current();
+        let target = self.target.get();
+
+        if let Some(Some(previous)) = self.last_drawn {
+            Rectangle::with_center(previous, Size::new_equal(2 * CROSSHAIR_SIZE as u32 + 1))
+                .into_styled(PrimitiveStyle::with_fill(theme.background))
+                .draw(display)
+                .unwrap();
+        }
+
+        if let Some(target) = target {
+            let style = PrimitiveStyle::with_stroke(theme.foreground, 1);
+            let (h, v) = (Point::new(CROSSHAIR_SIZE, 0), Point::new(0, CROSSHAIR_SIZE));
+
+            Line::new(target - h, target + h)
+                .into_styled(style)
+                .draw(display)
+                .unwrap();
+            Line::new(target - v, target + v)
+                .into_styled(style)
+                .draw(display)
+                .unwrap();
+            Circle::with_center(target, CROSSHAIR_SIZE as u32)
+                .into_styled(style)
+                .draw(display)
+                .unwrap();
+        }
+
+        self.last_drawn = Some(target);
+    }
+}
+
+/// Screen that shows targets to touch in turn and one more to confirm,
+/// then applies the fitted calibration to every screen, calls `on_done`
+/// with it and closes.
+pub struct CalibrationScreen {
+    calibrator: Calibrator,
+    target: Rc<Cell<Option<Point>>>,
+    prompt: Rc<RefCell<String>>,
+    on_done: Box<dyn Fn(TouchCalibration)>,
+}
+
+impl CalibrationScreen {
+    pub fn new(width: u16, height: u16, on_done: Box<dyn Fn(TouchCalibration)>) -> Self {
+        let calibrator = Calibrator::new(&targets(width, height), confirm_target(width, height));
+        let target = Rc::new(Cell::new(calibrator.target()));
+
+        Self {
+            calibrator,
+            target,
+            prompt: Rc::new(RefCell::new(TARGETS_PROMPT.to_string())),
+            on_done,
+        }
+    }
+}
+
+impl<D> Screen<D> for CalibrationScreen
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    fn build(&mut self, ui: &mut Ui<D>, _navigation: &Navigation<D>) {
+        let bounds = ui.bounds();
+
+        let prompt = Text::new(
+            self.prompt.borrow().clone(),
+            Point::new(TARGET_INSET * 2, bounds.center().y - TARGET_INSET),
+        );
+        self.prompt = prompt.text_ref();
+        ui.add_element(Box::new(prompt));
+        ui.add_element(Box::new(Crosshair {
+            area: bounds,
+            target: self.target.clone(),
+            last_drawn: None,
+        }));
+    }
+
+    fn on_raw_touch(
+        &mut self,
+        touch: (f64, f64, f64),
+        _time: u32,
+        navigation: &Navigation<D>,
+    ) -> bool {
+        if let Some(calibration) = self.calibrator.sample(touch) {
+            log::info!("touch calibration {:?}", calibration);
+            navigation.touch_calibration(calibration);
+            (*self.on_done)(calibration);
+            navigation.pop();
+        }
+        self.target.set(self.calibrator.target());
+
+        let prompt = if self.calibrator.confirming() {
+            CONFIRM_PROMPT
+        } else {
+            TARGETS_PROMPT
+        };
+        if *self.prompt.borrow() != prompt {
+            *self.prompt.borrow_mut() = prompt.to_string();
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        storage::MemoryStore,
+        ui::{button::Button, framebuffer::Framebuffer, gesture::TAP, screen::Navigator},
+    };
+
+    /// Screen with a button over all of it.
+    struct ButtonScreen {
+        tapped: Rc<Cell<bool>>,
+    }
+
+    impl Screen<Framebuffer> for ButtonScreen {
+        fn build(&mut self, ui: &mut Ui<Framebuffer>, _navigation: &Navigation<Framebuffer>) {
+            let tapped = self.tapped.clone();
+            ui.add_element(Box::new(Button::new(
+                Point::zero(),
+                Size::new(320, 240),
+                "HOME".to_string(),
+                Box::new(move || tapped.set(true)),
+            )));
+        }
+    }
+
+    /// Readings from a panel rotated by 3 degrees, scaled and offset, as
+    /// `(screen position) -> reading`.
+    fn panel(x: f64, y: f64) -> (f64, f64) {
+        let (sin, cos) = 3f64.to_radians().sin_cos();
+        (
+            0.7 - (cos * y - sin * x) / 480.0,
+            0.8 - (sin * y + cos * x) / 420.0,
+        )
+    }
+
+    fn assert_near(actual: (f64, f64), expected: (f64, f64)) {
+        let error = (actual.0 - expected.0).hypot(actual.1 - expected.1);
+        assert!(error < 1.0, "{:?} is not near {:?}", actual, expected);
+    }
+
+    #[test]
+    fn fits_rotated_panel() {
+        // readings are a little noisy
+        let noise = [0.001, -0.0015, 0.0005, 0.0, -0.001];
+        let samples: Vec<_> = targets(320, 240)
+            .iter()
+            .zip(noise)
+            .map(|(target, noise)| {
+                let (x, y) = (target.x as f64, target.y as f64);
+                let (tx, ty) = panel(x, y);
+                ((tx + noise, ty - noise), (x, y))
+            })
+            .collect();
+
+        let calibration = TouchCalibration::fit(&samples).unwrap();
+
+        for (x, y) in [(0.0, 0.0), (160.0, 120.0), (319.0, 239.0), (50.0, 200.0)] {
+            let (tx, ty) = panel(x, y);
+            assert_near(calibration.apply(tx, ty), (x, y));
+        }
+    }
+
+    #[test]
+    fn exact_fit_from_three_points() {
+        let calibration = TouchCalibration {
+            x: [-10.0, 400.0, 5.0],
+            y: [300.0, 2.0, -7.0],
+        };
+        let samples: Vec<_> = [(0.1, 0.2), (0.9, 0.3), (0.4, 0.8)]
+            .iter()
+            .map(|(tx, ty)| ((*tx, *ty), calibration.apply(*tx, *ty)))
+            .collect();
+
+        let fitted = TouchCalibration::fit(&samples).unwrap();
+        for (a, b) in fitted
+            .x
+            .iter()
+            .chain(fitted.y.iter())
+            .zip(calibration.x.iter().chain(calibration.y.iter()))
+        {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn points_in_a_line_do_not_fit() {
+        let samples: Vec<_> = [0.1, 0.2, 0.3, 0.4]
+            .iter()
+            .map(|t| ((*t, *t), (*t * 100.0, *t * 100.0)))
+            .collect();
+
+        assert_eq!(TouchCalibration::fit(&samples), None);
+        assert_eq!(TouchCalibration::fit(&samples[..2]), None);
+    }
+
+    fn samples(calibration: &TouchCalibration) -> Vec<CalibrationSample> {
+        [(0.1, 0.2), (0.9, 0.3), (0.4, 0.8), (0.8, 0.9)]
+            .iter()
+            .map(|(tx, ty)| ((*tx, *ty), calibration.apply(*tx, *ty)))
+            .collect()
+    }
+
+    #[test]
+    fn rejects_implausible_fits() {
+        let good = TouchCalibration {
+            x: [-10.0, 400.0, 5.0],
+            y: [300.0, 2.0, -7.0],
+        };
+        assert!(good.is_plausible());
+        assert!(good.residual(&samples(&good)) < 1e-6);
+
+        // one axis squashed almost flat
+        let squashed = TouchCalibration {
+            x: [-10.0, 400.0, 5.0],
+            y: [30.0, 0.2, -7.0],
+        };
+        assert!(!squashed.is_plausible());
+        let nan = TouchCalibration {
+            x: [f64::NAN, 400.0, 5.0],
+            ..good
+        };
+        assert!(!nan.is_plausible());
+
+        // one target touched well away from where it was shown
+        let mut sloppy = samples(&good);
+        sloppy[3].1 .0 += 80.0;
+        let fitted = TouchCalibration::fit(&sloppy).unwrap();
+        assert!(fitted.residual(&sloppy) > MAX_RESIDUAL);
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let mut store = MemoryStore::new();
+        assert!(matches!(TouchCalibration::load(&store), Ok(None)));
+
+        let calibration = TouchCalibration {
+            x: [-1.5, 400.25, 5.0],
+            y: [300.0, 2.0, -7.125],
+        };
+        calibration.save(&mut store).unwrap();
+
+        assert_eq!(TouchCalibration::load(&store).unwrap(), Some(calibration));
+
+        store.set(STORAGE_KEY, &[0; 12]).unwrap();
+        assert!(matches!(
+            TouchCalibration::load(&store),
+            Err(StorageError::Malformed)
+        ));
+
+        // all zeros folds the screen onto a point
+        store.set(STORAGE_KEY, &[0; 48]).unwrap();
+        assert!(matches!(
+            TouchCalibration::load(&store),
+            Err(StorageError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn calibration_screen_calibrates_every_screen() {
+        let saved = Rc::new(RefCell::new(None));
+        let saved2 = saved.clone();
+        let mut navigator = Navigator::<Framebuffer>::new(320, 240);
+        let mut display = Framebuffer::new(320, 240);
+
+        let tapped = Rc::new(Cell::new(false));
+        navigator.navigation().push(ButtonScreen {
+            tapped: tapped.clone(),
+        });
+        navigator.navigation().push(CalibrationScreen::new(
+            320,
+            240,
+            Box::new(move |calibration| *saved2.borrow_mut() = Some(calibration)),
+        ));
+        navigator.update();
+        navigator.draw(&mut display);
+
+        // a light brush is ignored
+        navigator.handle_touch((0.5, 0.5, 0.3), 0);
+        navigator.handle_touch((0.5, 0.5, 0.0), 10);
+
+        let touch =
+            |navigator: &mut Navigator<Framebuffer>, display: &mut Framebuffer, target: Point| {
+                let (tx, ty) = panel(target.x as f64, target.y as f64);
+                for time in 0..MIN_SAMPLES as u32 {
+                    navigator.handle_touch((tx, ty, 0.8), time * 10);
+                }
+                navigator.handle_touch((tx, ty, 0.0), 100);
+                navigator.update();
+                navigator.draw(display);
+            };
+
+        for target in targets(320, 240) {
+            touch(&mut navigator, &mut display, target);
+        }
+        // nothing is saved until confirmed, and a miss starts over
+        assert_eq!(navigator.depth(), 2);
+        assert!(saved.borrow().is_none());
+        touch(&mut navigator, &mut display, Point::new(300, 20));
+        assert_eq!(navigator.depth(), 2);
+
+        for target in targets(320, 240) {
+            touch(&mut navigator, &mut display, target);
+        }
+        touch(&mut navigator, &mut display, confirm_target(320, 240));
+
+        assert_eq!(navigator.depth(), 1);
+        assert!(saved.borrow().is_some());
+        assert!(!tapped.get());
+
+        // the screen beneath now takes calibrated touches
+        let (tx, ty) = panel(160.0, 120.0);
+        for (time, z) in TAP {
+            navigator.handle_touch((tx, ty, z), time);
+        }
+        assert!(tapped.get());
+    }
+}
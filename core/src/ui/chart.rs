@@ -0,0 +1,537 @@
+use std::{cell::RefCell, collections::VecDeque, fmt::Debug, rc::Rc};
+
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Circle, Line, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+
+use super::{
+    theme,
+    ui::{UiElement, UiEvent},
+};
+
+const TICK_LENGTH: i32 = 2;
+const MAX_TICKS: f32 = 5.0;
+const MARKER_DIAMETER: u32 = 5;
+
+/// Round `raw` up to 1, 2 or 5 times a power of ten.
+fn nice_step(raw: f32) -> f32 {
+    let magnitude = 10f32.powf(raw.log10().floor());
+    let fraction = raw / magnitude;
+
+    let nice = if fraction <= 1.0 {
+        1.0
+    } else if fraction <= 2.0 {
+        2.0
+    } else if fraction <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+
+    nice * magnitude
+}
+
+/// Range of an axis and the spacing of its ticks.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Scale {
+    min: f32,
+    max: f32,
+    step: f32,
+}
+
+impl Scale {
+    /// Scale covering `min` to `max`, widened to whole ticks.
+    fn nice(min: f32, max: f32) -> Self {
+        let scale = Self::exact(min, max);
+
+        Self {
+            min: (scale.min / scale.step).floor() * scale.step,
+            max: (scale.max / scale.step).ceil() * scale.step,
+            ..scale
+        }
+    }
+
+    /// Scale covering exactly `min` to `max`, with ticks wherever they fall.
+    fn exact(min: f32, max: f32) -> Self {
+        // a flat line still needs a range to be drawn in
+        let (min, max) = if max - min <= f32::EPSILON * max.abs().max(1.0) {
+            (min - 1.0, max + 1.0)
+        } else {
+            (min, max)
+        };
+
+        Self {
+            min,
+            max,
+            step: nice_step((max - min) / MAX_TICKS),
+        }
+    }
+
+    fn ticks(&self) -> impl Iterator<Item = f32> {
+        let first = (self.min / self.step).ceil() as i32;
+        let last = (self.max / self.step).floor() as i32;
+        let step = self.step;
+
+        (first..=last).map(move |i| i as f32 * step)
+    }
+
+    /// Decimal places needed to tell ticks apart.
+    fn decimals(&self) -> usize {
+        if self.step >= 1.0 {
+            0
+        } else {
+            (-self.step.log10()).ceil() as usize
+        }
+    }
+
+    fn label(&self, value: f32) -> String {
+        format!("{:.*}", self.decimals(), value)
+    }
+
+    /// Offset of `value` along an axis `length` pixels long.
+    fn offset(&self, value: f32, length: u32) -> i32 {
+        let fraction = (value - self.min) / (self.max - self.min);
+        (fraction * length.saturating_sub(1) as f32).round() as i32
+    }
+}
+
+/// One line on a [`Chart`].
+pub struct Series {
+    pub name: &'static str,
+    pub color: Rgb565,
+    /// Circle and label the highest value
+    pub mark_maximum: bool,
+    points: VecDeque<(u32, f32)>,
+}
+
+impl Series {
+    /// Retained samples as (time in ms, value), oldest first.
+    pub fn points(&self) -> impl Iterator<Item = (u32, f32)> + '_ {
+        self.points.iter().copied()
+    }
+
+    pub fn maximum(&self) -> Option<(u32, f32)> {
+        self.points()
+            .fold(None, |max: Option<(u32, f32)>, point| match max {
+                Some(max) if max.1 >= point.1 => Some(max),
+                _ => Some(point),
+            })
+    }
+}
+
+/// Samples shown by a [`Chart`].
+///
+/// Each series keeps at most `capacity` samples, dropping the oldest as new
+/// ones arrive so the chart scrolls.
+pub struct ChartData {
+    capacity: usize,
+    series: Vec<Series>,
+    revision: u32,
+}
+
+impl ChartData {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            series: Vec::new(),
+            revision: 0,
+        }
+    }
+
+    /// Add a series, returning its index for [`ChartData::push`].
+    pub fn add_series(&mut self, name: &'static str, color: Rgb565, mark_maximum: bool) -> usize {
+        self.series.push(Series {
+            name,
+            color,
+            mark_maximum,
+            points: VecDeque::with_capacity(self.capacity),
+        });
+        self.revision = self.revision.wrapping_add(1);
+        self.series.len() - 1
+    }
+
+    /// Append a sample.  A time earlier than the last sample means the
+    /// source restarted, so the series history is discarded.
+    pub fn push(&mut self, series: usize, time: u32, value: f32) {
+        let series = &mut self.series[series];
+
+        if series.points.back().is_some_and(|(last, _)| time < *last) {
+            series.points.clear();
+        }
+
+        if series.points.len() >= self.capacity {
+            series.points.pop_front();
+        }
+
+        series.points.push_back((time, value));
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    pub fn clear(&mut self) {
+        for series in self.series.iter_mut() {
+            series.points.clear();
+        }
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    /// Change the color of a series, as when the theme changes.
+    pub fn set_color(&mut self, series: usize, color: Rgb565) {
+        self.series[series].color = color;
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    pub fn series(&self) -> &[Series] {
+        &self.series
+    }
+
+    fn time_range(&self) -> Option<(u32, u32)> {
+        self.series
+            .iter()
+            .flat_map(|s| s.points())
+            .map(|(t, _)| (t, t))
+            .reduce(|(min, max), (t, _)| (min.min(t), max.max(t)))
+    }
+
+    fn value_range(&self) -> Option<(f32, f32)> {
+        self.series
+            .iter()
+            .flat_map(|s| s.points())
+            .map(|(_, v)| (v, v))
+            .reduce(|(min, max), (v, _)| (min.min(v), max.max(v)))
+    }
+}
+
+/// Line chart of one or more series against time.
+///
+/// The Y axis scales to fit every retained sample and the time axis spans
+/// the oldest to newest sample, labelled in seconds.
+pub struct Chart {
+    bounds: Rectangle,
+    data: Rc<RefCell<ChartData>>,
+    last_drawn: Option<u32>,
+}
+
+impl Chart {
+    pub fn new(bounds: Rectangle, data: Rc<RefCell<ChartData>>) -> Self {
+        Self {
+            bounds,
+            data,
+            last_drawn: None,
+        }
+    }
+
+    pub fn data_ref(&self) -> Rc<RefCell<ChartData>> {
+        self.data.clone()
+    }
+
+    /// Draw the whole chart within its bounds.
+    pub fn render<D>(&mut self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let data = self.data.borrow();
+        let theme = theme::current();
+        let font = theme.small_font;
+        let char_width = font.character_size.width as i32;
+        let char_height = font.character_size.height as i32;
+
+        self.bounds
+            .into_styled(PrimitiveStyle::with_fill(theme.background))
+            .draw(target)?;
+
+        let (t0, t1) = data.time_range().unwrap_or((0, 1000));
+        let (v0, v1) = data.value_range().unwrap_or((0.0, 1.0));
+
+        let y_scale = Scale::nice(v0, v1);
+        let x_scale = Scale::exact(t0 as f32 / 1000.0, t1 as f32 / 1000.0);
+
+        let y_labels: Vec<(f32, String)> = y_scale.ticks().map(|v| (v, y_scale.label(v))).collect();
+        let label_width = y_labels
+            .iter()
+            .map(|(_, label)| label.len() as i32 * char_width)
+            .max()
+            .unwrap_or(0);
+
+        let left = label_width + TICK_LENGTH + 1;
+        let bottom = char_height + TICK_LENGTH + 1;
+        // half a label above the top tick
+        let top = char_height / 2;
+        // room for half of the last time label past the end of the plot
+        let right_padding = 3 * char_width;
+
+        let plot = Rectangle::new(
+            self.bounds.top_left + Point::new(left, top),
+            Size::new(
+                (self.bounds.size.width as i32 - left - right_padding).max(1) as u32,
+                (self.bounds.size.height as i32 - top - bottom).max(1) as u32,
+            ),
+        );
+        let plot_bottom = plot.top_left.y + plot.size.height as i32 - 1;
+
+        let to_point = |time: u32, value: f32| {
+            Point::new(
+                plot.top_left.x + x_scale.offset(time as f32 / 1000.0, plot.size.width),
+                plot_bottom - y_scale.offset(value, plot.size.height),
+            )
+        };
+
+        let text_style = MonoTextStyle::new(font, theme.foreground);
+        let axis_style = PrimitiveStyle::with_stroke(theme.foreground, 1);
+
+        // axes
+        Line::new(plot.top_left, Point::new(plot.top_left.x, plot_bottom))
+            .into_styled(axis_style)
+            .draw(target)?;
+        Line::new(
+            Point::new(plot.top_left.x, plot_bottom),
+            Point::new(plot.top_left.x + plot.size.width as i32 - 1, plot_bottom),
+        )
+        .into_styled(axis_style)
+        .draw(target)?;
+
+        let right_aligned = TextStyleBuilder::new()
+            .alignment(Alignment::Right)
+            .baseline(Baseline::Middle)
+            .build();
+
+        for (value, label) in y_labels.iter() {
+            let y = plot_bottom - y_scale.offset(*value, plot.size.height);
+            let x = plot.top_left.x;
+
+            Line::new(Point::new(x - TICK_LENGTH, y), Point::new(x - 1, y))
+                .into_styled(axis_style)
+                .draw(target)?;
+            Text::with_text_style(
+                label,
+                Point::new(x - TICK_LENGTH - 1, y),
+                text_style,
+                right_aligned,
+            )
+            .draw(target)?;
+        }
+
+        let centered = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Top)
+            .build();
+
+        for seconds in x_scale.ticks() {
+            let x = plot.top_left.x + x_scale.offset(seconds, plot.size.width);
+
+            Line::new(
+                Point::new(x, plot_bottom + 1),
+                Point::new(x, plot_bottom + TICK_LENGTH),
+            )
+            .into_styled(axis_style)
+            .draw(target)?;
+            Text::with_text_style(
+                &format!("{}s", x_scale.label(seconds)),
+                Point::new(x, plot_bottom + TICK_LENGTH + 1),
+                text_style,
+                centered,
+            )
+            .draw(target)?;
+        }
+
+        // series, legend and maximum markers
+        let mut legend = plot.top_left + Point::new(TICK_LENGTH, 0);
+        // drawn a band at a time, so skip segments in rows the target lacks
+        let rows = target.bounding_box().rows();
+
+        for series in data.series() {
+            let line_style = PrimitiveStyle::with_stroke(series.color, 1);
+
+            let mut previous: Option<Point> = None;
+            for (time, value) in series.points() {
+                let point = to_point(time, value);
+                match previous {
+                    Some(previous)
+                        if previous.y.max(point.y) < rows.start
+                            || previous.y.min(point.y) >= rows.end => {}
+                    Some(previous) => Line::new(previous, point)
+                        .into_styled(line_style)
+                        .draw(target)?,
+                    None => Pixel(point, series.color).draw(target)?,
+                }
+                previous = Some(point);
+            }
+
+            let series_style = MonoTextStyle::new(font, series.color);
+            legend = Text::with_baseline(series.name, legend, series_style, Baseline::Top)
+                .draw(target)?
+                + Point::new(char_width, 0);
+
+            if let (true, Some((time, value))) = (series.mark_maximum, series.maximum()) {
+                let point = to_point(time, value);
+
+                Circle::with_center(point, MARKER_DIAMETER)
+                    .into_styled(line_style)
+                    .draw(target)?;
+
+                // keep the label inside the plot
+                let offset = MARKER_DIAMETER as i32 / 2 + 1;
+                let (x, alignment) = if point.x > plot.center().x {
+                    (point.x - offset, Alignment::Right)
+                } else {
+                    (point.x + offset, Alignment::Left)
+                };
+                let y = (point.y - offset).max(plot.top_left.y + char_height);
+
+                Text::with_text_style(
+                    &y_scale.label(value),
+                    Point::new(x, y),
+                    series_style,
+                    TextStyleBuilder::new()
+                        .alignment(alignment)
+                        .baseline(Baseline::Bottom)
+                        .build(),
+                )
+                .draw(target)?;
+            }
+        }
+
+        self.last_drawn = Some(data.revision);
+
+        Ok(())
+    }
+}
+
+impl<D> UiElement<D> for Chart
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    fn handle_event(&mut self, _event: UiEvent) {}
+
+    fn dirty(&self) -> bool {
+        self.last_drawn != Some(self.data.borrow().revision)
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        self.bounds
+    }
+
+    fn draw(&mut self, display: &mut D) {
+        self.render(display).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::pixelcolor::RgbColor;
+
+    use super::*;
+    use crate::ui::framebuffer::{assert_snapshot, Framebuffer, PALETTE};
+
+    fn render(data: ChartData) -> String {
+        let mut display = Framebuffer::new(120, 60);
+        let mut chart = Chart::new(
+            Rectangle::new(Point::zero(), display.size()),
+            Rc::new(RefCell::new(data)),
+        );
+
+        chart.render(&mut display).unwrap();
+        display.to_ascii(&PALETTE)
+    }
+
+    #[test]
+    fn nice_steps() {
+        assert_eq!(nice_step(0.7), 1.0);
+        assert_eq!(nice_step(1.5), 2.0);
+        assert_eq!(nice_step(3.0), 5.0);
+        assert_eq!(nice_step(7.0), 10.0);
+        assert_eq!(nice_step(230.0), 500.0);
+        assert!((nice_step(0.03) - 0.05).abs() < 1e-6);
+    }
+
+    #[test]
+    fn scale_widens_to_whole_ticks() {
+        let scale = Scale::nice(3.0, 97.0);
+
+        assert_eq!(
+            scale,
+            Scale {
+                min: 0.0,
+                max: 100.0,
+                step: 20.0
+            }
+        );
+        assert_eq!(
+            scale.ticks().collect::<Vec<_>>(),
+            vec![0.0, 20.0, 40.0, 60.0, 80.0, 100.0]
+        );
+        assert_eq!(scale.offset(50.0, 101), 50);
+
+        let flat = Scale::nice(10.0, 10.0);
+        assert!(flat.min < 10.0 && flat.max > 10.0);
+        assert_eq!(Scale::nice(0.0, 0.4).decimals(), 1);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut data = ChartData::new(3);
+        let altitude = data.add_series("ALT", Rgb565::GREEN, true);
+
+        for i in 0..5 {
+            data.push(altitude, i * 100, i as f32);
+        }
+
+        let points: Vec<_> = data.series()[altitude].points().collect();
+        assert_eq!(points, vec![(200, 2.0), (300, 3.0), (400, 4.0)]);
+
+        // a restart of the source clears what came before
+        data.push(altitude, 50, 9.0);
+        let points: Vec<_> = data.series()[altitude].points().collect();
+        assert_eq!(points, vec![(50, 9.0)]);
+    }
+
+    #[test]
+    fn redraws_only_after_new_samples() {
+        let data = Rc::new(RefCell::new(ChartData::new(10)));
+        let series = data.borrow_mut().add_series("ALT", Rgb565::GREEN, false);
+        let mut chart = Chart::new(Rectangle::new(Point::zero(), Size::new(60, 40)), data);
+        let mut display = Framebuffer::new(60, 40);
+        let dirty = |chart: &Chart| UiElement::<Framebuffer>::dirty(chart);
+
+        assert!(dirty(&chart));
+        chart.render(&mut display).unwrap();
+        assert!(!dirty(&chart));
+
+        chart.data_ref().borrow_mut().push(series, 0, 1.0);
+        assert!(dirty(&chart));
+    }
+
+    #[test]
+    fn renders_empty_chart() {
+        let mut data = ChartData::new(10);
+        data.add_series("ALT", Rgb565::YELLOW, true);
+
+        assert_snapshot("chart_empty", &render(data));
+    }
+
+    #[test]
+    fn renders_flight() {
+        let mut data = ChartData::new(100);
+        let altitude = data.add_series("ALT", Rgb565::YELLOW, true);
+        let velocity = data.add_series("VEL", Rgb565::CYAN, false);
+
+        // climb to apogee at 4 s then descend, sampled at 5 Hz
+        for i in 0..40u32 {
+            let t = i as f32 / 5.0;
+            let (alt, vel) = if t < 4.0 {
+                (100.0 * t - 12.5 * t * t, 100.0 - 25.0 * t)
+            } else {
+                (200.0 - 20.0 * (t - 4.0), -20.0)
+            };
+            data.push(altitude, 1000 + i * 200, alt);
+            data.push(velocity, 1000 + i * 200, vel);
+        }
+
+        assert_snapshot("chart_flight", &render(data));
+    }
+}
@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use embedded_graphics::{
+    geometry::{Point, Size},
+    primitives::Rectangle,
+};
+
+/// Most pixels composed in memory at once, 20 KB at 16 bits per pixel.
+/// Larger regions are drawn in bands of whole rows.
+pub const BAND_PIXELS: u32 = 320 * 32;
+
+/// Areas of the display that need repainting.
+///
+/// Overlapping areas are merged as they are added, so each pixel is written
+/// at most once per frame.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DirtyRegions {
+    regions: Vec<Rectangle>,
+}
+
+impl DirtyRegions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, area: Rectangle) {
+        if area.is_zero_sized() {
+            return;
+        }
+
+        // absorb every region the area overlaps, starting over as it grows
+        let mut area = area;
+        let mut i = 0;
+        while i < self.regions.len() {
+            if self.regions[i].intersection(&area).is_zero_sized() {
+                i += 1;
+            } else {
+                area = union(&self.regions.swap_remove(i), &area);
+                i = 0;
+            }
+        }
+
+        self.regions.push(area);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    pub fn regions(&self) -> &[Rectangle] {
+        &self.regions
+    }
+
+    /// Remove and return the regions.
+    pub fn take(&mut self) -> Vec<Rectangle> {
+        std::mem::take(&mut self.regions)
+    }
+}
+
+/// Smallest rectangle containing both `a` and `b`, ignoring either if it
+/// is empty.
+pub fn union(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    if a.is_zero_sized() {
+        return *b;
+    }
+    if b.is_zero_sized() {
+        return *a;
+    }
+
+    let bottom_right = |r: &Rectangle| r.top_left + r.size;
+
+    let top_left = a.top_left.component_min(b.top_left);
+    let bottom_right = bottom_right(a).component_max(bottom_right(b));
+
+    Rectangle::with_corners(top_left, bottom_right - Point::new(1, 1))
+}
+
+/// Split `area` into bands of whole rows of at most `max_pixels`.
+pub fn bands(area: Rectangle, max_pixels: u32) -> impl Iterator<Item = Rectangle> {
+    let rows = (max_pixels / area.size.width.max(1)).max(1);
+
+    (0..area.size.height).step_by(rows as usize).map(move |y| {
+        Rectangle::new(
+            area.top_left + Point::new(0, y as i32),
+            Size::new(area.size.width, rows.min(area.size.height - y)),
+        )
+    })
+}
+
+/// Time spent drawing, over the frames since the stats were last taken.
+///
+/// Only frames that wrote to the display are counted.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    pub frames: u32,
+    /// Regions written to the display
+    pub regions: u32,
+    /// Pixels written to the display
+    pub pixels: u32,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl FrameStats {
+    pub fn record(&mut self, regions: u32, pixels: u32, elapsed: Duration) {
+        self.frames += 1;
+        self.regions += regions;
+        self.pixels += pixels;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+
+    /// Add in frames counted elsewhere, e.g. by another screen.
+    pub fn merge(&mut self, other: &FrameStats) {
+        self.frames += other.frames;
+        self.regions += other.regions;
+        self.pixels += other.pixels;
+        self.total += other.total;
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.frames == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.frames
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn merges_overlapping_regions() {
+        let mut dirty = DirtyRegions::new();
+
+        dirty.add(rect(0, 0, 10, 10));
+        dirty.add(rect(50, 0, 10, 10));
+        dirty.add(rect(0, 0, 0, 0));
+        assert_eq!(dirty.regions().len(), 2);
+
+        // bridges the two, which become one
+        dirty.add(rect(5, 5, 50, 2));
+        assert_eq!(dirty.take(), vec![rect(0, 0, 60, 10)]);
+        assert!(dirty.is_empty());
+
+        // touching edges don't overlap
+        dirty.add(rect(0, 0, 10, 10));
+        dirty.add(rect(10, 0, 10, 10));
+        assert_eq!(dirty.regions().len(), 2);
+    }
+
+    #[test]
+    fn splits_into_bands() {
+        assert_eq!(
+            bands(rect(5, 10, 100, 25), 1000).collect::<Vec<_>>(),
+            vec![
+                rect(5, 10, 100, 10),
+                rect(5, 20, 100, 10),
+                rect(5, 30, 100, 5)
+            ]
+        );
+
+        // never less than a row
+        assert_eq!(bands(rect(0, 0, 100, 2), 10).count(), 2);
+    }
+
+    #[test]
+    fn averages_frames() {
+        let mut stats = FrameStats::default();
+        assert_eq!(stats.average(), Duration::ZERO);
+
+        stats.record(1, 100, Duration::from_millis(4));
+        stats.record(2, 300, Duration::from_millis(8));
+
+        let mut other = FrameStats::default();
+        other.record(1, 20, Duration::from_millis(12));
+        stats.merge(&other);
+
+        assert_eq!(stats.frames, 3);
+        assert_eq!(stats.pixels, 420);
+        assert_eq!(stats.average(), Duration::from_millis(8));
+        assert_eq!(stats.max, Duration::from_millis(12));
+    }
+}
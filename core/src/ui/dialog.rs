@@ -0,0 +1,189 @@
+use std::{fmt::Debug, rc::Rc};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    pixelcolor::Rgb565,
+    primitives::{Primitive, PrimitiveStyleBuilder, Rectangle},
+    Drawable,
+};
+
+use super::{
+    button::Button,
+    layout::{column, place, row, Align, Insets},
+    screen::{Navigation, Screen},
+    text::Text,
+    theme,
+    ui::{Ui, UiDimension, UiElement, UiEvent},
+};
+
+const DIALOG_SIZE: Size = Size::new(220, 100);
+const BUTTON_HEIGHT: i16 = 25;
+const BUTTON_WIDTH: i16 = 60;
+const LINE_HEIGHT: i16 = 14;
+
+/// Outlined panel the dialog is drawn on, hiding the screen beneath.
+struct Panel {
+    area: Rectangle,
+    dirty: bool,
+}
+
+impl<D> UiElement<D> for Panel
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    fn handle_event(&mut self, _event: UiEvent) {}
+
+    fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        self.area
+    }
+
+    fn draw(&mut self, display: &mut D) {
+        let theme = theme::current();
+        let style = PrimitiveStyleBuilder::new()
+            .fill_color(theme.background)
+            .stroke_color(theme.foreground)
+            .stroke_width(2)
+            .build();
+
+        self.area.into_styled(style).draw(display).unwrap();
+        self.dirty = false;
+    }
+}
+
+/// Modal asking whether to go ahead with something that can't be undone.
+///
+/// OK calls `on_confirm` and closes the dialog; CANCEL just closes it.
+pub struct ConfirmDialog {
+    title: String,
+    message: String,
+    on_confirm: Rc<dyn Fn()>,
+}
+
+impl ConfirmDialog {
+    pub fn new(title: &str, message: &str, on_confirm: Box<dyn Fn()>) -> Self {
+        Self {
+            title: title.to_string(),
+            message: message.to_string(),
+            on_confirm: on_confirm.into(),
+        }
+    }
+}
+
+impl<D> Screen<D> for ConfirmDialog
+where
+    D: DrawTarget<Color = Rgb565> + 'static,
+    D::Error: Debug,
+{
+    fn build(&mut self, ui: &mut Ui<D>, navigation: &Navigation<D>) {
+        let area = place(ui.bounds(), DIALOG_SIZE, Align::Center, Align::Center);
+        ui.add_element(Box::new(Panel { area, dirty: true }));
+
+        let areas = column([
+            UiDimension::Fixed(LINE_HEIGHT),
+            UiDimension::Fixed(LINE_HEIGHT),
+            UiDimension::Auto,
+            UiDimension::Fixed(BUTTON_HEIGHT),
+        ])
+        .gap(4)
+        .padding(Insets::all(8))
+        .layout(area);
+
+        for (text, area) in [&self.title, &self.message].into_iter().zip(&areas) {
+            ui.add_element(Box::new(Text::new(
+                text.clone(),
+                area.top_left + Point::new(0, LINE_HEIGHT as i32 - 4),
+            )));
+        }
+
+        let cells = row([
+            UiDimension::Auto,
+            UiDimension::Fixed(BUTTON_WIDTH),
+            UiDimension::Fixed(BUTTON_WIDTH),
+        ])
+        .gap(4)
+        .layout(areas[3]);
+
+        let cancel_navigation = navigation.clone();
+        ui.add_element(Box::new(Button::new(
+            cells[1].top_left,
+            cells[1].size,
+            "CANCEL".to_string(),
+            Box::new(move || cancel_navigation.pop()),
+        )));
+
+        let on_confirm = self.on_confirm.clone();
+        let confirm_navigation = navigation.clone();
+        ui.add_element(Box::new(Button::new(
+            cells[2].top_left,
+            cells[2].size,
+            "OK".to_string(),
+            Box::new(move || {
+                (*on_confirm)();
+                confirm_navigation.pop();
+            }),
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::ui::{
+        calibration::TouchCalibration,
+        framebuffer::{assert_snapshot, Framebuffer, PALETTE},
+        screen::Navigator,
+    };
+
+    struct Blank;
+
+    impl Screen<Framebuffer> for Blank {
+        fn build(&mut self, _ui: &mut Ui<Framebuffer>, _navigation: &Navigation<Framebuffer>) {}
+    }
+
+    #[test]
+    fn confirms_or_cancels() {
+        let confirmed = Rc::new(Cell::new(0));
+        let mut display = Framebuffer::new(320, 240);
+        let mut navigator = Navigator::<Framebuffer>::new(320, 240);
+        navigator.touch_calibration(TouchCalibration::IDENTITY);
+        navigator.navigation().push(Blank);
+
+        let open = |navigator: &mut Navigator<Framebuffer>| {
+            let confirmed = confirmed.clone();
+            navigator.navigation().modal(ConfirmDialog::new(
+                "RST",
+                "RESET MAX ALTITUDE?",
+                Box::new(move || confirmed.set(confirmed.get() + 1)),
+            ));
+            navigator.update();
+        };
+
+        open(&mut navigator);
+        navigator.draw(&mut display);
+        let dialog = display
+            .to_ascii(&PALETTE)
+            .lines()
+            .skip(70)
+            .take(100)
+            .map(|line| &line[50..270])
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_snapshot("confirm_dialog", &dialog);
+
+        // CANCEL, at the bottom right beside OK
+        navigator.tap(170, 150);
+        assert_eq!((navigator.depth(), confirmed.get()), (1, 0));
+
+        open(&mut navigator);
+        navigator.tap(230, 150);
+        assert_eq!((navigator.depth(), confirmed.get()), (1, 1));
+    }
+}
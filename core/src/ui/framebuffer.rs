@@ -0,0 +1,259 @@
+use std::convert::Infallible;
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    pixelcolor::{Rgb565, RgbColor},
+    primitives::Rectangle,
+    Pixel,
+};
+
+/// Display held in memory, for running screens on the host and for
+/// composing regions of the panel before they are written out.
+///
+/// The buffer may cover any area of the display.  Drawing outside it is
+/// ignored, as it is by the panel, and fills are clipped to it before any
+/// pixel is visited, so an element drawn into each band of a region only
+/// pays for the rows in that band.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    area: Rectangle,
+    pixels: Vec<Rgb565>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::with_area(Rectangle::new(Point::zero(), Size::new(width, height)))
+    }
+
+    pub fn with_area(area: Rectangle) -> Self {
+        Self {
+            area,
+            pixels: vec![Rgb565::BLACK; area.size.width as usize * area.size.height as usize],
+        }
+    }
+
+    /// Cover `area` instead, filled with `color`, reusing the allocation.
+    pub fn reset(&mut self, area: Rectangle, color: Rgb565) {
+        self.area = area;
+        self.pixels.clear();
+        self.pixels
+            .resize(area.size.width as usize * area.size.height as usize, color);
+    }
+
+    pub fn size(&self) -> Size {
+        self.area.size
+    }
+
+    pub fn pixel(&self, point: Point) -> Option<Rgb565> {
+        self.index(point).map(|i| self.pixels[i])
+    }
+
+    /// Pixels in rows from the top left.
+    pub fn pixels(&self) -> &[Rgb565] {
+        &self.pixels
+    }
+
+    /// One character per pixel, looked up in `palette`; unlisted colors are `?`.
+    pub fn to_ascii(&self, palette: &[(Rgb565, char)]) -> String {
+        let mut result = String::with_capacity(self.pixels.len() + self.area.size.height as usize);
+
+        for row in self.pixels.chunks(self.area.size.width as usize) {
+            for pixel in row {
+                let c = palette
+                    .iter()
+                    .find(|(color, _)| color == pixel)
+                    .map_or('?', |(_, c)| *c);
+                result.push(c);
+            }
+            result.push('\n');
+        }
+
+        result
+    }
+
+    fn index(&self, point: Point) -> Option<usize> {
+        if self.area.contains(point) {
+            let point = point - self.area.top_left;
+            Some(point.y as usize * self.area.size.width as usize + point.x as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl Dimensions for Framebuffer {
+    fn bounding_box(&self) -> Rectangle {
+        self.area
+    }
+}
+
+impl DrawTarget for Framebuffer {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let Some(i) = self.index(point) {
+                self.pixels[i] = color;
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let clipped = area.intersection(&self.area);
+        if clipped.is_zero_sized() {
+            return Ok(());
+        }
+
+        let width = area.size.width as usize;
+        let left = (clipped.top_left.x - area.top_left.x) as usize;
+        let columns = left..left + clipped.size.width as usize;
+        let mut colors = colors.into_iter();
+
+        // skip the rows above, and stop after the last row inside
+        let above = (clipped.top_left.y - area.top_left.y) as usize * width;
+        if above > 0 && colors.nth(above - 1).is_none() {
+            return Ok(());
+        }
+        for y in clipped.rows() {
+            let start = self.index(Point::new(clipped.top_left.x, y)).unwrap();
+            for (x, color) in colors.by_ref().take(width).enumerate() {
+                if columns.contains(&x) {
+                    self.pixels[start + x - left] = color;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let clipped = area.intersection(&self.area);
+        if clipped.is_zero_sized() {
+            return Ok(());
+        }
+
+        let columns = clipped.size.width as usize;
+        for y in clipped.rows() {
+            let start = self.index(Point::new(clipped.top_left.x, y)).unwrap();
+            self.pixels[start..start + columns].fill(color);
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.pixels.fill(color);
+        Ok(())
+    }
+}
+
+/// Colors used by the stock screens, for [`Framebuffer::to_ascii`].
+#[cfg(test)]
+pub(crate) const PALETTE: [(Rgb565, char); 7] = [
+    (Rgb565::BLACK, '.'),
+    (Rgb565::GREEN, '#'),
+    (Rgb565::YELLOW, 'y'),
+    (Rgb565::CYAN, 'c'),
+    (Rgb565::RED, 'r'),
+    (Rgb565::WHITE, 'w'),
+    (Rgb565::BLUE, 'b'),
+];
+
+/// Compare `actual` against `src/ui/snapshots/<name>.txt`, rewriting the
+/// snapshot instead when `UPDATE_SNAPSHOTS` is set.
+#[cfg(test)]
+pub(crate) fn assert_snapshot(name: &str, actual: &str) {
+    let path = std::path::Path::new(file!())
+        .parent()
+        .unwrap()
+        .join("snapshots")
+        .join(format!("{}.txt", name));
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("missing snapshot {}", path.display()));
+    assert!(expected == actual, "{} differs:\n{}", name, actual);
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{
+        prelude::*,
+        primitives::{PrimitiveStyle, Rectangle},
+    };
+
+    use super::*;
+
+    #[test]
+    fn clips_to_buffer() {
+        let mut display = Framebuffer::new(4, 3);
+
+        Rectangle::new(Point::new(2, 1), Size::new(10, 10))
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::GREEN))
+            .draw(&mut display)
+            .unwrap();
+
+        assert_eq!(display.pixel(Point::new(3, 2)), Some(Rgb565::GREEN));
+        assert_eq!(display.pixel(Point::new(4, 2)), None);
+        assert_eq!(display.to_ascii(&PALETTE), "....\n..##\n..##\n");
+    }
+
+    #[test]
+    fn covers_an_area_of_the_display() {
+        let mut display = Framebuffer::new(1, 1);
+        display.reset(
+            Rectangle::new(Point::new(10, 20), Size::new(3, 2)),
+            Rgb565::BLACK,
+        );
+
+        Rectangle::new(Point::new(11, 20), Size::new(10, 1))
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::GREEN))
+            .draw(&mut display)
+            .unwrap();
+
+        assert_eq!(display.pixel(Point::new(0, 0)), None);
+        assert_eq!(display.to_ascii(&PALETTE), ".##\n...\n");
+    }
+
+    #[test]
+    fn clips_fills_to_the_area() {
+        let mut display = Framebuffer::new(1, 1);
+        display.reset(
+            Rectangle::new(Point::new(3, 1), Size::new(3, 2)),
+            Rgb565::BLACK,
+        );
+
+        // rows of G G R R R Y Y, the third all white
+        let area = Rectangle::new(Point::zero(), Size::new(7, 4));
+        let colors = (0..28).map(|i| match (i / 7, i % 7) {
+            (2, _) => Rgb565::WHITE,
+            (_, 0..=1) => Rgb565::GREEN,
+            (_, 2..=4) => Rgb565::RED,
+            _ => Rgb565::YELLOW,
+        });
+        display.fill_contiguous(&area, colors).unwrap();
+        assert_eq!(display.to_ascii(&PALETTE), "rry\nwww\n");
+
+        display.fill_solid(&area, Rgb565::BLUE).unwrap();
+        assert_eq!(display.to_ascii(&PALETTE), "bbb\nbbb\n");
+        display
+            .fill_solid(
+                &Rectangle::new(Point::new(4, 2), Size::new(9, 9)),
+                Rgb565::CYAN,
+            )
+            .unwrap();
+        assert_eq!(display.to_ascii(&PALETTE), "bbb\nbcc\n");
+    }
+}
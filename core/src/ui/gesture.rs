@@ -0,0 +1,380 @@
+use super::ui::TouchEvent;
+
+/// Pressure at which a touch starts.
+pub const Z_THRESHOLD: f64 = 0.25;
+/// Pressure below which a touch ends.  Lower than [`Z_THRESHOLD`] so a
+/// press hovering around the threshold doesn't flicker.
+pub const Z_RELEASE_THRESHOLD: f64 = 0.15;
+/// How long a press or release must last to count, in ms.
+pub const DEBOUNCE_MS: u32 = 30;
+/// How long a press must be held in place to be a long press, in ms.
+pub const LONG_PRESS_MS: u32 = 800;
+/// Movement within which a press is still held in place, in pixels.
+pub const SLOP: i32 = 8;
+/// Horizontal movement needed for a swipe, in pixels.
+pub const SWIPE_DISTANCE: i32 = 60;
+/// Longest time a swipe may take, in ms.
+pub const SWIPE_MS: u32 = 500;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum TouchStatus {
+    Up,
+    Down,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TouchState {
+    x: i32,
+    y: i32,
+    z: f64,
+    status: TouchStatus,
+}
+
+/// The touch in progress, from when it was accepted as down.
+#[derive(Copy, Clone, Debug)]
+struct Press {
+    start: (i32, i32),
+    time: u32,
+    /// Moved further than [`SLOP`] from the start
+    moved: bool,
+    long_pressed: bool,
+}
+
+/// Turns samples from the touch panel into [`TouchEvent`]s.
+///
+/// Samples are given in screen coordinates with the time they were taken,
+/// so recorded sequences can be replayed on the host.
+pub struct GestureRecognizer {
+    state: TouchState,
+    /// Status the panel has reported since the given time, that is not yet
+    /// accepted
+    pending: Option<(TouchStatus, u32)>,
+    press: Option<Press>,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GestureRecognizer {
+    pub fn new() -> Self {
+        Self {
+            state: TouchState {
+                x: 0,
+                y: 0,
+                z: 0.0,
+                status: TouchStatus::Up,
+            },
+            pending: None,
+            press: None,
+        }
+    }
+
+    /// Pressure of the last sample.
+    pub fn pressure(&self) -> f64 {
+        self.state.z
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.state.status == TouchStatus::Down
+    }
+
+    /// Take a sample at `time` ms, returning what it means.
+    pub fn update(&mut self, time: u32, x: i32, y: i32, z: f64) -> TouchEvent {
+        let threshold = match self.state.status {
+            TouchStatus::Up => Z_THRESHOLD,
+            TouchStatus::Down => Z_RELEASE_THRESHOLD,
+        };
+        let status = if z >= threshold {
+            TouchStatus::Down
+        } else {
+            TouchStatus::Up
+        };
+
+        self.state.z = z;
+
+        if status == self.state.status {
+            self.pending = None;
+        } else {
+            let since = match self.pending {
+                Some((pending, since)) if pending == status => since,
+                _ => time,
+            };
+
+            if time.wrapping_sub(since) < DEBOUNCE_MS {
+                self.pending = Some((status, since));
+                return TouchEvent::None;
+            }
+
+            self.pending = None;
+            self.state.status = status;
+
+            return match status {
+                TouchStatus::Down => {
+                    self.state.x = x;
+                    self.state.y = y;
+                    self.press = Some(Press {
+                        start: (x, y),
+                        time: since,
+                        moved: false,
+                        long_pressed: false,
+                    });
+                    TouchEvent::Down(x, y)
+                }
+                TouchStatus::Up => self.release(since),
+            };
+        }
+
+        match status {
+            TouchStatus::Up => TouchEvent::None,
+            TouchStatus::Down => self.hold(time, x, y),
+        }
+    }
+
+    fn hold(&mut self, time: u32, x: i32, y: i32) -> TouchEvent {
+        let from = (self.state.x, self.state.y);
+        let press = match self.press.as_mut() {
+            Some(press) => press,
+            None => return TouchEvent::None,
+        };
+
+        if (x - press.start.0).abs() > SLOP || (y - press.start.1).abs() > SLOP {
+            press.moved = true;
+        }
+
+        if !press.moved {
+            // jitter while held in place is neither a drag nor a new position
+            if !press.long_pressed && time.wrapping_sub(press.time) >= LONG_PRESS_MS {
+                press.long_pressed = true;
+                return TouchEvent::LongPress(from.0, from.1);
+            }
+            return TouchEvent::None;
+        }
+
+        if from != (x, y) {
+            self.state.x = x;
+            self.state.y = y;
+            TouchEvent::Drag { from, to: (x, y) }
+        } else {
+            TouchEvent::None
+        }
+    }
+
+    /// The touch ended at `time`, at the last position it was down.
+    fn release(&mut self, time: u32) -> TouchEvent {
+        let (x, y) = (self.state.x, self.state.y);
+        let press = match self.press.take() {
+            Some(press) => press,
+            None => return TouchEvent::Up(x, y),
+        };
+
+        let dx = x - press.start.0;
+        let dy = y - press.start.1;
+
+        if press.long_pressed {
+            TouchEvent::Release(x, y)
+        } else if dx.abs() >= SWIPE_DISTANCE
+            && dy.abs() * 2 <= dx.abs()
+            && time.wrapping_sub(press.time) <= SWIPE_MS
+        {
+            TouchEvent::Swipe {
+                direction: if dx < 0 {
+                    SwipeDirection::Left
+                } else {
+                    SwipeDirection::Right
+                },
+                from: press.start,
+                to: (x, y),
+            }
+        } else {
+            TouchEvent::Up(x, y)
+        }
+    }
+}
+
+/// Samples of a quick tap, as (time in ms, pressure).
+#[cfg(test)]
+pub(crate) const TAP: [(u32, f64); 4] = [(0, 1.0), (50, 1.0), (100, 0.0), (150, 0.0)];
+
+/// Samples of a press held past [`LONG_PRESS_MS`], as (time in ms, pressure).
+#[cfg(test)]
+pub(crate) const LONG_PRESS: [(u32, f64); 5] =
+    [(0, 1.0), (50, 1.0), (900, 1.0), (950, 0.0), (1000, 0.0)];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replay `samples` of (time in ms, x, y, pressure), returning the events
+    /// other than `None`.
+    fn replay(samples: &[(u32, i32, i32, f64)]) -> Vec<TouchEvent> {
+        let mut recognizer = GestureRecognizer::new();
+
+        samples
+            .iter()
+            .map(|(time, x, y, z)| recognizer.update(*time, *x, *y, *z))
+            .filter(|event| !matches!(event, TouchEvent::None))
+            .collect()
+    }
+
+    #[test]
+    fn tap() {
+        let events = replay(&[
+            (0, 10, 20, 0.6),
+            (20, 10, 20, 0.6),
+            (40, 11, 20, 0.6),
+            (60, 0, 0, 0.0),
+            (100, 0, 0, 0.0),
+        ]);
+
+        assert_eq!(
+            events,
+            vec![TouchEvent::Down(11, 20), TouchEvent::Up(11, 20)]
+        );
+    }
+
+    #[test]
+    fn debounces_and_holds_through_dips() {
+        let events = replay(&[
+            // a blip too short to be a press
+            (0, 50, 50, 0.9),
+            (10, 0, 0, 0.0),
+            (60, 0, 0, 0.0),
+            // a press that dips under the start threshold, and briefly
+            // under the release threshold
+            (100, 10, 20, 0.3),
+            (140, 10, 20, 0.3),
+            (160, 10, 20, 0.2),
+            (180, 0, 0, 0.1),
+            (190, 10, 20, 0.3),
+            (240, 0, 0, 0.0),
+            (280, 0, 0, 0.0),
+        ]);
+
+        assert_eq!(
+            events,
+            vec![TouchEvent::Down(10, 20), TouchEvent::Up(10, 20)]
+        );
+    }
+
+    #[test]
+    fn long_press() {
+        let events = replay(&[
+            (0, 100, 100, 0.5),
+            (40, 100, 100, 0.5),
+            (500, 103, 98, 0.5),
+            (900, 103, 98, 0.5),
+            (1000, 103, 98, 0.5),
+            (1100, 0, 0, 0.0),
+            (1200, 0, 0, 0.0),
+        ]);
+
+        assert_eq!(
+            events,
+            vec![
+                TouchEvent::Down(100, 100),
+                TouchEvent::LongPress(100, 100),
+                TouchEvent::Release(100, 100),
+            ]
+        );
+    }
+
+    #[test]
+    fn long_press_through_jitter() {
+        // a resistive panel wanders by a pixel on every sample
+        let mut samples: Vec<_> = (0..25)
+            .map(|i| (i * 40, 100 + i as i32 % 2, 100 - i as i32 % 3 + 1, 0.5))
+            .collect();
+        samples.extend([(1000, 0, 0, 0.0), (1100, 0, 0, 0.0)]);
+
+        let events = replay(&samples);
+
+        // down once debounced, at the second sample
+        assert_eq!(
+            events,
+            vec![
+                TouchEvent::Down(101, 100),
+                TouchEvent::LongPress(101, 100),
+                TouchEvent::Release(101, 100),
+            ]
+        );
+    }
+
+    #[test]
+    fn dragging_is_not_a_long_press() {
+        let events = replay(&[
+            (0, 100, 100, 0.5),
+            (40, 100, 100, 0.5),
+            (400, 100, 120, 0.5),
+            (1000, 100, 120, 0.5),
+            (1100, 0, 0, 0.0),
+            (1200, 0, 0, 0.0),
+        ]);
+
+        assert_eq!(
+            events,
+            vec![
+                TouchEvent::Down(100, 100),
+                TouchEvent::Drag {
+                    from: (100, 100),
+                    to: (100, 120)
+                },
+                TouchEvent::Up(100, 120),
+            ]
+        );
+    }
+
+    #[test]
+    fn swipes() {
+        let swipe = |to: i32| {
+            replay(&[
+                (0, 160, 100, 0.5),
+                (40, 160, 100, 0.5),
+                (120, (160 + to) / 2, 105, 0.5),
+                (200, to, 110, 0.5),
+                (240, 0, 0, 0.0),
+                (280, 0, 0, 0.0),
+            ])
+            .pop()
+        };
+
+        assert_eq!(
+            swipe(40),
+            Some(TouchEvent::Swipe {
+                direction: SwipeDirection::Left,
+                from: (160, 100),
+                to: (40, 110)
+            })
+        );
+        assert!(matches!(
+            swipe(300),
+            Some(TouchEvent::Swipe {
+                direction: SwipeDirection::Right,
+                ..
+            })
+        ));
+        // too short
+        assert_eq!(swipe(140), Some(TouchEvent::Up(140, 110)));
+    }
+
+    #[test]
+    fn slow_swipe_is_a_drag() {
+        let events = replay(&[
+            (0, 160, 100, 0.5),
+            (40, 160, 100, 0.5),
+            (800, 40, 100, 0.5),
+            (840, 0, 0, 0.0),
+            (880, 0, 0, 0.0),
+        ]);
+
+        assert_eq!(events.last(), Some(&TouchEvent::Up(40, 100)));
+    }
+}
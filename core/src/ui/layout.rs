@@ -0,0 +1,387 @@
+use embedded_graphics::{
+    geometry::{Point, Size},
+    primitives::Rectangle,
+};
+
+use super::ui::UiDimension;
+
+/// Where to put something smaller than the space given to it.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Start,
+    Center,
+    End,
+}
+
+impl Align {
+    fn offset(&self, size: u32, length: u32) -> i32 {
+        let free = length.saturating_sub(size) as i32;
+
+        match self {
+            Align::Start => 0,
+            Align::Center => free / 2,
+            Align::End => free,
+        }
+    }
+}
+
+/// Space kept clear inside the edges of a container.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Insets {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32,
+}
+
+impl Insets {
+    pub const fn all(inset: u32) -> Self {
+        Self {
+            top: inset,
+            right: inset,
+            bottom: inset,
+            left: inset,
+        }
+    }
+
+    pub fn shrink(&self, area: Rectangle) -> Rectangle {
+        Rectangle::new(
+            area.top_left + Point::new(self.left as i32, self.top as i32),
+            Size::new(
+                area.size.width.saturating_sub(self.left + self.right),
+                area.size.height.saturating_sub(self.top + self.bottom),
+            ),
+        )
+    }
+}
+
+/// Place `size` within `area`.
+pub fn place(area: Rectangle, size: Size, horizontal: Align, vertical: Align) -> Rectangle {
+    Rectangle::new(
+        area.top_left
+            + Point::new(
+                horizontal.offset(size.width, area.size.width),
+                vertical.offset(size.height, area.size.height),
+            ),
+        size,
+    )
+}
+
+/// Size of one dimension given `length` to share, clamping negative fixed sizes.
+fn resolve(dimension: UiDimension, length: u32) -> Option<u32> {
+    match dimension {
+        UiDimension::Fixed(size) => Some(size.max(0) as u32),
+        UiDimension::Percent(percent) => Some((length as f32 * percent / 100.0).round() as u32),
+        UiDimension::Auto => None,
+    }
+}
+
+/// Split `length` between `dimensions` separated by `gap`, returning the
+/// offset and size of each.
+///
+/// Fixed and percentage sizes are taken first, with percentages of the
+/// length left after gaps.  Auto sizes share what remains equally.  Any
+/// space still left over is placed according to `justify`.
+fn distribute(
+    dimensions: &[UiDimension],
+    length: u32,
+    gap: u32,
+    justify: Align,
+) -> Vec<(i32, u32)> {
+    let gaps = gap * dimensions.len().saturating_sub(1) as u32;
+    let available = length.saturating_sub(gaps);
+
+    let resolved: Vec<Option<u32>> = dimensions.iter().map(|d| resolve(*d, available)).collect();
+    let taken: u32 = resolved.iter().flatten().sum();
+    let autos = resolved.iter().filter(|r| r.is_none()).count() as u32;
+
+    let remaining = available.saturating_sub(taken);
+    let mut extra = if autos > 0 { remaining % autos } else { 0 };
+
+    let sizes: Vec<u32> = resolved
+        .iter()
+        .map(|r| match r {
+            Some(size) => *size,
+            None => {
+                let size = remaining / autos + u32::from(extra > 0);
+                extra = extra.saturating_sub(1);
+                size
+            }
+        })
+        .collect();
+
+    let total = sizes.iter().sum::<u32>() + gaps;
+    let mut offset = justify.offset(total, length);
+
+    sizes
+        .into_iter()
+        .map(|size| {
+            let placed = (offset, size);
+            offset += (size + gap) as i32;
+            placed
+        })
+        .collect()
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Direction {
+    Horizontal,
+    Vertical,
+}
+
+/// Children side by side in a row, or stacked in a column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Linear {
+    direction: Direction,
+    children: Vec<UiDimension>,
+    cross: UiDimension,
+    gap: u32,
+    padding: Insets,
+    justify: Align,
+    align: Align,
+}
+
+/// Children left to right, sized across by `children`.
+pub fn row(children: impl IntoIterator<Item = UiDimension>) -> Linear {
+    Linear::new(Direction::Horizontal, children)
+}
+
+/// Children top to bottom, sized down by `children`.
+pub fn column(children: impl IntoIterator<Item = UiDimension>) -> Linear {
+    Linear::new(Direction::Vertical, children)
+}
+
+impl Linear {
+    fn new(direction: Direction, children: impl IntoIterator<Item = UiDimension>) -> Self {
+        Self {
+            direction,
+            children: children.into_iter().collect(),
+            cross: UiDimension::Auto,
+            gap: 0,
+            padding: Insets::default(),
+            justify: Align::Start,
+            align: Align::Start,
+        }
+    }
+
+    /// Space between children.
+    pub fn gap(mut self, gap: u32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    pub fn padding(mut self, padding: Insets) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Placement along the row or column when the children do not fill it.
+    pub fn justify(mut self, justify: Align) -> Self {
+        self.justify = justify;
+        self
+    }
+
+    /// Size of every child across the row or column; `Auto` fills it.
+    pub fn cross(mut self, cross: UiDimension) -> Self {
+        self.cross = cross;
+        self
+    }
+
+    /// Placement across the row or column of children smaller than it.
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Area of each child within `area`, in order.
+    pub fn layout(&self, area: Rectangle) -> Vec<Rectangle> {
+        let inner = self.padding.shrink(area);
+
+        let (length, breadth) = match self.direction {
+            Direction::Horizontal => (inner.size.width, inner.size.height),
+            Direction::Vertical => (inner.size.height, inner.size.width),
+        };
+
+        let across = resolve(self.cross, breadth).unwrap_or(breadth);
+        let cross_offset = self.align.offset(across, breadth);
+
+        distribute(&self.children, length, self.gap, self.justify)
+            .into_iter()
+            .map(|(offset, size)| match self.direction {
+                Direction::Horizontal => Rectangle::new(
+                    inner.top_left + Point::new(offset, cross_offset),
+                    Size::new(size, across),
+                ),
+                Direction::Vertical => Rectangle::new(
+                    inner.top_left + Point::new(cross_offset, offset),
+                    Size::new(across, size),
+                ),
+            })
+            .collect()
+    }
+}
+
+/// Children in cells of a table, filled a row at a time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    columns: Vec<UiDimension>,
+    rows: Vec<UiDimension>,
+    gap: u32,
+    padding: Insets,
+    justify: Align,
+    align: Align,
+}
+
+impl Grid {
+    pub fn new(
+        columns: impl IntoIterator<Item = UiDimension>,
+        rows: impl IntoIterator<Item = UiDimension>,
+    ) -> Self {
+        Self {
+            columns: columns.into_iter().collect(),
+            rows: rows.into_iter().collect(),
+            gap: 0,
+            padding: Insets::default(),
+            justify: Align::Start,
+            align: Align::Start,
+        }
+    }
+
+    /// Space between both rows and columns.
+    pub fn gap(mut self, gap: u32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    pub fn padding(mut self, padding: Insets) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Horizontal placement of columns that do not fill the width.
+    pub fn justify(mut self, justify: Align) -> Self {
+        self.justify = justify;
+        self
+    }
+
+    /// Vertical placement of rows that do not fill the height.
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Area of each cell within `area`, left to right then top to bottom.
+    pub fn layout(&self, area: Rectangle) -> Vec<Rectangle> {
+        let inner = self.padding.shrink(area);
+        let columns = distribute(&self.columns, inner.size.width, self.gap, self.justify);
+        let rows = distribute(&self.rows, inner.size.height, self.gap, self.align);
+
+        rows.iter()
+            .flat_map(|(y, height)| {
+                columns.iter().map(move |(x, width)| {
+                    Rectangle::new(
+                        inner.top_left + Point::new(*x, *y),
+                        Size::new(*width, *height),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn distributes_fixed_percent_and_auto() {
+        let dimensions = [
+            UiDimension::Fixed(10),
+            UiDimension::Percent(50.0),
+            UiDimension::Auto,
+            UiDimension::Auto,
+        ];
+
+        // 100 less three gaps of 2 leaves 94: 10 fixed, 47 percent, 37 shared
+        assert_eq!(
+            distribute(&dimensions, 100, 2, Align::Start),
+            vec![(0, 10), (12, 47), (61, 19), (82, 18)]
+        );
+    }
+
+    #[test]
+    fn justifies_leftover_space() {
+        let dimensions = [UiDimension::Fixed(10), UiDimension::Fixed(10)];
+
+        assert_eq!(
+            distribute(&dimensions, 50, 0, Align::End),
+            vec![(30, 10), (40, 10)]
+        );
+        assert_eq!(
+            distribute(&dimensions, 50, 0, Align::Center),
+            vec![(15, 10), (25, 10)]
+        );
+        // negative sizes are empty, and overflowing children are not squeezed
+        assert_eq!(
+            distribute(
+                &[UiDimension::Fixed(-5), UiDimension::Fixed(60)],
+                50,
+                0,
+                Align::Start
+            ),
+            vec![(0, 0), (0, 60)]
+        );
+    }
+
+    #[test]
+    fn row_and_column() {
+        let area = rect(10, 20, 100, 40);
+
+        let cells = row([UiDimension::Fixed(30), UiDimension::Auto])
+            .gap(4)
+            .padding(Insets::all(2))
+            .cross(UiDimension::Fixed(10))
+            .align(Align::Center)
+            .layout(area);
+        assert_eq!(cells, vec![rect(12, 35, 30, 10), rect(46, 35, 62, 10)]);
+
+        let cells = column([UiDimension::Auto, UiDimension::Percent(25.0)]).layout(area);
+        assert_eq!(cells, vec![rect(10, 20, 100, 30), rect(10, 50, 100, 10)]);
+    }
+
+    #[test]
+    fn grid_fills_rows_first() {
+        let cells = Grid::new([UiDimension::Auto; 2], [UiDimension::Fixed(5); 2])
+            .gap(1)
+            .align(Align::End)
+            .layout(rect(0, 0, 21, 20));
+
+        assert_eq!(
+            cells,
+            vec![
+                rect(0, 9, 10, 5),
+                rect(11, 9, 10, 5),
+                rect(0, 15, 10, 5),
+                rect(11, 15, 10, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn places_within_area() {
+        assert_eq!(
+            place(
+                rect(0, 0, 100, 50),
+                Size::new(20, 10),
+                Align::Center,
+                Align::End
+            ),
+            rect(40, 40, 20, 10)
+        );
+    }
+}
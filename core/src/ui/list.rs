@@ -0,0 +1,315 @@
+use std::{
+    cell::RefCell,
+    collections::hash_map::DefaultHasher,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb565,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+    Drawable,
+};
+
+use super::{
+    theme,
+    ui::{TouchEvent, UiElement, UiEvent},
+};
+
+/// Space between rows, beyond the height of the theme's font.
+const ROW_SPACING: u32 = 2;
+const TEXT_INSET: i32 = 2;
+const SCROLLBAR_WIDTH: u32 = 10;
+
+/// Scrollable list of text rows, e.g. flight logs or peers.
+///
+/// Tapping a row selects it.  Dragging scrolls, as does tapping the upper
+/// or lower half of the scrollbar on the right to move up or down a page.
+pub struct List {
+    area: Rectangle,
+    items: Rc<RefCell<Vec<String>>>,
+    first: usize,
+    selected: Option<usize>,
+    /// Drag not yet scrolled by, in pixels
+    drag: i32,
+    /// The touch in progress has scrolled, so is not a tap
+    scrolled: bool,
+    /// Hash of what was last drawn, so items need not be copied to compare
+    last_drawn: Option<u64>,
+    on_select: Box<dyn Fn(usize)>,
+}
+
+impl List {
+    pub fn new(area: Rectangle, on_select: Box<dyn Fn(usize)>) -> Self {
+        Self {
+            area,
+            items: Rc::new(RefCell::new(Vec::new())),
+            first: 0,
+            selected: None,
+            drag: 0,
+            scrolled: false,
+            last_drawn: None,
+            on_select,
+        }
+    }
+
+    pub fn items_ref(&self) -> Rc<RefCell<Vec<String>>> {
+        self.items.clone()
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+            .filter(|selected| *selected < self.items.borrow().len())
+    }
+
+    /// Highlight `index`, as when it is tapped but without calling `on_select`.
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index;
+    }
+
+    fn row_height(&self) -> u32 {
+        theme::current().font.character_size.height + ROW_SPACING
+    }
+
+    fn visible_rows(&self) -> usize {
+        (self.area.size.height / self.row_height()).max(1) as usize
+    }
+
+    /// Index of the top row, kept in range as items come and go.
+    fn first(&self) -> usize {
+        let last_page = self
+            .items
+            .borrow()
+            .len()
+            .saturating_sub(self.visible_rows());
+        self.first.min(last_page)
+    }
+
+    /// Move the view by `rows`, negative being towards the start.
+    pub fn scroll_by(&mut self, rows: isize) {
+        self.first = self.first().saturating_add_signed(rows);
+        self.first = self.first();
+    }
+
+    fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (&*self.items.borrow(), self.first(), self.selected()).hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl<D> UiElement<D> for List
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    fn handle_event(&mut self, event: UiEvent) {
+        let (x, y) = match event {
+            UiEvent::TouchEnter(TouchEvent::Down(..)) => {
+                self.drag = 0;
+                self.scrolled = false;
+                return;
+            }
+            UiEvent::Drag(TouchEvent::Drag { from, to }) => {
+                // content follows the finger, so dragging up scrolls down
+                let row_height = self.row_height() as i32;
+                self.drag += to.1 - from.1;
+                let rows = self.drag / row_height;
+                if rows != 0 {
+                    self.scroll_by(-rows as isize);
+                    self.drag -= rows * row_height;
+                    self.scrolled = true;
+                }
+                return;
+            }
+            UiEvent::Tap(TouchEvent::Up(_, _)) if self.scrolled => {
+                self.scrolled = false;
+                return;
+            }
+            UiEvent::Tap(TouchEvent::Up(x, y)) => (x, y),
+            _ => return,
+        };
+
+        let scrollbar = self.area.top_left.x + (self.area.size.width - SCROLLBAR_WIDTH) as i32;
+        let page = self.visible_rows() as isize;
+
+        if x >= scrollbar {
+            if y < self.area.center().y {
+                self.scroll_by(-page);
+            } else {
+                self.scroll_by(page);
+            }
+        } else {
+            let row =
+                ((y - self.area.top_left.y) as u32 / self.row_height()) as usize + self.first();
+            if row < self.items.borrow().len() {
+                self.selected = Some(row);
+                (*self.on_select)(row);
+            }
+        }
+    }
+
+    fn dirty(&self) -> bool {
+        self.last_drawn != Some(self.state_hash())
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        self.area
+    }
+
+    fn draw(&mut self, display: &mut D) {
+        let theme = theme::current();
+        let row_height = self.row_height();
+        let items = self.items.borrow();
+        let (first, selected) = (self.first(), self.selected());
+
+        self.area
+            .into_styled(PrimitiveStyle::with_fill(theme.background))
+            .draw(display)
+            .unwrap();
+
+        let row_width = self.area.size.width - SCROLLBAR_WIDTH - 1;
+
+        for (i, item) in items
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(self.visible_rows())
+        {
+            let top = self.area.top_left + Point::new(0, ((i - first) as u32 * row_height) as i32);
+
+            // the selected row is drawn inverted, like a pressed button
+            let text_color = if Some(i) == selected {
+                Rectangle::new(top, Size::new(row_width, row_height))
+                    .into_styled(PrimitiveStyle::with_fill(theme.foreground))
+                    .draw(display)
+                    .unwrap();
+                theme.background
+            } else {
+                theme.foreground
+            };
+
+            Text::with_baseline(
+                item,
+                top + Point::new(TEXT_INSET, row_height as i32 / 2),
+                MonoTextStyle::new(theme.font, text_color),
+                Baseline::Middle,
+            )
+            .draw(display)
+            .unwrap();
+        }
+
+        // scrollbar, with a thumb sized to the share of items in view
+        let track = Rectangle::new(
+            self.area.top_left + Point::new((self.area.size.width - SCROLLBAR_WIDTH) as i32, 0),
+            Size::new(SCROLLBAR_WIDTH, self.area.size.height),
+        );
+        track
+            .into_styled(PrimitiveStyle::with_stroke(theme.foreground, 1))
+            .draw(display)
+            .unwrap();
+
+        let count = items.len().max(1) as u32;
+        let inner = track.size.height.saturating_sub(4);
+        let visible = (self.visible_rows() as u32).min(count);
+        let thumb = Rectangle::new(
+            track.top_left + Point::new(2, 2 + (inner * first as u32 / count) as i32),
+            Size::new(SCROLLBAR_WIDTH - 4, (inner * visible / count).max(1)),
+        );
+        thumb
+            .into_styled(PrimitiveStyle::with_fill(theme.foreground))
+            .draw(display)
+            .unwrap();
+
+        drop(items);
+        self.last_drawn = Some(self.state_hash());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::ui::{
+        calibration::TouchCalibration,
+        framebuffer::{assert_snapshot, Framebuffer, PALETTE},
+        ui::Ui,
+    };
+
+    fn tap(list: &mut List, x: i32, y: i32) {
+        UiElement::<Framebuffer>::handle_event(list, UiEvent::Tap(TouchEvent::Up(x, y)));
+    }
+
+    #[test]
+    fn selects_and_scrolls() {
+        let chosen = Rc::new(Cell::new(None));
+        let chosen2 = chosen.clone();
+        let mut display = Framebuffer::new(60, 36);
+        let mut list = List::new(
+            Rectangle::new(Point::zero(), Size::new(60, 36)),
+            Box::new(move |i| chosen2.set(Some(i))),
+        );
+        list.items_ref()
+            .borrow_mut()
+            .extend((1..=5).map(|i| format!("LOG {}", i)));
+
+        tap(&mut list, 10, 15);
+        assert_eq!(chosen.get(), Some(1));
+        list.draw(&mut display);
+        assert_snapshot("list_top", &display.to_ascii(&PALETTE));
+
+        // a page down shows the last three rows
+        tap(&mut list, 55, 30);
+        assert!(UiElement::<Framebuffer>::dirty(&list));
+        list.draw(&mut display);
+        assert_snapshot("list_scrolled", &display.to_ascii(&PALETTE));
+
+        tap(&mut list, 10, 30);
+        assert_eq!(chosen.get(), Some(4));
+
+        // rows past the end are ignored
+        list.items_ref().borrow_mut().truncate(2);
+        tap(&mut list, 10, 30);
+        assert_eq!(chosen.get(), Some(4));
+        assert_eq!(list.selected(), None);
+    }
+
+    #[test]
+    fn drags_to_scroll() {
+        let chosen = Rc::new(Cell::new(None));
+        let chosen2 = chosen.clone();
+        let mut ui = Ui::<Framebuffer>::new(60, 36);
+        ui.touch_calibration(TouchCalibration::IDENTITY);
+
+        let list = List::new(
+            Rectangle::new(Point::zero(), Size::new(60, 36)),
+            Box::new(move |i| chosen2.set(Some(i))),
+        );
+        list.items_ref()
+            .borrow_mut()
+            .extend((1..=8).map(|i| format!("LOG {}", i)));
+        ui.add_element(Box::new(list));
+
+        // up by two rows, which is not a tap
+        for (time, y, z) in [
+            (0, 30.0, 1.0),
+            (50, 30.0, 1.0),
+            (300, 18.0, 1.0),
+            (600, 6.0, 1.0),
+            (650, 6.0, 0.0),
+            (700, 6.0, 0.0),
+        ] {
+            ui.handle_touch((10.0, y, z), time);
+        }
+        assert_eq!(chosen.get(), None);
+
+        ui.tap(10, 5);
+        assert_eq!(chosen.get(), Some(2));
+    }
+}
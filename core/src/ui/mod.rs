@@ -0,0 +1,15 @@
+pub mod button;
+pub mod calibration;
+pub mod chart;
+pub mod compositor;
+pub mod dialog;
+pub mod framebuffer;
+pub mod gesture;
+pub mod layout;
+pub mod list;
+pub mod readout;
+pub mod screen;
+pub mod text;
+pub mod theme;
+#[allow(clippy::module_inception)]
+pub mod ui;
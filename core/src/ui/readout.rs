@@ -0,0 +1,175 @@
+use std::{cell::Cell, fmt::Debug, rc::Rc};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb565,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+
+use super::{
+    theme,
+    ui::{UiElement, UiEvent},
+};
+
+/// Labelled number with units, e.g. `ALT 1234.5 ft`.
+///
+/// The label is drawn at the left of the area and the value and units at
+/// the right; a missing value shows as dashes.
+pub struct Readout {
+    area: Rectangle,
+    label: &'static str,
+    unit: &'static str,
+    precision: usize,
+    value: Rc<Cell<Option<f32>>>,
+    /// [`shown`] of the value last drawn, compared without formatting it
+    last_drawn: Option<Option<(i64, u32)>>,
+}
+
+impl Readout {
+    pub fn new(area: Rectangle, label: &'static str, unit: &'static str, precision: usize) -> Self {
+        Self {
+            area,
+            label,
+            unit,
+            precision,
+            value: Rc::new(Cell::new(None)),
+            last_drawn: None,
+        }
+    }
+
+    pub fn value_ref(&self) -> Rc<Cell<Option<f32>>> {
+        self.value.clone()
+    }
+
+    fn formatted(&self) -> String {
+        match self.value.get() {
+            Some(value) => format!("{:.*} {}", self.precision, value, self.unit),
+            None => format!("--- {}", self.unit),
+        }
+    }
+}
+
+/// `value` in steps of `precision` decimal places, and its sign, which are
+/// the same for two values exactly when they format the same.
+fn shown(value: f32, precision: usize) -> (i64, u32) {
+    if !value.is_finite() {
+        return (0, value.to_bits());
+    }
+
+    // exact, as an f32 times a small power of ten fits in an f64
+    let scaled = value as f64 * 10_f64.powi(precision as i32);
+    let steps = if (scaled - scaled.trunc()).abs() == 0.5 {
+        // ties go to even, as formatting does
+        (scaled / 2.0).round() * 2.0
+    } else {
+        scaled.round()
+    };
+
+    (steps as i64, value.is_sign_negative() as u32)
+}
+
+impl<D> UiElement<D> for Readout
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    fn handle_event(&mut self, _event: UiEvent) {}
+
+    fn dirty(&self) -> bool {
+        self.last_drawn != Some(self.value.get().map(|value| shown(value, self.precision)))
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        self.area
+    }
+
+    fn draw(&mut self, display: &mut D) {
+        let text = self.formatted();
+        let theme = theme::current();
+        let text_style = MonoTextStyle::new(theme.font, theme.foreground);
+        let middle = self.area.center().y;
+
+        self.area
+            .into_styled(PrimitiveStyle::with_fill(theme.background))
+            .draw(display)
+            .unwrap();
+
+        Text::with_baseline(
+            self.label,
+            (self.area.top_left.x, middle).into(),
+            text_style,
+            Baseline::Middle,
+        )
+        .draw(display)
+        .unwrap();
+
+        Text::with_text_style(
+            &text,
+            (
+                self.area.top_left.x + self.area.size.width as i32 - 1,
+                middle,
+            )
+                .into(),
+            text_style,
+            TextStyleBuilder::new()
+                .alignment(Alignment::Right)
+                .baseline(Baseline::Middle)
+                .build(),
+        )
+        .draw(display)
+        .unwrap();
+
+        self.last_drawn = Some(self.value.get().map(|value| shown(value, self.precision)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::geometry::{Point, Size};
+
+    use super::*;
+    use crate::ui::framebuffer::{assert_snapshot, Framebuffer, PALETTE};
+
+    #[test]
+    fn renders_value_with_units() {
+        let mut display = Framebuffer::new(80, 12);
+        let mut readout = Readout::new(
+            Rectangle::new(Point::zero(), Size::new(80, 12)),
+            "ALT",
+            "ft",
+            1,
+        );
+        let value = readout.value_ref();
+
+        assert!(UiElement::<Framebuffer>::dirty(&readout));
+        readout.draw(&mut display);
+        assert_snapshot("readout_empty", &display.to_ascii(&PALETTE));
+
+        value.set(Some(1234.56));
+        assert!(UiElement::<Framebuffer>::dirty(&readout));
+        readout.draw(&mut display);
+        assert!(!UiElement::<Framebuffer>::dirty(&readout));
+        assert_snapshot("readout_value", &display.to_ascii(&PALETTE));
+
+        // changes below the shown precision need no redraw
+        value.set(Some(1234.58));
+        assert!(!UiElement::<Framebuffer>::dirty(&readout));
+    }
+
+    #[test]
+    fn compares_as_formatted() {
+        for (a, b) in [(0.25_f32, 0.2), (0.35, 0.3), (2.45, 2.5), (-0.04, 0.04)] {
+            assert_eq!(
+                shown(a, 1) == shown(b, 1),
+                format!("{:.1}", a) == format!("{:.1}", b),
+                "{} and {}",
+                a,
+                b
+            );
+        }
+        assert_ne!(shown(f32::NAN, 1), shown(0.0, 1));
+    }
+}
@@ -0,0 +1,493 @@
+use std::{cell::RefCell, collections::VecDeque, fmt::Debug, rc::Rc};
+
+use embedded_graphics::{draw_target::DrawTarget, pixelcolor::Rgb565};
+
+use super::{
+    calibration::TouchCalibration,
+    compositor::FrameStats,
+    gesture::SwipeDirection,
+    theme,
+    ui::{TouchEvent, Ui},
+};
+
+/// A full page of the interface, or a modal dialog over one.
+///
+/// Each screen gets its own [`Ui`] which it fills in [`Screen::build`]; the
+/// elements live as long as the screen is on the [`Navigator`] stack.
+pub trait Screen<D>
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    /// Add the screen's elements.  Called once, when the screen is shown.
+    fn build(&mut self, ui: &mut Ui<D>, navigation: &Navigation<D>);
+
+    /// Called every frame while the screen is on top, before drawing.
+    fn update(&mut self, _ui: &mut Ui<D>) {}
+
+    /// Another screen was pushed over this one.
+    fn on_pause(&mut self) {}
+
+    /// The screen over this one was popped.
+    fn on_resume(&mut self, _ui: &mut Ui<D>) {}
+
+    /// The screen was popped or replaced and is about to be dropped.
+    fn on_exit(&mut self) {}
+
+    /// The screen was swiped, as to move to a neighbouring screen.
+    fn on_swipe(&mut self, _direction: SwipeDirection, _navigation: &Navigation<D>) {}
+
+    /// A sample straight from the touch panel, before calibration.  Return
+    /// true to keep it from the screen's elements.
+    fn on_raw_touch(
+        &mut self,
+        _touch: (f64, f64, f64),
+        _time: u32,
+        _navigation: &Navigation<D>,
+    ) -> bool {
+        false
+    }
+}
+
+enum Request<D> {
+    Push(Box<dyn Screen<D>>),
+    Modal(Box<dyn Screen<D>>),
+    Replace(Box<dyn Screen<D>>),
+    Pop,
+    Redraw,
+    TouchCalibration(TouchCalibration),
+}
+
+/// Handle for changing screens from within element callbacks.
+///
+/// Requests are queued and carried out by the next [`Navigator::update`].
+pub struct Navigation<D> {
+    requests: Rc<RefCell<VecDeque<Request<D>>>>,
+}
+
+impl<D> Clone for Navigation<D> {
+    fn clone(&self) -> Self {
+        Self {
+            requests: self.requests.clone(),
+        }
+    }
+}
+
+impl<D> Navigation<D>
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    /// Show `screen` in place of the current one, which resumes when it is popped.
+    pub fn push(&self, screen: impl Screen<D> + 'static) {
+        self.request(Request::Push(Box::new(screen)));
+    }
+
+    /// Show `screen` over the current one, which stays visible beneath it
+    /// but no longer receives touches.
+    pub fn modal(&self, screen: impl Screen<D> + 'static) {
+        self.request(Request::Modal(Box::new(screen)));
+    }
+
+    /// Swap the current screen for `screen`.
+    pub fn replace(&self, screen: impl Screen<D> + 'static) {
+        self.request(Request::Replace(Box::new(screen)));
+    }
+
+    /// Return to the previous screen.  The first screen is never popped.
+    pub fn pop(&self) {
+        self.request(Request::Pop);
+    }
+
+    /// Clear the display and draw every visible screen again.
+    pub fn redraw(&self) {
+        self.request(Request::Redraw);
+    }
+
+    /// Calibrate touches on every screen with `touch_calibration`.
+    pub fn touch_calibration(&self, touch_calibration: TouchCalibration) {
+        self.request(Request::TouchCalibration(touch_calibration));
+    }
+
+    fn request(&self, request: Request<D>) {
+        self.requests.borrow_mut().push_back(request);
+    }
+}
+
+struct Entry<D> {
+    screen: Box<dyn Screen<D>>,
+    ui: Ui<D>,
+    modal: bool,
+}
+
+/// Stack of screens, of which the top one receives touches.
+pub struct Navigator<D> {
+    width: u16,
+    height: u16,
+    stack: Vec<Entry<D>>,
+    navigation: Navigation<D>,
+    touch_calibration: Option<TouchCalibration>,
+    redraw: bool,
+}
+
+impl<D> Navigator<D>
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            stack: Vec::new(),
+            navigation: Navigation {
+                requests: Rc::new(RefCell::new(VecDeque::new())),
+            },
+            touch_calibration: None,
+            redraw: true,
+        }
+    }
+
+    pub fn navigation(&self) -> Navigation<D> {
+        self.navigation.clone()
+    }
+
+    /// Used by the [`Ui`] of every screen, current and future.
+    pub fn touch_calibration(&mut self, touch_calibration: TouchCalibration) {
+        self.touch_calibration = Some(touch_calibration);
+        for entry in self.stack.iter_mut() {
+            entry.ui.touch_calibration(touch_calibration);
+        }
+    }
+
+    /// Drawing time of every screen since last taken, resetting it.
+    pub fn take_frame_stats(&mut self) -> FrameStats {
+        let mut stats = FrameStats::default();
+        for entry in self.stack.iter_mut() {
+            stats.merge(&entry.ui.take_frame_stats());
+        }
+        stats
+    }
+
+    /// Number of screens on the stack.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Pass a touch sample taken at `time` ms to the top screen.
+    pub fn handle_touch(&mut self, touch: (f64, f64, f64), time: u32) {
+        if let Some(top) = self.stack.last_mut() {
+            if top.screen.on_raw_touch(touch, time, &self.navigation) {
+                return;
+            }
+
+            if let TouchEvent::Swipe { direction, .. } = top.ui.handle_touch(touch, time) {
+                top.screen.on_swipe(direction, &self.navigation);
+            }
+        }
+    }
+
+    /// Carry out queued navigation, then update the top screen.
+    pub fn update(&mut self) {
+        loop {
+            // release the queue first, building a screen may add to it
+            let request = self.navigation.requests.borrow_mut().pop_front();
+
+            match request {
+                Some(request) => self.apply(request),
+                None => break,
+            }
+        }
+
+        if let Some(top) = self.stack.last_mut() {
+            top.screen.update(&mut top.ui);
+        }
+    }
+
+    /// Draw the top screen, or after navigating, clear the display and draw
+    /// the top screen along with any screens visible beneath modals.
+    pub fn draw(&mut self, display: &mut D) {
+        if self.redraw {
+            display.clear(theme::current().background).unwrap();
+
+            let first_visible = self
+                .stack
+                .iter()
+                .rposition(|entry| !entry.modal)
+                .unwrap_or(0);
+
+            for entry in self.stack[first_visible..].iter_mut() {
+                entry.ui.dirty_all();
+                entry.ui.draw(display);
+            }
+
+            self.redraw = false;
+        } else if let Some(top) = self.stack.last_mut() {
+            top.ui.draw(display);
+        }
+    }
+
+    fn apply(&mut self, request: Request<D>) {
+        match request {
+            Request::Push(screen) => self.open(screen, false),
+            Request::Modal(screen) => self.open(screen, true),
+            Request::Replace(screen) => {
+                let modal = match self.stack.pop() {
+                    Some(mut entry) => {
+                        entry.screen.on_exit();
+                        entry.modal
+                    }
+                    None => false,
+                };
+                self.show(screen, modal);
+            }
+            Request::Pop => {
+                if self.stack.len() > 1 {
+                    let mut entry = self.stack.pop().unwrap();
+                    entry.screen.on_exit();
+
+                    let top = self.stack.last_mut().unwrap();
+                    top.screen.on_resume(&mut top.ui);
+                }
+            }
+            Request::Redraw => (),
+            Request::TouchCalibration(touch_calibration) => {
+                self.touch_calibration(touch_calibration);
+                return;
+            }
+        }
+
+        self.redraw = true;
+    }
+
+    fn open(&mut self, screen: Box<dyn Screen<D>>, modal: bool) {
+        if let Some(top) = self.stack.last_mut() {
+            top.screen.on_pause();
+        }
+        self.show(screen, modal);
+    }
+
+    fn show(&mut self, mut screen: Box<dyn Screen<D>>, modal: bool) {
+        let mut ui = Ui::new(self.width, self.height);
+        if let Some(touch_calibration) = self.touch_calibration {
+            ui.touch_calibration(touch_calibration);
+        }
+
+        screen.build(&mut ui, &self.navigation);
+        self.stack.push(Entry { screen, ui, modal });
+    }
+}
+
+#[cfg(test)]
+impl<D> Navigator<D>
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    /// Touch `x`, `y` on the screen with `samples` of (time in ms,
+    /// pressure), as [`TAP`](super::gesture::TAP), then update.
+    pub(crate) fn touch(&mut self, x: i32, y: i32, samples: &[(u32, f64)]) {
+        for (time, z) in samples {
+            self.handle_touch((x as f64, y as f64, *z), *time);
+        }
+        self.update();
+    }
+
+    pub(crate) fn tap(&mut self, x: i32, y: i32) {
+        self.touch(x, y, &super::gesture::TAP);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{geometry::Point, prelude::*};
+
+    use super::*;
+    use crate::ui::{button::Button, framebuffer::Framebuffer};
+
+    type Log = Rc<RefCell<Vec<String>>>;
+    type OnClick = Rc<dyn Fn(&Navigation<Framebuffer>)>;
+
+    /// Screen with one button filling `area`, that logs its lifecycle and
+    /// runs `on_click` with the navigation handle when tapped.
+    struct TestScreen {
+        name: &'static str,
+        area: (Point, Size),
+        log: Log,
+        on_click: OnClick,
+    }
+
+    impl TestScreen {
+        fn new(
+            name: &'static str,
+            log: &Log,
+            on_click: impl Fn(&Navigation<Framebuffer>) + 'static,
+        ) -> Self {
+            Self {
+                name,
+                area: (Point::zero(), Size::new(320, 240)),
+                log: log.clone(),
+                on_click: Rc::new(on_click),
+            }
+        }
+
+        fn record(&self, event: &str) {
+            self.log
+                .borrow_mut()
+                .push(format!("{} {}", self.name, event));
+        }
+    }
+
+    impl Screen<Framebuffer> for TestScreen {
+        fn build(&mut self, ui: &mut Ui<Framebuffer>, navigation: &Navigation<Framebuffer>) {
+            self.record("build");
+
+            let navigation = navigation.clone();
+            let on_click = self.on_click.clone();
+            ui.add_element(Box::new(Button::new(
+                self.area.0,
+                self.area.1,
+                self.name.to_string(),
+                Box::new(move || on_click(&navigation)),
+            )));
+        }
+
+        fn on_pause(&mut self) {
+            self.record("pause");
+        }
+
+        fn on_resume(&mut self, _ui: &mut Ui<Framebuffer>) {
+            self.record("resume");
+        }
+
+        fn on_exit(&mut self) {
+            self.record("exit");
+        }
+
+        fn on_swipe(&mut self, direction: SwipeDirection, _navigation: &Navigation<Framebuffer>) {
+            self.record(&format!("swipe {:?}", direction));
+        }
+    }
+
+    fn navigator() -> Navigator<Framebuffer> {
+        let mut navigator = Navigator::new(320, 240);
+        navigator.touch_calibration(TouchCalibration::IDENTITY);
+        navigator
+    }
+
+    #[test]
+    fn push_and_pop() {
+        let log = Log::default();
+        let mut navigator = navigator();
+
+        let child_log = log.clone();
+        navigator
+            .navigation()
+            .push(TestScreen::new("home", &log, move |nav| {
+                nav.push(TestScreen::new("child", &child_log, |nav| nav.pop()));
+            }));
+        navigator.update();
+        assert_eq!(navigator.depth(), 1);
+
+        navigator.tap(10, 10);
+        assert_eq!(navigator.depth(), 2);
+
+        navigator.tap(10, 10);
+        assert_eq!(navigator.depth(), 1);
+
+        // the first screen stays
+        navigator.navigation().pop();
+        navigator.update();
+        assert_eq!(navigator.depth(), 1);
+
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                "home build",
+                "home pause",
+                "child build",
+                "child exit",
+                "home resume"
+            ]
+        );
+    }
+
+    #[test]
+    fn replace_keeps_depth() {
+        let log = Log::default();
+        let mut navigator = navigator();
+
+        let next_log = log.clone();
+        navigator
+            .navigation()
+            .push(TestScreen::new("first", &log, move |nav| {
+                nav.replace(TestScreen::new("second", &next_log, |_| ()));
+            }));
+        navigator.update();
+        navigator.tap(10, 10);
+
+        assert_eq!(navigator.depth(), 1);
+        assert_eq!(
+            *log.borrow(),
+            vec!["first build", "first exit", "second build"]
+        );
+    }
+
+    #[test]
+    fn swipes_reach_the_top_screen() {
+        let log = Log::default();
+        let mut navigator = navigator();
+
+        navigator
+            .navigation()
+            .push(TestScreen::new("home", &log, |nav| nav.pop()));
+        navigator.update();
+
+        for (time, x, z) in [
+            (0, 200.0, 1.0),
+            (50, 200.0, 1.0),
+            (150, 100.0, 1.0),
+            (200, 100.0, 0.0),
+            (250, 100.0, 0.0),
+        ] {
+            navigator.handle_touch((x, 120.0, z), time);
+        }
+
+        // and are not taps
+        assert_eq!(*log.borrow(), vec!["home build", "home swipe Left"]);
+    }
+
+    #[test]
+    fn modal_draws_over_and_takes_touches() {
+        let log = Log::default();
+        let mut navigator = navigator();
+        let mut display = Framebuffer::new(320, 240);
+
+        let modal_log = log.clone();
+        navigator
+            .navigation()
+            .push(TestScreen::new("home", &log, move |nav| {
+                let mut dialog = TestScreen::new("dialog", &modal_log, |nav| nav.pop());
+                dialog.area = (Point::new(100, 100), Size::new(50, 50));
+                nav.modal(dialog);
+            }));
+        navigator.update();
+        navigator.draw(&mut display);
+
+        navigator.tap(10, 10);
+        navigator.draw(&mut display);
+        assert_eq!(navigator.depth(), 2);
+
+        // both screens are visible
+        assert_eq!(display.pixel(Point::new(0, 0)), Some(Rgb565::GREEN));
+        assert_eq!(display.pixel(Point::new(100, 100)), Some(Rgb565::GREEN));
+
+        // the screen beneath ignores touches while the dialog is open
+        navigator.tap(10, 10);
+        assert_eq!(navigator.depth(), 2);
+
+        navigator.tap(120, 120);
+        navigator.draw(&mut display);
+        assert_eq!(navigator.depth(), 1);
+    }
+}
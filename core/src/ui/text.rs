@@ -1,21 +1,21 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
 
 use embedded_graphics::{
-    geometry::{Dimensions, Point, Size},
-    mono_font::{ascii::FONT_6X10, MonoTextStyle},
-    pixelcolor::{Rgb565, RgbColor},
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point},
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb565,
     primitives::{PrimitiveStyle, Rectangle, StyledDrawable},
     text::Text as GfxText,
     Drawable,
 };
 
-use super::ui::{ColorTheme, UiElement};
+use super::{compositor::union, theme, ui::UiElement};
 
 pub struct Text {
     text: Rc<RefCell<String>>,
     last_drawn: String,
     position: Point,
-    color_theme: ColorTheme,
 }
 
 impl Text {
@@ -27,17 +27,17 @@ impl Text {
         Self {
             text: Rc::new(RefCell::new(text)),
             position,
-            color_theme: ColorTheme {
-                text_color: Rgb565::GREEN,
-                ..ColorTheme::default()
-            },
             last_drawn: "".to_string(),
         }
     }
 }
 
-impl UiElement for Text {
-    fn handle_event(&mut self, event: super::ui::UiEvent) {
+impl<D> UiElement<D> for Text
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    fn handle_event(&mut self, _event: super::ui::UiEvent) {
         // don't care..
     }
 
@@ -45,13 +45,20 @@ impl UiElement for Text {
         !self.text.borrow().eq(&self.last_drawn)
     }
 
+    /// Covers both the text and what was last drawn, so a change clears
+    /// the old text.
     fn bounding_box(&self) -> Rectangle {
-        Rectangle::new(self.position, Size::new(5, 5))
+        let text_style = MonoTextStyle::new(theme::current().font, theme::current().foreground);
+        let text = GfxText::new(&self.text.borrow(), self.position, text_style).bounding_box();
+        let drawn = GfxText::new(&self.last_drawn, self.position, text_style).bounding_box();
+
+        union(&text, &drawn)
     }
 
-    fn draw(&mut self, display: &mut ez_cyd_rs::CydDisplay) {
-        let style = PrimitiveStyle::with_fill(Rgb565::BLACK);
-        let text_style = MonoTextStyle::new(&FONT_6X10, self.color_theme.text_color);
+    fn draw(&mut self, display: &mut D) {
+        let theme = theme::current();
+        let style = PrimitiveStyle::with_fill(theme.background);
+        let text_style = MonoTextStyle::new(theme.font, theme.foreground);
         let text: String = self.text.borrow().clone();
 
         // clear the previous area
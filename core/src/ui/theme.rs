@@ -0,0 +1,218 @@
+use std::cell::Cell;
+
+use embedded_graphics::{
+    mono_font::{
+        ascii::{FONT_10X20, FONT_4X6, FONT_5X8, FONT_6X10, FONT_6X13_BOLD, FONT_9X18_BOLD},
+        MonoFont,
+    },
+    pixelcolor::{Rgb565, RgbColor},
+};
+
+use super::ui::ColorTheme;
+use crate::storage::{KeyValueStore, StorageError};
+
+/// Colors and fonts shared by every element.
+pub struct Theme {
+    pub name: &'static str,
+    pub background: Rgb565,
+    pub foreground: Rgb565,
+    /// Colors for chart series, in the order they are added
+    pub series: [Rgb565; 2],
+    /// Axis labels and other fine print
+    pub small_font: &'static MonoFont<'static>,
+    pub font: &'static MonoFont<'static>,
+    /// Headline numbers
+    pub large_font: &'static MonoFont<'static>,
+}
+
+impl Theme {
+    /// Foreground on background, as for text and outlined buttons.
+    pub fn normal(&self) -> ColorTheme {
+        ColorTheme {
+            text_color: self.foreground,
+            fill: self.background,
+            outline: self.foreground,
+        }
+    }
+
+    /// Background on foreground, as for pressed buttons and selections.
+    pub fn inverted(&self) -> ColorTheme {
+        ColorTheme {
+            text_color: self.background,
+            fill: self.foreground,
+            outline: self.foreground,
+        }
+    }
+}
+
+/// Themes that can be chosen from the settings screen.  The first is used
+/// until another is selected.
+pub static THEMES: [Theme; 3] = [
+    Theme {
+        name: "NIGHT GREEN",
+        background: Rgb565::BLACK,
+        foreground: Rgb565::GREEN,
+        series: [Rgb565::GREEN, Rgb565::CYAN],
+        small_font: &FONT_4X6,
+        font: &FONT_6X10,
+        large_font: &FONT_10X20,
+    },
+    // black on white with heavier type, readable in direct sun
+    Theme {
+        name: "DAYLIGHT",
+        background: Rgb565::WHITE,
+        foreground: Rgb565::BLACK,
+        series: [Rgb565::BLACK, Rgb565::BLUE],
+        small_font: &FONT_5X8,
+        font: &FONT_6X13_BOLD,
+        large_font: &FONT_9X18_BOLD,
+    },
+    // nothing but red, to keep night vision at the pad
+    Theme {
+        name: "RED NIGHT",
+        background: Rgb565::BLACK,
+        foreground: Rgb565::RED,
+        series: [Rgb565::RED, Rgb565::new(16, 0, 0)],
+        small_font: &FONT_4X6,
+        font: &FONT_6X10,
+        large_font: &FONT_10X20,
+    },
+];
+
+/// Storage key of the selected theme's name.
+const STORAGE_KEY: &str = "theme";
+
+thread_local! {
+    // the UI is single threaded, and tests each get their own selection
+    static CURRENT: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The selected theme.
+pub fn current() -> &'static Theme {
+    &THEMES[CURRENT.with(|current| current.get())]
+}
+
+pub fn find(name: &str) -> Option<&'static Theme> {
+    THEMES.iter().find(|theme| theme.name == name)
+}
+
+/// Select the theme called `name`, returning false if there is none.
+///
+/// Elements pick up the theme when next drawn, so the screen should be
+/// redrawn in full afterwards.
+pub fn select(name: &str) -> bool {
+    match THEMES.iter().position(|theme| theme.name == name) {
+        Some(i) => {
+            CURRENT.with(|current| current.set(i));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Select the theme saved by [`save`], if there is one.
+pub fn load<S>(store: &S) -> Result<(), StorageError>
+where
+    S: KeyValueStore + ?Sized,
+{
+    let mut buffer = [0_u8; 32];
+
+    if let Some(len) = store.get(STORAGE_KEY, &mut buffer)? {
+        let name = std::str::from_utf8(&buffer[..len]).map_err(|_| StorageError::Malformed)?;
+        if !select(name) {
+            log::warn!("saved theme {} no longer exists", name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Save the selected theme, to be restored by [`load`] after a restart.
+pub fn save<S>(store: &mut S) -> Result<(), StorageError>
+where
+    S: KeyValueStore + ?Sized,
+{
+    store.set(STORAGE_KEY, current().name.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{
+        draw_target::DrawTarget,
+        geometry::{Point, Size},
+        primitives::Rectangle,
+    };
+
+    use super::*;
+    use crate::{
+        storage::MemoryStore,
+        ui::{
+            button::Button,
+            framebuffer::{assert_snapshot, Framebuffer, PALETTE},
+            readout::Readout,
+            ui::UiElement,
+        },
+    };
+
+    #[test]
+    fn selects_by_name() {
+        assert_eq!(current().name, "NIGHT GREEN");
+
+        assert!(select("DAYLIGHT"));
+        assert_eq!(current().background, Rgb565::WHITE);
+
+        assert!(!select("SEPIA"));
+        assert_eq!(current().name, "DAYLIGHT");
+
+        select(THEMES[0].name);
+    }
+
+    #[test]
+    fn persists_selection() {
+        let mut store = MemoryStore::new();
+
+        // nothing saved keeps the default
+        load(&store).unwrap();
+        assert_eq!(current().name, "NIGHT GREEN");
+
+        select("RED NIGHT");
+        save(&mut store).unwrap();
+        select("NIGHT GREEN");
+
+        load(&store).unwrap();
+        assert_eq!(current().name, "RED NIGHT");
+
+        // a theme removed by an update is ignored
+        store.set(STORAGE_KEY, b"SEPIA").unwrap();
+        load(&store).unwrap();
+        assert_eq!(current().name, "RED NIGHT");
+
+        select(THEMES[0].name);
+    }
+
+    #[test]
+    fn draws_in_daylight() {
+        let mut display = Framebuffer::new(80, 36);
+        let mut button = Button::new(
+            Point::new(0, 0),
+            Size::new(40, 20),
+            "TON".to_string(),
+            Box::new(|| ()),
+        );
+        let mut readout = Readout::new(
+            Rectangle::new(Point::new(0, 22), Size::new(80, 14)),
+            "V+",
+            "V",
+            2,
+        );
+        readout.value_ref().set(Some(3.92));
+
+        select("DAYLIGHT");
+        display.clear(current().background).unwrap();
+        UiElement::<Framebuffer>::draw(&mut button, &mut display);
+        UiElement::<Framebuffer>::draw(&mut readout, &mut display);
+        select(THEMES[0].name);
+
+        assert_snapshot("theme_daylight", &display.to_ascii(&PALETTE));
+    }
+}
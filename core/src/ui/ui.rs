@@ -0,0 +1,506 @@
+use std::{fmt::Debug, marker::PhantomData, time::Instant};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    pixelcolor::{Rgb565, RgbColor},
+    primitives::Rectangle,
+};
+
+use super::{
+    calibration::TouchCalibration,
+    compositor::{bands, DirtyRegions, FrameStats, BAND_PIXELS},
+    framebuffer::Framebuffer,
+    gesture::{GestureRecognizer, SwipeDirection},
+    theme,
+};
+
+/// Elements drawn on, and receiving touches from, a display of type `D`.
+///
+/// Elements are composed in memory and only the areas that changed are
+/// written to the display, each in one go, so overlapping elements don't
+/// flicker.  Later elements, and those with a higher z, are on top.
+pub struct Ui<D> {
+    width: u16,
+    height: u16,
+
+    /// In drawing order, bottom first
+    layers: Vec<Layer>,
+    dirty: DirtyRegions,
+    /// Reused for composing each band of a region
+    canvas: Framebuffer,
+    stats: FrameStats,
+
+    gestures: GestureRecognizer,
+    /// Where the touch in progress started
+    touch_origin: Option<(i32, i32)>,
+    touch_calibration: TouchCalibration,
+
+    dirty_all: bool,
+    display: PhantomData<D>,
+}
+
+struct Layer {
+    element: Box<dyn UiElement<Framebuffer>>,
+    z: i16,
+    /// Where the element was last drawn
+    drawn: Option<Rectangle>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TouchEvent {
+    Down(i32, i32),
+    /// Released without a long press or swipe, i.e. a tap
+    Up(i32, i32),
+    Drag {
+        from: (i32, i32),
+        to: (i32, i32),
+    },
+    /// Held in place for [`LONG_PRESS_MS`](super::gesture::LONG_PRESS_MS)
+    LongPress(i32, i32),
+    /// Released after a long press
+    Release(i32, i32),
+    Swipe {
+        direction: SwipeDirection,
+        from: (i32, i32),
+        to: (i32, i32),
+    },
+    None,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum UiEvent {
+    TouchEnter(TouchEvent),
+    TouchLeave(TouchEvent),
+    Tap(TouchEvent),
+    LongPress(TouchEvent),
+    /// The touch moved, having started on the element
+    Drag(TouchEvent),
+}
+
+/// Size of a child in a [`layout`](super::layout) container, `Percent` being
+/// out of 100.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UiDimension {
+    Fixed(i16),
+    Auto,
+    Percent(f32),
+}
+
+pub trait UiElement<D>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    // type Item;
+    fn handle_event(&mut self, event: UiEvent);
+    fn dirty(&self) -> bool;
+    fn bounding_box(&self) -> Rectangle;
+    fn draw(&mut self, display: &mut D);
+
+    /// Whether the element acts on a long press.  If not, a long press
+    /// released over it is a tap, so a slow tap still works.
+    fn handles_long_press(&self) -> bool {
+        false
+    }
+}
+
+impl<D> Ui<D>
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            dirty_all: true,
+            gestures: GestureRecognizer::new(),
+            touch_origin: None,
+            layers: Vec::new(),
+            dirty: DirtyRegions::new(),
+            canvas: Framebuffer::new(0, 0),
+            stats: FrameStats::default(),
+            touch_calibration: TouchCalibration::IDENTITY,
+            display: PhantomData,
+        }
+    }
+
+    /// The whole display.
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle::new(
+            Point::zero(),
+            Size::new(self.width as u32, self.height as u32),
+        )
+    }
+
+    /// Redraw every element on the next draw.  The display between them is
+    /// left alone, so modals can be drawn over the screen beneath.
+    pub fn dirty_all(&mut self) {
+        self.dirty_all = true;
+    }
+
+    /// Repaint `area` on the next draw, with whatever elements cover it.
+    pub fn invalidate(&mut self, area: Rectangle) {
+        self.dirty.add(area);
+    }
+
+    pub fn touch_calibration(&mut self, touch_calibration: TouchCalibration) {
+        self.touch_calibration = touch_calibration;
+    }
+
+    /// Add an element on top of those with the same or lower z.
+    pub fn add_element(&mut self, element: Box<dyn UiElement<Framebuffer>>) {
+        self.add_element_at(element, 0);
+    }
+
+    /// Add an element at depth `z`, above elements with a lower z and below
+    /// those with a higher one.
+    pub fn add_element_at(&mut self, element: Box<dyn UiElement<Framebuffer>>, z: i16) {
+        let i = self.layers.partition_point(|layer| layer.z <= z);
+        self.layers.insert(
+            i,
+            Layer {
+                element,
+                z,
+                drawn: None,
+            },
+        );
+    }
+
+    /// Remove every element, blanking where they were drawn.
+    pub fn clear(&mut self) {
+        for layer in self.layers.drain(..) {
+            if let Some(drawn) = layer.drawn {
+                self.dirty.add(drawn);
+            }
+        }
+    }
+
+    /// Drawing time since last taken, resetting it.
+    pub fn take_frame_stats(&mut self) -> FrameStats {
+        std::mem::take(&mut self.stats)
+    }
+
+    /// Repaint the areas of changed elements, along with anything that
+    /// overlaps them.
+    pub fn draw(&mut self, display: &mut D) {
+        let started = Instant::now();
+
+        for layer in self.layers.iter() {
+            if self.dirty_all || layer.element.dirty() {
+                let area = layer.element.bounding_box();
+                self.dirty.add(area);
+
+                // it moved or shrank, so uncover what was beneath
+                if let Some(drawn) = layer.drawn.filter(|drawn| *drawn != area) {
+                    self.dirty.add(drawn);
+                }
+            }
+        }
+        self.dirty_all = false;
+
+        if self.dirty.is_empty() {
+            return;
+        }
+
+        let bounds = self.bounds();
+        let background = theme::current().background;
+        let (mut regions, mut pixels) = (0, 0);
+
+        for region in self.dirty.take() {
+            let region = region.intersection(&bounds);
+            if region.is_zero_sized() {
+                continue;
+            }
+
+            for band in bands(region, BAND_PIXELS) {
+                self.canvas.reset(band, background);
+
+                // each element is drawn once per band it overlaps, which the
+                // canvas clips to the band's rows
+                for layer in self.layers.iter_mut() {
+                    let area = layer.element.bounding_box();
+                    if !area.intersection(&band).is_zero_sized() {
+                        layer.element.draw(&mut self.canvas);
+                    }
+                }
+
+                // one window and burst of pixels per band
+                display
+                    .fill_contiguous(&band, self.canvas.pixels().iter().copied())
+                    .unwrap();
+                pixels += band.size.width * band.size.height;
+            }
+            regions += 1;
+        }
+
+        for layer in self.layers.iter_mut() {
+            layer.drawn = Some(layer.element.bounding_box());
+        }
+
+        self.stats.record(regions, pixels, started.elapsed());
+    }
+
+    /// Index of the topmost element at `point`.
+    fn hit(&self, point: (i32, i32)) -> Option<usize> {
+        self.layers
+            .iter()
+            .rposition(|layer| layer.element.bounding_box().contains(point.into()))
+    }
+
+    fn send(&mut self, target: Option<usize>, event: UiEvent) {
+        if let Some(i) = target {
+            self.layers[i].element.handle_event(event);
+        }
+    }
+
+    fn process_touch(&mut self, touch: (f64, f64, f64), time: u32) -> TouchEvent {
+        let (tx, ty, tz) = touch;
+
+        // log::info!("raw touch: {:?}", touch);
+
+        let (x, y) = self.touch_calibration.apply(tx, ty);
+        let (x, y) = (x as i32, y as i32);
+
+        // log::info!("touch at {} {}", x, y);
+
+        self.gestures.update(time, x, y, tz)
+    }
+
+    /// Handle a raw sample from the touch panel taken at `time` ms,
+    /// returning the gesture it completed, if any.
+    pub fn handle_touch(&mut self, touch: (f64, f64, f64), time: u32) -> TouchEvent {
+        let event = self.process_touch(touch, time);
+
+        if let TouchEvent::None = event {
+        } else {
+            // log::info!("Received event {:?}", event);
+        }
+
+        match event {
+            TouchEvent::Down(x, y) => self.touch_origin = Some((x, y)),
+            TouchEvent::Up(..) | TouchEvent::Release(..) | TouchEvent::Swipe { .. } => {
+                self.touch_origin = None
+            }
+            _ => (),
+        }
+        let origin = self.touch_origin;
+
+        // only the topmost element at a point is touched
+        match event {
+            TouchEvent::Down(x, y) => self.send(self.hit((x, y)), UiEvent::TouchEnter(event)),
+            TouchEvent::Up(x, y) => self.send(self.hit((x, y)), UiEvent::Tap(event)),
+            TouchEvent::LongPress(x, y) => self.send(self.hit((x, y)), UiEvent::LongPress(event)),
+            TouchEvent::Release(x, y) => {
+                let target = self.hit((x, y));
+                match target {
+                    Some(i) if !self.layers[i].element.handles_long_press() => {
+                        self.send(target, UiEvent::Tap(TouchEvent::Up(x, y)))
+                    }
+                    _ => self.send(target, UiEvent::TouchLeave(event)),
+                }
+            }
+            // the touch ended without a tap
+            TouchEvent::Swipe { to: (x, y), .. } => {
+                self.send(self.hit((x, y)), UiEvent::TouchLeave(event))
+            }
+            TouchEvent::Drag { from, to } => {
+                // did we move from one element onto another?
+                let (left, entered) = (self.hit(from), self.hit(to));
+                if left != entered {
+                    self.send(left, UiEvent::TouchLeave(event));
+                    self.send(entered, UiEvent::TouchEnter(event));
+                }
+
+                if let Some(origin) = origin {
+                    self.send(self.hit(origin), UiEvent::Drag(event));
+                }
+            }
+            TouchEvent::None => (),
+        }
+
+        event
+    }
+}
+
+#[cfg(test)]
+impl<D> Ui<D>
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    /// Tap at `x`, `y` on the screen, with the identity calibration used
+    /// in tests.
+    pub(crate) fn tap(&mut self, x: i32, y: i32) {
+        for (time, z) in super::gesture::TAP {
+            self.handle_touch((x as f64, y as f64, z), time);
+        }
+    }
+}
+
+pub struct ColorTheme {
+    pub text_color: Rgb565,
+    pub fill: Rgb565,
+    pub outline: Rgb565,
+}
+
+impl Default for ColorTheme {
+    fn default() -> Self {
+        Self {
+            text_color: Rgb565::BLACK,
+            fill: Rgb565::BLACK,
+            outline: Rgb565::BLACK,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use embedded_graphics::{
+        primitives::{Primitive, PrimitiveStyle},
+        Drawable,
+    };
+
+    use super::*;
+    use crate::ui::framebuffer::PALETTE;
+
+    /// Rectangle of a color, both of which can be changed, counting taps.
+    struct Block {
+        area: Rc<Cell<Rectangle>>,
+        color: Rc<Cell<Rgb565>>,
+        taps: Rc<Cell<u32>>,
+        last_drawn: Option<(Rectangle, Rgb565)>,
+    }
+
+    impl Block {
+        fn new(x: i32, y: i32, width: u32, height: u32, color: Rgb565) -> Self {
+            Self {
+                area: Rc::new(Cell::new(Rectangle::new(
+                    Point::new(x, y),
+                    Size::new(width, height),
+                ))),
+                color: Rc::new(Cell::new(color)),
+                taps: Rc::new(Cell::new(0)),
+                last_drawn: None,
+            }
+        }
+    }
+
+    impl<D> UiElement<D> for Block
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        fn handle_event(&mut self, event: UiEvent) {
+            if let UiEvent::Tap(_) = event {
+                self.taps.set(self.taps.get() + 1);
+            }
+        }
+
+        fn dirty(&self) -> bool {
+            self.last_drawn != Some((self.area.get(), self.color.get()))
+        }
+
+        fn bounding_box(&self) -> Rectangle {
+            self.area.get()
+        }
+
+        fn draw(&mut self, display: &mut D) {
+            self.area
+                .get()
+                .into_styled(PrimitiveStyle::with_fill(self.color.get()))
+                .draw(display)
+                .unwrap();
+            self.last_drawn = Some((self.area.get(), self.color.get()));
+        }
+    }
+
+    #[test]
+    fn draws_only_changed_regions() {
+        let mut display = Framebuffer::new(8, 4);
+        let mut ui = Ui::new(8, 4);
+        let left = Block::new(0, 0, 2, 2, Rgb565::GREEN);
+        let right = Block::new(4, 0, 3, 3, Rgb565::GREEN);
+        let color = right.color.clone();
+        ui.add_element(Box::new(left));
+        ui.add_element(Box::new(right));
+
+        ui.draw(&mut display);
+        let stats = ui.take_frame_stats();
+        assert_eq!((stats.frames, stats.regions, stats.pixels), (1, 2, 13));
+
+        // nothing changed, nothing written
+        ui.draw(&mut display);
+        assert_eq!(ui.take_frame_stats().frames, 0);
+
+        color.set(Rgb565::RED);
+        ui.draw(&mut display);
+        let stats = ui.take_frame_stats();
+        assert_eq!((stats.regions, stats.pixels), (1, 9));
+        assert_eq!(
+            display.to_ascii(&PALETTE),
+            "##..rrr.\n##..rrr.\n....rrr.\n........\n"
+        );
+    }
+
+    #[test]
+    fn composes_overlapping_elements_in_z_order() {
+        let mut display = Framebuffer::new(6, 4);
+        let mut ui = Ui::new(6, 4);
+        ui.touch_calibration(TouchCalibration::IDENTITY);
+        let top = Block::new(2, 1, 2, 2, Rgb565::YELLOW);
+        let bottom = Block::new(0, 0, 6, 4, Rgb565::GREEN);
+        let (top_taps, bottom_taps) = (top.taps.clone(), bottom.taps.clone());
+        let color = bottom.color.clone();
+        // added first, but above
+        ui.add_element_at(Box::new(top), 1);
+        ui.add_element(Box::new(bottom));
+
+        ui.draw(&mut display);
+        color.set(Rgb565::CYAN);
+        ui.draw(&mut display);
+
+        assert_eq!(
+            display.to_ascii(&PALETTE),
+            "cccccc\nccyycc\nccyycc\ncccccc\n"
+        );
+
+        ui.tap(2, 2);
+        ui.tap(5, 3);
+        assert_eq!((top_taps.get(), bottom_taps.get()), (1, 1));
+    }
+
+    #[test]
+    fn slow_tap_is_a_tap() {
+        let mut ui = Ui::<Framebuffer>::new(6, 4);
+        ui.touch_calibration(TouchCalibration::IDENTITY);
+        let block = Block::new(0, 0, 6, 4, Rgb565::GREEN);
+        let taps = block.taps.clone();
+        ui.add_element(Box::new(block));
+
+        for (time, z) in [(0, 1.0), (50, 1.0), (900, 1.0), (950, 0.0), (1000, 0.0)] {
+            ui.handle_touch((2.0, 2.0, z), time);
+        }
+        assert_eq!(taps.get(), 1);
+    }
+
+    #[test]
+    fn uncovers_what_was_beneath() {
+        let mut display = Framebuffer::new(6, 2);
+        let mut ui = Ui::new(6, 2);
+        let block = Block::new(0, 0, 6, 2, Rgb565::GREEN);
+        let area = block.area.clone();
+        ui.add_element(Box::new(block));
+
+        ui.draw(&mut display);
+        area.set(Rectangle::new(Point::new(4, 0), Size::new(2, 2)));
+        ui.draw(&mut display);
+        assert_eq!(display.to_ascii(&PALETTE), "....##\n....##\n");
+
+        ui.clear();
+        ui.draw(&mut display);
+        assert_eq!(display.to_ascii(&PALETTE), "......\n......\n");
+    }
+}
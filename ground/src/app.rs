@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+
+use rocket_protocol::{
+    datalink::packet::PacketKind, link::LinkStatus, serial::SerialLine, telemetry::Telemetry,
+};
+
+use crate::link::Incoming;
+
+/// Samples kept for the plot.
+pub const PLOT_HISTORY: usize = 600;
+/// Console messages kept on screen.
+const MESSAGE_HISTORY: usize = 200;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MessageKind {
+    /// A line sent to the basestation
+    Sent,
+    Reply,
+    Error,
+    /// News from the basestation, such as an ack from the rocket
+    Event,
+    /// Other console output, such as log messages
+    Console,
+}
+
+/// What the ground station knows of the rocket and the basestation.
+pub struct App {
+    pub latest: Option<Telemetry>,
+    /// Time in s and altitude in ft of recent samples, oldest first
+    pub altitudes: VecDeque<(f64, f64)>,
+    pub link: Option<LinkStatus>,
+    pub messages: VecDeque<(MessageKind, String)>,
+    /// Console line being typed
+    pub input: String,
+    /// Why the link closed, once it has
+    pub closed: Option<String>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self {
+            latest: None,
+            altitudes: VecDeque::with_capacity(PLOT_HISTORY),
+            link: None,
+            messages: VecDeque::with_capacity(MESSAGE_HISTORY),
+            input: String::new(),
+            closed: None,
+        }
+    }
+
+    pub fn message(&mut self, kind: MessageKind, text: &str) {
+        for line in text.lines() {
+            if self.messages.len() == MESSAGE_HISTORY {
+                self.messages.pop_front();
+            }
+            self.messages.push_back((kind, line.to_string()));
+        }
+    }
+
+    /// Take in something heard from the basestation, returning the sample
+    /// if it carried one.
+    pub fn handle(&mut self, incoming: Incoming) -> Option<Telemetry> {
+        match incoming {
+            Incoming::Line(SerialLine::Frame(frame)) => return self.frame(&frame),
+            Incoming::Line(SerialLine::Reply(Ok(reply))) => {
+                self.message(MessageKind::Reply, &reply)
+            }
+            Incoming::Line(SerialLine::Reply(Err(e))) => {
+                self.message(MessageKind::Error, &format!("error: {}", e))
+            }
+            Incoming::Line(SerialLine::Event(text)) => self.message(MessageKind::Event, &text),
+            Incoming::Text(text) => self.message(MessageKind::Console, &text),
+            Incoming::Closed(reason) => {
+                self.message(MessageKind::Error, &format!("link closed: {}", reason));
+                self.closed = Some(reason);
+            }
+        }
+
+        None
+    }
+
+    fn frame(&mut self, frame: &[u8]) -> Option<Telemetry> {
+        match PacketKind::of(frame) {
+            Some(PacketKind::Telemetry) => match Telemetry::from_frame(frame) {
+                Ok(telemetry) => {
+                    if self.altitudes.len() == PLOT_HISTORY {
+                        self.altitudes.pop_front();
+                    }
+                    self.altitudes
+                        .push_back((telemetry.time as f64 / 1000.0, telemetry.altitude as f64));
+                    self.latest = Some(telemetry);
+                    Some(telemetry)
+                }
+                Err(e) => {
+                    self.message(MessageKind::Error, &format!("bad telemetry: {:?}", e));
+                    None
+                }
+            },
+            Some(PacketKind::LinkStatus) => {
+                self.link = LinkStatus::from_frame(frame);
+                None
+            }
+            _ => {
+                self.message(MessageKind::Error, "unknown frame");
+                None
+            }
+        }
+    }
+}
+
+/// One line describing a sample, for printing.
+pub fn summary(telemetry: &Telemetry) -> String {
+    format!(
+        "{:>8.2} s  {:>8.1} ft  max {:>8.1} ft  {:>6.1} ft/s  {}  {:>5.1} C  {:.2} V",
+        telemetry.time as f64 / 1000.0,
+        telemetry.altitude,
+        telemetry.maximum_altitude,
+        telemetry.vertical_velocity,
+        telemetry.phase.label(),
+        telemetry.temperature,
+        telemetry.battery_voltage
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket_protocol::{flight::FlightPhase, telemetry::ProtocolVersion};
+
+    use super::*;
+
+    #[test]
+    fn handles_frames_and_messages() {
+        let mut app = App::new();
+        let sample = Telemetry {
+            time: 1500,
+            altitude: 120.5,
+            phase: FlightPhase::Ascent,
+            ..Telemetry::default()
+        };
+
+        let frame = sample.to_frame(ProtocolVersion::Extended);
+        let received = app.handle(Incoming::Line(SerialLine::Frame(frame)));
+        assert_eq!(
+            received.map(|telemetry| telemetry.phase),
+            Some(FlightPhase::Ascent)
+        );
+        // to the decimetre the frame carries
+        let (time, altitude) = app.altitudes[0];
+        assert_eq!((app.altitudes.len(), time), (1, 1.5));
+        assert!((altitude - 120.5).abs() < 0.2);
+
+        let status = LinkStatus {
+            receiving: true,
+            received: 1,
+            ..LinkStatus::default()
+        };
+        let frame = status.to_frame();
+        assert!(app
+            .handle(Incoming::Line(SerialLine::Frame(frame)))
+            .is_none());
+        assert_eq!(app.link, Some(status));
+
+        app.handle(Incoming::Line(SerialLine::Reply(Ok("a\nb".to_string()))));
+        app.handle(Incoming::Line(SerialLine::Reply(Err("no".to_string()))));
+        app.handle(Incoming::Closed("unplugged".to_string()));
+        assert_eq!(
+            app.messages,
+            [
+                (MessageKind::Reply, "a".to_string()),
+                (MessageKind::Reply, "b".to_string()),
+                (MessageKind::Error, "error: no".to_string()),
+                (MessageKind::Error, "link closed: unplugged".to_string()),
+            ]
+        );
+        assert_eq!(app.closed.as_deref(), Some("unplugged"));
+    }
+
+    #[test]
+    fn keeps_recent_samples() {
+        let mut app = App::new();
+        for time in 0..PLOT_HISTORY as u32 + 5 {
+            let frame = Telemetry {
+                time,
+                ..Telemetry::default()
+            }
+            .to_frame(ProtocolVersion::Compact);
+            app.handle(Incoming::Line(SerialLine::Frame(frame)));
+        }
+
+        assert_eq!(app.altitudes.len(), PLOT_HISTORY);
+        assert_eq!(app.altitudes.front(), Some(&(0.005, 0.0)));
+        assert_eq!(app.latest.map(|telemetry| telemetry.time), Some(604));
+    }
+}
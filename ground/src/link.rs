@@ -0,0 +1,246 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpStream, UdpSocket},
+    sync::mpsc::Sender,
+    time::Duration,
+};
+
+use rocket_protocol::serial::SerialLine;
+
+/// Baud rate of the basestation's console.
+pub const DEFAULT_BAUD_RATE: u32 = 115_200;
+/// How long a read waits, so a closed link is noticed.
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+/// Longest line kept; anything longer is not from the basestation.
+const MAX_LINE: usize = 1024;
+
+/// Where the basestation, or something standing in for it, is reached.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    Serial {
+        path: String,
+        baud_rate: u32,
+    },
+    /// Connect to `host:port`
+    Tcp(String),
+    /// Send datagrams to `host:port`, and read the replies
+    Udp(String),
+}
+
+impl Endpoint {
+    /// Read `serial:PATH[@BAUD]`, `tcp:HOST:PORT` or `udp:HOST:PORT`.  A
+    /// bare path is a serial port.
+    pub fn parse(text: &str) -> Result<Endpoint, String> {
+        let (scheme, rest) = match text.split_once(':') {
+            Some((scheme @ ("serial" | "tcp" | "udp"), rest)) => (scheme, rest),
+            _ => ("serial", text),
+        };
+        if rest.is_empty() {
+            return Err(format!("{} needs an address", scheme));
+        }
+
+        match scheme {
+            "tcp" => Ok(Endpoint::Tcp(rest.to_string())),
+            "udp" => Ok(Endpoint::Udp(rest.to_string())),
+            _ => {
+                let (path, baud_rate) = match rest.rsplit_once('@') {
+                    Some((path, baud_rate)) => (
+                        path,
+                        baud_rate
+                            .parse()
+                            .map_err(|_| format!("bad baud rate {}", baud_rate))?,
+                    ),
+                    None => (rest, DEFAULT_BAUD_RATE),
+                };
+                Ok(Endpoint::Serial {
+                    path: path.to_string(),
+                    baud_rate,
+                })
+            }
+        }
+    }
+
+    /// Open the link, returning its two directions.
+    pub fn open(&self) -> io::Result<(Box<dyn Read + Send>, Box<dyn Write + Send>)> {
+        match self {
+            Endpoint::Serial { path, baud_rate } => {
+                let port = serialport::new(path, *baud_rate)
+                    .timeout(READ_TIMEOUT)
+                    .open()?;
+                Ok((Box::new(port.try_clone()?), Box::new(port)))
+            }
+            Endpoint::Tcp(address) => {
+                let stream = TcpStream::connect(address)?;
+                stream.set_read_timeout(Some(READ_TIMEOUT))?;
+                Ok((Box::new(stream.try_clone()?), Box::new(stream)))
+            }
+            Endpoint::Udp(address) => {
+                let socket = UdpSocket::bind("0.0.0.0:0")?;
+                socket.connect(address)?;
+                socket.set_read_timeout(Some(READ_TIMEOUT))?;
+                Ok((
+                    Box::new(Datagrams(socket.try_clone()?)),
+                    Box::new(Datagrams(socket)),
+                ))
+            }
+        }
+    }
+}
+
+/// A connected UDP socket read and written like a stream, each write one
+/// datagram.
+struct Datagrams(UdpSocket);
+
+impl Read for Datagrams {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.recv(buf)
+    }
+}
+
+impl Write for Datagrams {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.send(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Something heard from the basestation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Incoming {
+    Line(SerialLine),
+    /// Any other console output, such as a log message
+    Text(String),
+    /// The link failed, and nothing more will arrive
+    Closed(String),
+}
+
+/// Splits bytes read into lines, whichever of `\r` and `\n` end them.
+#[derive(Default)]
+pub struct LineSplitter {
+    pending: Vec<u8>,
+}
+
+impl LineSplitter {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for &byte in bytes {
+            match byte {
+                b'\r' | b'\n' if !self.pending.is_empty() => {
+                    lines.push(String::from_utf8_lossy(&self.pending).into_owned());
+                    self.pending.clear();
+                }
+                b'\r' | b'\n' => (),
+                byte if self.pending.len() < MAX_LINE => self.pending.push(byte),
+                _ => (),
+            }
+        }
+
+        lines
+    }
+}
+
+/// Read lines from the basestation until the link fails, passing on what
+/// they say.
+pub fn read_lines(mut reader: Box<dyn Read + Send>, sender: Sender<Incoming>) {
+    let mut splitter = LineSplitter::default();
+    let mut buf = [0_u8; 256];
+
+    loop {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => {
+                let _ = sender.send(Incoming::Closed("link closed".to_string()));
+                return;
+            }
+            Ok(len) => len,
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => continue,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => {
+                let _ = sender.send(Incoming::Closed(e.to_string()));
+                return;
+            }
+        };
+
+        for line in splitter.push(&buf[..len]) {
+            let incoming = match SerialLine::from_line(&line) {
+                Some(line) => Incoming::Line(line),
+                None => Incoming::Text(line),
+            };
+            if sender.send(incoming).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Send a console line to the basestation.
+pub fn send_line(writer: &mut dyn Write, line: &str) -> io::Result<()> {
+    writer.write_all(format!("{}\r", line.trim()).as_bytes())?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_endpoints() {
+        assert_eq!(
+            Endpoint::parse("/dev/ttyUSB0"),
+            Ok(Endpoint::Serial {
+                path: "/dev/ttyUSB0".to_string(),
+                baud_rate: DEFAULT_BAUD_RATE
+            })
+        );
+        assert_eq!(
+            Endpoint::parse("serial:/dev/ttyACM1@921600"),
+            Ok(Endpoint::Serial {
+                path: "/dev/ttyACM1".to_string(),
+                baud_rate: 921_600
+            })
+        );
+        assert_eq!(
+            Endpoint::parse("tcp:localhost:7000"),
+            Ok(Endpoint::Tcp("localhost:7000".to_string()))
+        );
+        assert_eq!(
+            Endpoint::parse("udp:127.0.0.1:7001"),
+            Ok(Endpoint::Udp("127.0.0.1:7001".to_string()))
+        );
+
+        assert!(Endpoint::parse("tcp:").is_err());
+        assert!(Endpoint::parse("/dev/ttyUSB0@fast").is_err());
+    }
+
+    #[test]
+    fn splits_lines() {
+        let mut splitter = LineSplitter::default();
+
+        assert_eq!(splitter.push(b"> {\"event\":"), Vec::<String>::new());
+        assert_eq!(
+            splitter.push(b"\"a\"}\r\n\r\nI (10) log\n{"),
+            ["> {\"event\":\"a\"}", "I (10) log"]
+        );
+        assert_eq!(splitter.push(b"}\r"), ["{}"]);
+    }
+
+    #[test]
+    fn reads_until_closed() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let input = b"{\"event\":\"rocket batch = 4\"}\r\nI (10) wifi: started\r\n{\"fr";
+        read_lines(Box::new(&input[..]), sender);
+
+        let received: Vec<Incoming> = receiver.iter().collect();
+        assert_eq!(
+            received,
+            [
+                Incoming::Line(SerialLine::Event("rocket batch = 4".to_string())),
+                Incoming::Text("I (10) wifi: started".to_string()),
+                Incoming::Closed("link closed".to_string()),
+            ]
+        );
+    }
+}
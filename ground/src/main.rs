@@ -0,0 +1,239 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufRead, BufWriter, Write},
+    process::ExitCode,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    DefaultTerminal,
+};
+use rocket_protocol::telemetry::Telemetry;
+
+use app::{summary, App, MessageKind};
+use link::{read_lines, send_line, Endpoint, Incoming};
+use record::CsvLog;
+
+mod app;
+mod link;
+mod record;
+mod ui;
+
+const USAGE: &str = "usage: rocket-ground [--log FILE] [--plain] ENDPOINT
+
+Talks to the basestation's serial console, showing live telemetry and
+sending the console commands typed, such as `ton` or `psl 101325`.
+
+  ENDPOINT     serial:PATH[@BAUD], or just PATH, for the basestation's USB
+               port; tcp:HOST:PORT or udp:HOST:PORT for a stand-in
+  --log FILE   write each sample to FILE as CSV
+  --plain      print samples and replies instead of plotting them, and
+               send lines read from stdin";
+
+/// How often the screen is redrawn while nothing is typed.
+const FRAME_PERIOD: Duration = Duration::from_millis(50);
+
+type Writer = Arc<Mutex<Box<dyn Write + Send>>>;
+
+struct Options {
+    endpoint: Endpoint,
+    log: Option<String>,
+    plain: bool,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut endpoint = None;
+        let mut log = None;
+        let mut plain = false;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--log" => log = Some(args.next().ok_or("--log needs a file")?),
+                "--plain" => plain = true,
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+                _ if endpoint.is_none() => endpoint = Some(Endpoint::parse(&arg)?),
+                _ => return Err("only one endpoint".to_string()),
+            }
+        }
+
+        Ok(Options {
+            endpoint: endpoint.ok_or(USAGE)?,
+            log,
+            plain,
+        })
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("rocket-ground: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(options: Options) -> Result<(), Box<dyn Error>> {
+    let mut log = match &options.log {
+        Some(path) => Some(CsvLog::new(BufWriter::new(File::create(path)?))?),
+        None => None,
+    };
+
+    let (reader, writer) = options
+        .endpoint
+        .open()
+        .map_err(|e| format!("unable to open {:?}: {}", options.endpoint, e))?;
+    let writer: Writer = Arc::new(Mutex::new(writer));
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || read_lines(reader, sender));
+
+    // replies as JSON lines, with every frame dashboard clients see
+    send_line(&mut **writer.lock().unwrap(), "mode machine")?;
+
+    if options.plain {
+        run_plain(&receiver, &writer, &mut log)
+    } else {
+        let mut terminal = ratatui::init();
+        let result = run_tui(&mut terminal, &receiver, &writer, &mut log);
+        ratatui::restore();
+        result
+    }
+}
+
+/// Hand `incoming` to the app, logging any sample it carried.  The log is
+/// dropped if it cannot be written, rather than stopping the display.
+fn record<W: Write>(
+    log: &mut Option<CsvLog<W>>,
+    app: &mut App,
+    incoming: Incoming,
+) -> Option<Telemetry> {
+    let telemetry = app.handle(incoming)?;
+    if let Some(csv) = log {
+        if let Err(e) = csv.push(&telemetry) {
+            app.message(MessageKind::Error, &format!("unable to log: {}", e));
+            *log = None;
+        }
+    }
+    Some(telemetry)
+}
+
+fn run_plain<W: Write>(
+    receiver: &Receiver<Incoming>,
+    writer: &Writer,
+    log: &mut Option<CsvLog<W>>,
+) -> Result<(), Box<dyn Error>> {
+    {
+        let writer = writer.clone();
+        std::thread::spawn(move || {
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                if send_line(&mut **writer.lock().unwrap(), &line).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    let mut app = App::new();
+    for incoming in receiver {
+        let printed = app.messages.len();
+        if let Some(telemetry) = record(log, &mut app, incoming) {
+            println!("{}", summary(&telemetry));
+        }
+        for (_, text) in app.messages.iter().skip(printed) {
+            println!("{}", text);
+        }
+        if let Some(reason) = app.closed {
+            return Err(reason.into());
+        }
+    }
+
+    Ok(())
+}
+
+fn run_tui<W: Write>(
+    terminal: &mut DefaultTerminal,
+    receiver: &Receiver<Incoming>,
+    writer: &Writer,
+    log: &mut Option<CsvLog<W>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut app = App::new();
+
+    loop {
+        while let Ok(incoming) = receiver.try_recv() {
+            record(log, &mut app, incoming);
+        }
+
+        terminal.draw(|frame| ui::draw(frame, &app))?;
+
+        if !event::poll(FRAME_PERIOD)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Char(c) => app.input.push(c),
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            KeyCode::Enter if !app.input.trim().is_empty() => {
+                let line = std::mem::take(&mut app.input);
+                app.message(MessageKind::Sent, &format!("> {}", line));
+                if let Err(e) = send_line(&mut **writer.lock().unwrap(), &line) {
+                    app.message(MessageKind::Error, &format!("unable to send: {}", e));
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_options() {
+        let options = parse(&["--log", "flight.csv", "tcp:localhost:7000"]).unwrap();
+        assert_eq!(
+            options.endpoint,
+            Endpoint::Tcp("localhost:7000".to_string())
+        );
+        assert_eq!(options.log.as_deref(), Some("flight.csv"));
+        assert!(!options.plain);
+
+        assert!(parse(&["--plain", "/dev/ttyUSB0"]).unwrap().plain);
+        assert_eq!(parse(&[]).err().as_deref(), Some(USAGE));
+        assert!(parse(&["--log"]).is_err());
+        assert!(parse(&["/dev/ttyUSB0", "/dev/ttyUSB1"]).is_err());
+        assert!(parse(&["--fast", "/dev/ttyUSB0"]).is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use std::io::{self, Write};
+
+use rocket_protocol::telemetry::{
+    export::{write_csv, CSV_HEADER},
+    Telemetry,
+};
+
+/// Samples written as CSV, in the same columns as the basestation's
+/// `/api/log.csv` download.
+pub struct CsvLog<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CsvLog<W> {
+    /// Start the log with its header.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "{}", CSV_HEADER)?;
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+
+    /// Add a row, flushed so the log survives the program being killed.
+    pub fn push(&mut self, telemetry: &Telemetry) -> io::Result<()> {
+        let mut row = String::new();
+        write_csv(&mut row, telemetry).expect("writing to a String");
+        self.writer.write_all(row.as_bytes())?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket_protocol::telemetry::export::from_csv;
+
+    use super::*;
+
+    #[test]
+    fn writes_header_and_rows() {
+        let mut log = CsvLog::new(Vec::new()).unwrap();
+        for sequence in 0..3 {
+            log.push(&Telemetry {
+                sequence,
+                ..Telemetry::default()
+            })
+            .unwrap();
+        }
+
+        let text = String::from_utf8(log.writer).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+        let sequences: Vec<u32> = lines.map(|row| from_csv(row).unwrap().sequence).collect();
+        assert_eq!(sequences, [0, 1, 2]);
+    }
+}
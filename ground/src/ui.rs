@@ -0,0 +1,166 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    symbols::Marker,
+    text::{Line, Span},
+    widgets::{Axis, Block, Chart, Dataset, GraphType, List, ListItem, Paragraph},
+    Frame,
+};
+use rocket_protocol::telemetry::Status;
+
+use crate::app::{App, MessageKind};
+
+/// Least altitude range plotted, so noise on the pad is not magnified.
+const MIN_ALTITUDE_SPAN: f64 = 10.0;
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [readouts, chart, messages, input] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(8),
+        Constraint::Length(10),
+        Constraint::Length(3),
+    ])
+    .areas(frame.area());
+
+    draw_readouts(frame, app, readouts);
+    draw_chart(frame, app, chart);
+    draw_messages(frame, app, messages);
+
+    let prompt = Paragraph::new(format!("> {}", app.input))
+        .block(Block::bordered().title("command (Enter to send, Esc to quit)"));
+    frame.render_widget(prompt, input);
+    frame.set_cursor_position((input.x + 3 + app.input.chars().count() as u16, input.y + 1));
+}
+
+fn link_span(app: &App) -> Span<'static> {
+    let (text, color) = match (&app.closed, app.link) {
+        (Some(_), _) => ("NO BASESTATION", Color::Red),
+        (None, None) => ("CONNECTING", Color::Gray),
+        (None, Some(link)) if link.receiving => ("RECEIVING", Color::Green),
+        (None, Some(link)) if link.last_sample_age.is_none() => ("NO ROCKET", Color::Red),
+        (None, Some(_)) => ("SIGNAL LOST", Color::Red),
+    };
+
+    Span::styled(
+        format!(" {} ", text),
+        Style::new().fg(Color::Black).bg(color),
+    )
+}
+
+fn draw_readouts(frame: &mut Frame, app: &App, area: Rect) {
+    let value = |value: Option<f32>, digits: usize| {
+        value.map_or("---".to_string(), |value| format!("{:.*}", digits, value))
+    };
+    let latest = app.latest;
+    let warn = |flag: Status| {
+        if latest.is_some_and(|telemetry| telemetry.status.contains(flag)) {
+            Style::new().fg(Color::Red)
+        } else {
+            Style::new()
+        }
+    };
+
+    let mut spans = vec![
+        link_span(app),
+        Span::raw("  ALT "),
+        Span::styled(
+            value(latest.map(|telemetry| telemetry.altitude), 1),
+            warn(Status::SENSOR_FAULT).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(" ft  MAX "),
+        Span::raw(value(latest.map(|telemetry| telemetry.maximum_altitude), 1)),
+        Span::raw(" ft  VEL "),
+        Span::raw(value(
+            latest.map(|telemetry| telemetry.vertical_velocity),
+            1,
+        )),
+        Span::raw(" ft/s  "),
+        Span::raw(latest.map_or("---", |telemetry| telemetry.phase.label())),
+        Span::raw("  TEMP "),
+        Span::raw(value(latest.map(|telemetry| telemetry.temperature), 1)),
+        Span::raw(" C  BATT "),
+        Span::styled(
+            value(latest.map(|telemetry| telemetry.battery_voltage), 2),
+            warn(Status::LOW_BATTERY),
+        ),
+        Span::raw(" V"),
+    ];
+    if let Some(link) = app.link {
+        spans.push(Span::styled(
+            format!("  {} received, {} missed", link.received, link.missed),
+            Style::new().fg(Color::DarkGray),
+        ));
+    }
+
+    let readouts = Paragraph::new(Line::from(spans)).block(Block::bordered().title("rocket"));
+    frame.render_widget(readouts, area);
+}
+
+fn draw_chart(frame: &mut Frame, app: &App, area: Rect) {
+    let points: Vec<(f64, f64)> = app.altitudes.iter().copied().collect();
+
+    let (start, end) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) if last.0 > first.0 => (first.0, last.0),
+        (Some(first), _) => (first.0, first.0 + 1.0),
+        _ => (0.0, 1.0),
+    };
+    let low = points
+        .iter()
+        .map(|point| point.1)
+        .fold(f64::INFINITY, f64::min);
+    let high = points
+        .iter()
+        .map(|point| point.1)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let (low, high) = if low.is_finite() {
+        (low, high.max(low + MIN_ALTITUDE_SPAN))
+    } else {
+        (0.0, MIN_ALTITUDE_SPAN)
+    };
+
+    let dataset = Dataset::default()
+        .name("altitude")
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::new().fg(Color::Cyan))
+        .data(&points);
+
+    let chart = Chart::new(vec![dataset])
+        .block(Block::bordered().title("altitude (ft) against time (s)"))
+        .x_axis(
+            Axis::default()
+                .bounds([start, end])
+                .labels([format!("{:.0}", start), format!("{:.0}", end)]),
+        )
+        .y_axis(
+            Axis::default()
+                .bounds([low, high])
+                .labels([format!("{:.0}", low), format!("{:.0}", high)]),
+        );
+    frame.render_widget(chart, area);
+}
+
+fn draw_messages(frame: &mut Frame, app: &App, area: Rect) {
+    // the latest that fit inside the border
+    let shown = area.height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = app
+        .messages
+        .iter()
+        .skip(app.messages.len().saturating_sub(shown))
+        .map(|(kind, text)| {
+            let style = match kind {
+                MessageKind::Sent => Style::new().add_modifier(Modifier::BOLD),
+                MessageKind::Reply => Style::new(),
+                MessageKind::Error => Style::new().fg(Color::Red),
+                MessageKind::Event => Style::new().fg(Color::Yellow),
+                MessageKind::Console => Style::new().fg(Color::DarkGray),
+            };
+            ListItem::new(text.as_str()).style(style)
+        })
+        .collect();
+
+    frame.render_widget(
+        List::new(items).block(Block::bordered().title("basestation")),
+        area,
+    );
+}
@@ -0,0 +1,471 @@
+use std::{
+    mem::discriminant,
+    time::{Duration, Instant},
+};
+
+use bytes::{Buf, BufMut};
+
+use crate::{config::FlightSetting, datalink::packet::PacketKind};
+
+/// Conversion for pressures entered as on weather reports.  Pressures are
+/// sent and kept in Pa.
+pub const PASCALS_PER_INHG: f64 = 3386.389;
+
+/// Sea level pressures the rocket accepts, in Pa, a little beyond the
+/// records.
+pub const SEA_LEVEL_PRESSURE_MIN: f32 = 84_600.0;
+pub const SEA_LEVEL_PRESSURE_MAX: f32 = 108_400.0;
+
+/// How long to wait for an [`Ack`] before sending a command again.
+pub const ACK_TIMEOUT: Duration = Duration::from_millis(500);
+/// Times a command is sent before giving up on it.
+pub const MAX_ATTEMPTS: u8 = 4;
+
+const SET_SEA_LEVEL_PRESSURE: u8 = 1;
+const GET_CONFIG: u8 = 2;
+const SET_CONFIG: u8 = 3;
+const FACTORY_RESET: u8 = 4;
+
+/// Encoded size of an [`Ack`] frame.
+const ACK_SIZE: usize = 7;
+
+/// Command from the basestation to the flight computer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// One of the rocket's text commands, such as `ton` or `reset`, sent as
+    /// is and not acknowledged
+    Text(String),
+    /// Pressure at sea level used for altitude, in Pa.  The rocket keeps it
+    /// across restarts.
+    SetSeaLevelPressure(f32),
+    /// Read one of the rocket's settings, answered in the [`Ack`]
+    GetConfig(FlightSetting),
+    /// Change and save one of the rocket's settings
+    SetConfig(FlightSetting, f32),
+    /// Forget every saved setting, going back to the defaults
+    FactoryReset,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CommandError {
+    UnknownCommand(u8),
+    UnknownSetting(u8),
+    Malformed,
+}
+
+pub fn valid_sea_level_pressure(pascals: f32) -> bool {
+    (SEA_LEVEL_PRESSURE_MIN..=SEA_LEVEL_PRESSURE_MAX).contains(&pascals)
+}
+
+impl Command {
+    /// Whether the rocket answers the command with an [`Ack`].
+    pub fn needs_ack(&self) -> bool {
+        !matches!(self, Command::Text(_))
+    }
+
+    /// Frame carrying the command, tagged with `id` to be echoed in the
+    /// [`Ack`].  Text commands are sent untagged, as older firmware expects.
+    pub fn to_frame(&self, id: u8) -> Vec<u8> {
+        match self {
+            Command::Text(text) => text.as_bytes().to_vec(),
+            Command::SetSeaLevelPressure(pascals) => {
+                let mut frame = Self::header(id, SET_SEA_LEVEL_PRESSURE);
+                frame.put_f32_le(*pascals);
+                frame
+            }
+            Command::GetConfig(setting) => {
+                let mut frame = Self::header(id, GET_CONFIG);
+                frame.put_u8(*setting as u8);
+                frame
+            }
+            Command::SetConfig(setting, value) => {
+                let mut frame = Self::header(id, SET_CONFIG);
+                frame.put_u8(*setting as u8);
+                frame.put_f32_le(*value);
+                frame
+            }
+            Command::FactoryReset => Self::header(id, FACTORY_RESET),
+        }
+    }
+
+    fn header(id: u8, code: u8) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(8);
+        frame.put_u8(PacketKind::Command as u8);
+        frame.put_u8(id);
+        frame.put_u8(code);
+        frame
+    }
+
+    /// Whether `self` makes a pending `other` pointless to retry, as a
+    /// newer value for the same setting.
+    fn replaces(&self, other: &Command) -> bool {
+        match (self, other) {
+            (Command::GetConfig(a), Command::GetConfig(b))
+            | (Command::SetConfig(a, _), Command::SetConfig(b, _)) => a == b,
+            _ => discriminant(self) == discriminant(other),
+        }
+    }
+
+    /// Decode a received frame into its id and command.  Untagged frames
+    /// are text commands, with id 0.
+    pub fn from_frame(frame: &[u8]) -> Result<(u8, Command), CommandError> {
+        match PacketKind::of(frame) {
+            None => std::str::from_utf8(frame)
+                .map(|text| (0, Command::Text(text.trim().to_string())))
+                .map_err(|_| CommandError::Malformed),
+            Some(PacketKind::Command) if frame.len() >= 3 => {
+                let (id, code, mut payload) = (frame[1], frame[2], &frame[3..]);
+                match code {
+                    SET_SEA_LEVEL_PRESSURE if payload.len() == 4 => {
+                        Ok((id, Command::SetSeaLevelPressure(payload.get_f32_le())))
+                    }
+                    GET_CONFIG if payload.len() == 1 => {
+                        Ok((id, Command::GetConfig(setting(payload[0])?)))
+                    }
+                    SET_CONFIG if payload.len() == 5 => {
+                        let setting = setting(payload.get_u8())?;
+                        Ok((id, Command::SetConfig(setting, payload.get_f32_le())))
+                    }
+                    FACTORY_RESET if payload.is_empty() => Ok((id, Command::FactoryReset)),
+                    SET_SEA_LEVEL_PRESSURE | GET_CONFIG | SET_CONFIG | FACTORY_RESET => {
+                        Err(CommandError::Malformed)
+                    }
+                    code => Err(CommandError::UnknownCommand(code)),
+                }
+            }
+            Some(_) => Err(CommandError::Malformed),
+        }
+    }
+}
+
+fn setting(value: u8) -> Result<FlightSetting, CommandError> {
+    FlightSetting::from_u8(value).ok_or(CommandError::UnknownSetting(value))
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AckStatus {
+    Accepted = 0,
+    /// Out of range or otherwise refused; the setting is unchanged
+    Rejected = 1,
+}
+
+/// The rocket's answer to a command.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ack {
+    /// Id the command was sent with
+    pub id: u8,
+    pub status: AckStatus,
+    /// The setting as the rocket now has it, or 0 for commands without one
+    pub value: f32,
+}
+
+impl Ack {
+    pub fn to_frame(&self) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(ACK_SIZE);
+        frame.put_u8(PacketKind::Ack as u8);
+        frame.put_u8(self.id);
+        frame.put_u8(self.status as u8);
+        frame.put_f32_le(self.value);
+        frame
+    }
+
+    pub fn from_frame(frame: &[u8]) -> Result<Ack, CommandError> {
+        if PacketKind::of(frame) != Some(PacketKind::Ack) || frame.len() != ACK_SIZE {
+            return Err(CommandError::Malformed);
+        }
+
+        let status = match frame[2] {
+            0 => AckStatus::Accepted,
+            1 => AckStatus::Rejected,
+            _ => return Err(CommandError::Malformed),
+        };
+
+        Ok(Ack {
+            id: frame[1],
+            status,
+            value: (&frame[3..]).get_f32_le(),
+        })
+    }
+}
+
+/// What became of a command that needed an [`Ack`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandOutcome {
+    Acked(Command, Ack),
+    /// Sent [`MAX_ATTEMPTS`] times without an answer
+    TimedOut(Command),
+}
+
+struct Pending {
+    id: u8,
+    command: Command,
+    sent: Instant,
+    attempts: u8,
+}
+
+/// Commands sent and waiting for an [`Ack`], which are sent again every
+/// [`ACK_TIMEOUT`] until one arrives.
+///
+/// A command replaces any pending command of the same kind and setting, so
+/// only the latest value is retried.
+pub struct PendingCommands {
+    next_id: u8,
+    pending: Vec<Pending>,
+}
+
+impl Default for PendingCommands {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PendingCommands {
+    pub fn new() -> Self {
+        Self {
+            next_id: 1,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Frame to send for `command`, which is kept until acknowledged if it
+    /// needs to be.
+    pub fn send(&mut self, command: Command, now: Instant) -> Vec<u8> {
+        if !command.needs_ack() {
+            return command.to_frame(0);
+        }
+
+        // 0 is left for text commands
+        let id = self.next_id;
+        self.next_id = self.next_id.checked_add(1).unwrap_or(1);
+
+        let frame = command.to_frame(id);
+        self.pending
+            .retain(|pending| !command.replaces(&pending.command));
+        self.pending.push(Pending {
+            id,
+            command,
+            sent: now,
+            attempts: 1,
+        });
+
+        frame
+    }
+
+    /// Match `ack` to the command it answers, if that is still pending.
+    pub fn acknowledge(&mut self, ack: Ack) -> Option<CommandOutcome> {
+        let i = self
+            .pending
+            .iter()
+            .position(|pending| pending.id == ack.id)?;
+        let pending = self.pending.remove(i);
+
+        Some(CommandOutcome::Acked(pending.command, ack))
+    }
+
+    /// Frames to send again at `now`, and commands given up on.
+    pub fn poll(&mut self, now: Instant) -> (Vec<Vec<u8>>, Vec<CommandOutcome>) {
+        let mut resend = Vec::new();
+        let mut timed_out = Vec::new();
+
+        self.pending.retain_mut(|pending| {
+            if now.duration_since(pending.sent) < ACK_TIMEOUT {
+                true
+            } else if pending.attempts >= MAX_ATTEMPTS {
+                timed_out.push(CommandOutcome::TimedOut(pending.command.clone()));
+                false
+            } else {
+                pending.sent = now;
+                pending.attempts += 1;
+                resend.push(pending.command.to_frame(pending.id));
+                true
+            }
+        });
+
+        (resend, timed_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        datalink::{packet::LEGACY_FRAME_SIZE, ByteSerialize},
+        telemetry::Telemetry,
+    };
+
+    use super::*;
+
+    #[test]
+    fn round_trips_frames() {
+        let command = Command::SetSeaLevelPressure(101_625.0);
+        let frame = command.to_frame(7);
+
+        assert_eq!(frame[0], PacketKind::Command as u8);
+        assert_eq!(Command::from_frame(&frame), Ok((7, command)));
+
+        // text stays readable by older firmware
+        let frame = Command::Text("ton".to_string()).to_frame(0);
+        assert_eq!(frame, b"ton");
+        assert_eq!(
+            Command::from_frame(b"reset\n"),
+            Ok((0, Command::Text("reset".to_string())))
+        );
+
+        assert_eq!(
+            Command::from_frame(&[PacketKind::Command as u8, 1, 99]),
+            Err(CommandError::UnknownCommand(99))
+        );
+        assert_eq!(
+            Command::from_frame(&frame_with_len(2)),
+            Err(CommandError::Malformed)
+        );
+
+        let ack = Ack {
+            id: 7,
+            status: AckStatus::Rejected,
+            value: 102_030.0,
+        };
+        assert_eq!(Ack::from_frame(&ack.to_frame()), Ok(ack));
+        assert_eq!(Ack::from_frame(b"ton"), Err(CommandError::Malformed));
+    }
+
+    #[test]
+    fn round_trips_config_frames() {
+        for command in [
+            Command::GetConfig(FlightSetting::BuzzerPeriod),
+            Command::SetConfig(FlightSetting::MeasurementNoise, 0.2),
+            Command::FactoryReset,
+        ] {
+            assert_eq!(Command::from_frame(&command.to_frame(3)), Ok((3, command)));
+        }
+
+        assert_eq!(
+            Command::from_frame(&[PacketKind::Command as u8, 1, GET_CONFIG, 0]),
+            Err(CommandError::UnknownSetting(0))
+        );
+        assert_eq!(
+            Command::from_frame(&[PacketKind::Command as u8, 1, FACTORY_RESET, 0]),
+            Err(CommandError::Malformed)
+        );
+    }
+
+    #[test]
+    fn legacy_telemetry_is_not_an_ack() {
+        for tag in [PacketKind::Command, PacketKind::Ack] {
+            let telemetry = Telemetry {
+                time: 0x3400 | tag as u32,
+                ..Telemetry::default()
+            };
+            let mut frame = [0u8; LEGACY_FRAME_SIZE];
+            telemetry.as_bytes(&mut frame).unwrap();
+            assert_eq!(frame[0], tag as u8);
+
+            assert_eq!(PacketKind::of(&frame), None);
+            assert_eq!(Ack::from_frame(&frame), Err(CommandError::Malformed));
+            assert_eq!(Telemetry::from_frame(&frame).unwrap().time, telemetry.time);
+        }
+
+        let ack = Ack {
+            id: 1,
+            status: AckStatus::Accepted,
+            value: 0.0,
+        };
+        assert_ne!(ack.to_frame().len(), LEGACY_FRAME_SIZE);
+        for command in [
+            Command::SetSeaLevelPressure(101_325.0),
+            Command::GetConfig(FlightSetting::BuzzerPeriod),
+            Command::SetConfig(FlightSetting::BuzzerPeriod, 200.0),
+            Command::FactoryReset,
+        ] {
+            assert_ne!(command.to_frame(1).len(), LEGACY_FRAME_SIZE);
+        }
+    }
+
+    fn frame_with_len(payload: usize) -> Vec<u8> {
+        let mut frame = vec![PacketKind::Command as u8, 1, SET_SEA_LEVEL_PRESSURE];
+        frame.resize(3 + payload, 0);
+        frame
+    }
+
+    #[test]
+    fn checks_sea_level_pressure() {
+        assert!(valid_sea_level_pressure(101_325.0));
+        assert!(!valid_sea_level_pressure(30.01));
+        assert!(!valid_sea_level_pressure(f32::NAN));
+    }
+
+    #[test]
+    fn resends_until_acknowledged() {
+        let start = Instant::now();
+        let mut pending = PendingCommands::new();
+
+        pending.send(Command::Text("ton".to_string()), start);
+        assert!(pending.is_empty());
+
+        let frame = pending.send(Command::SetSeaLevelPressure(101_000.0), start);
+        let (id, _) = Command::from_frame(&frame).unwrap();
+
+        // nothing due yet, then sent again
+        assert_eq!(pending.poll(start + ACK_TIMEOUT / 2).0.len(), 0);
+        assert_eq!(pending.poll(start + ACK_TIMEOUT).0, vec![frame]);
+
+        let ack = Ack {
+            id,
+            status: AckStatus::Accepted,
+            value: 101_000.0,
+        };
+        assert_eq!(
+            pending.acknowledge(ack),
+            Some(CommandOutcome::Acked(
+                Command::SetSeaLevelPressure(101_000.0),
+                ack
+            ))
+        );
+        assert!(pending.is_empty());
+
+        // a late duplicate answers nothing
+        assert_eq!(pending.acknowledge(ack), None);
+    }
+
+    #[test]
+    fn gives_up_and_keeps_latest() {
+        let start = Instant::now();
+        let mut pending = PendingCommands::new();
+
+        pending.send(Command::SetSeaLevelPressure(100_000.0), start);
+        pending.send(Command::SetSeaLevelPressure(101_000.0), start);
+        assert_eq!(pending.len(), 1);
+
+        // other settings are kept
+        pending.send(
+            Command::SetConfig(FlightSetting::BuzzerPeriod, 200.0),
+            start,
+        );
+        pending.send(Command::SetConfig(FlightSetting::BatchSamples, 4.0), start);
+        pending.send(Command::SetConfig(FlightSetting::BatchSamples, 3.0), start);
+        assert_eq!(pending.len(), 3);
+
+        let mut timed_out = Vec::new();
+        for attempt in 1..=MAX_ATTEMPTS as u32 {
+            timed_out = pending.poll(start + ACK_TIMEOUT * attempt).1;
+        }
+
+        assert_eq!(
+            timed_out,
+            vec![
+                CommandOutcome::TimedOut(Command::SetSeaLevelPressure(101_000.0)),
+                CommandOutcome::TimedOut(Command::SetConfig(FlightSetting::BuzzerPeriod, 200.0)),
+                CommandOutcome::TimedOut(Command::SetConfig(FlightSetting::BatchSamples, 3.0)),
+            ]
+        );
+        assert!(pending.is_empty());
+    }
+}
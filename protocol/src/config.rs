@@ -0,0 +1,98 @@
+use std::{mem::size_of, ops::RangeInclusive};
+
+use crate::{
+    command::{SEA_LEVEL_PRESSURE_MAX, SEA_LEVEL_PRESSURE_MIN},
+    telemetry::{batch::max_batch_samples, ProtocolVersion, Telemetry},
+};
+
+/// Heap the flight computer sets aside for recorded samples, alongside WiFi
+/// on a board without PSRAM.
+const RECORDING_BUDGET: usize = 80_000;
+
+/// Most samples the flight computer can keep in memory for
+/// retransmission.
+pub const MAX_RECORDING_CAPACITY: u32 = (RECORDING_BUDGET / size_of::<Telemetry>()) as u32;
+
+/// A setting of the flight computer's config, as named on the basestation
+/// console and numbered in config commands.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FlightSetting {
+    MeasurementNoise = 1,
+    ProcessNoise = 2,
+    SeaLevelPressure = 3,
+    BuzzerPeriod = 4,
+    RecordingCapacity = 5,
+    BatchSamples = 6,
+}
+
+pub const FLIGHT_SETTINGS: [FlightSetting; 6] = [
+    FlightSetting::MeasurementNoise,
+    FlightSetting::ProcessNoise,
+    FlightSetting::SeaLevelPressure,
+    FlightSetting::BuzzerPeriod,
+    FlightSetting::RecordingCapacity,
+    FlightSetting::BatchSamples,
+];
+
+impl FlightSetting {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        FLIGHT_SETTINGS
+            .into_iter()
+            .find(|setting| *setting as u8 == value)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        FLIGHT_SETTINGS
+            .into_iter()
+            .find(|setting| setting.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FlightSetting::MeasurementNoise => "measurement_noise",
+            FlightSetting::ProcessNoise => "process_noise",
+            FlightSetting::SeaLevelPressure => "psl",
+            FlightSetting::BuzzerPeriod => "buzzer_period",
+            FlightSetting::RecordingCapacity => "recording",
+            FlightSetting::BatchSamples => "batch",
+        }
+    }
+
+    /// Values accepted, which are whole numbers unless [`Self::is_whole`]
+    /// says otherwise.
+    pub fn range(&self) -> RangeInclusive<f64> {
+        match self {
+            FlightSetting::MeasurementNoise => 0.001..=100.0,
+            FlightSetting::ProcessNoise => 0.0001..=10.0,
+            FlightSetting::SeaLevelPressure => {
+                SEA_LEVEL_PRESSURE_MIN as f64..=SEA_LEVEL_PRESSURE_MAX as f64
+            }
+            // the beep itself lasts 50 ms
+            FlightSetting::BuzzerPeriod => 60.0..=10_000.0,
+            // kept in RAM, within RECORDING_BUDGET
+            FlightSetting::RecordingCapacity => 1.0..=MAX_RECORDING_CAPACITY as f64,
+            FlightSetting::BatchSamples => {
+                1.0..=max_batch_samples(ProtocolVersion::Extended) as f64
+            }
+        }
+    }
+
+    pub fn is_whole(&self) -> bool {
+        matches!(
+            self,
+            FlightSetting::BuzzerPeriod
+                | FlightSetting::RecordingCapacity
+                | FlightSetting::BatchSamples
+        )
+    }
+
+    /// Whether a change only takes effect after the flight computer
+    /// restarts, rather than straight away.
+    pub fn needs_restart(&self) -> bool {
+        matches!(
+            self,
+            FlightSetting::RecordingCapacity | FlightSetting::BatchSamples
+        )
+    }
+}
@@ -0,0 +1,12 @@
+pub mod packet;
+pub mod peers;
+pub mod queue;
+pub mod transport;
+
+pub type MacAddr = [u8; 6];
+
+#[allow(clippy::result_unit_err)]
+pub trait ByteSerialize<T> {
+    fn as_bytes(&self, buffer: &mut [u8]) -> Result<(), ()>;
+    fn from_bytes(buffer: &[u8]) -> Result<T, ()>;
+}
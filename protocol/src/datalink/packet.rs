@@ -0,0 +1,60 @@
+/// Largest payload ESP-NOW will carry in one frame (ESP_NOW_MAX_DATA_LEN).
+pub const MAX_FRAME_SIZE: usize = 250;
+
+/// Size of the untagged float samples sent by firmware that predates tags,
+/// which padded each sample to 33 bytes.
+pub const LEGACY_FRAME_SIZE: usize = 33;
+
+/// Tag carried in the first byte of every tagged frame sent over the datalink.
+///
+/// Tags are outside the printable ASCII range so they can never be confused
+/// with the text commands sent by the basestation.  Untagged telemetry starts
+/// with the low byte of its time, which may equal any tag, so frames of
+/// [`LEGACY_FRAME_SIZE`] are never read as tagged and no tagged frame is
+/// ever that long.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PacketKind {
+    /// A typed [`Command`](crate::command::Command) for the flight computer
+    Command = 0xC0,
+    /// The flight computer's answer to a command
+    Ack = 0xC1,
+    /// The basestation's view of its link to the rocket, sent to dashboard clients
+    LinkStatus = 0xD0,
+    /// One telemetry sample, followed by its [`ProtocolVersion`](crate::telemetry::ProtocolVersion)
+    Telemetry = 0xE0,
+    /// Several telemetry samples packed into one frame
+    TelemetryBatch = 0xE1,
+    /// One piece of a message split by the transport
+    Fragment = 0xF0,
+    /// Receiver report of which fragments of a message are still missing
+    FragmentAck = 0xF1,
+}
+
+impl PacketKind {
+    /// Kind of a received frame, or `None` for untagged frames.
+    pub fn of(frame: &[u8]) -> Option<PacketKind> {
+        if frame.len() == LEGACY_FRAME_SIZE {
+            return None;
+        }
+
+        frame.first().and_then(|b| PacketKind::try_from(*b).ok())
+    }
+}
+
+impl TryFrom<u8> for PacketKind {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0xC0 => Ok(PacketKind::Command),
+            0xC1 => Ok(PacketKind::Ack),
+            0xD0 => Ok(PacketKind::LinkStatus),
+            0xE0 => Ok(PacketKind::Telemetry),
+            0xE1 => Ok(PacketKind::TelemetryBatch),
+            0xF0 => Ok(PacketKind::Fragment),
+            0xF1 => Ok(PacketKind::FragmentAck),
+            _ => Err(()),
+        }
+    }
+}
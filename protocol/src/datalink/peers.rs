@@ -0,0 +1,112 @@
+use super::MacAddr;
+
+/// ESP-NOW supports at most 20 peers (ESP_NOW_MAX_TOTAL_PEER_NUM).
+pub const MAX_PEERS: usize = 20;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PeerUpdate {
+    /// Peer was already registered
+    Known,
+    /// Peer must be registered with the driver
+    Added,
+    /// Peer must be registered after removing the least recently used peer
+    Evicted(MacAddr),
+}
+
+/// Mirror of the driver's peer list with least recently used eviction, so
+/// talking to a new device never fails just because the table is full.
+pub struct PeerTable {
+    peers: Vec<(MacAddr, u32)>,
+    capacity: usize,
+    clock: u32,
+}
+
+impl PeerTable {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            peers: Vec::with_capacity(capacity),
+            capacity,
+            clock: 0,
+        }
+    }
+
+    /// Record use of `addr` and report what must change in the driver.
+    pub fn touch(&mut self, addr: MacAddr) -> PeerUpdate {
+        self.clock = self.clock.wrapping_add(1);
+
+        if let Some(peer) = self.peers.iter_mut().find(|(a, _)| *a == addr) {
+            peer.1 = self.clock;
+            return PeerUpdate::Known;
+        }
+
+        let result = if self.peers.len() < self.capacity {
+            PeerUpdate::Added
+        } else {
+            let (i, _) = self
+                .peers
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, (_, last_used))| self.clock.wrapping_sub(*last_used))
+                .expect("capacity is non-zero");
+            PeerUpdate::Evicted(self.peers.swap_remove(i).0)
+        };
+
+        self.peers.push((addr, self.clock));
+
+        result
+    }
+
+    pub fn remove(&mut self, addr: MacAddr) {
+        self.peers.retain(|(a, _)| *a != addr);
+    }
+
+    pub fn contains(&self, addr: MacAddr) -> bool {
+        self.peers.iter().any(|(a, _)| *a == addr)
+    }
+
+    /// Every peer in the table, in no particular order.
+    pub fn addrs(&self) -> Vec<MacAddr> {
+        self.peers.iter().map(|(addr, _)| *addr).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_until_full_then_evicts_least_recently_used() {
+        let mut table = PeerTable::new(2);
+
+        assert_eq!(table.touch([1; 6]), PeerUpdate::Added);
+        assert_eq!(table.touch([2; 6]), PeerUpdate::Added);
+        assert_eq!(table.touch([1; 6]), PeerUpdate::Known);
+
+        // 2 is now the least recently used
+        assert_eq!(table.touch([3; 6]), PeerUpdate::Evicted([2; 6]));
+        assert!(table.contains([1; 6]));
+        assert!(table.contains([3; 6]));
+        assert!(!table.contains([2; 6]));
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.addrs(), [[1; 6], [3; 6]]);
+    }
+
+    #[test]
+    fn removed_peer_is_added_again() {
+        let mut table = PeerTable::new(2);
+
+        table.touch([1; 6]);
+        table.remove([1; 6]);
+
+        assert!(table.is_empty());
+        assert_eq!(table.touch([1; 6]), PeerUpdate::Added);
+    }
+}
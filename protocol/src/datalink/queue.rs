@@ -0,0 +1,239 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+use super::MacAddr;
+
+/// Transmit priority of a frame.  Lower variants are always sent first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Commands and acknowledgements
+    Control,
+    /// Live telemetry
+    Telemetry,
+    /// Bulk transfers such as log downloads
+    Bulk,
+}
+
+impl Priority {
+    pub const COUNT: usize = 3;
+    pub const ALL: [Priority; Priority::COUNT] =
+        [Priority::Control, Priority::Telemetry, Priority::Bulk];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QueueError {
+    /// The queue for this priority is at capacity; the frame was not queued.
+    Full(Priority),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub peer_addr: MacAddr,
+    pub data: Vec<u8>,
+    pub priority: Priority,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    /// Frames currently waiting, per priority
+    pub queued: [usize; Priority::COUNT],
+    /// Frames rejected because the queue was full, per priority
+    pub dropped: [u32; Priority::COUNT],
+    /// Largest number of frames ever waiting, per priority
+    pub high_water: [usize; Priority::COUNT],
+}
+
+/// Bounded, strict priority transmit queue.
+///
+/// Each priority has its own capacity so a long log download can never
+/// starve commands or push them out of the queue.
+pub struct TxQueue {
+    queues: [VecDeque<Frame>; Priority::COUNT],
+    capacity: [usize; Priority::COUNT],
+    stats: QueueStats,
+}
+
+impl TxQueue {
+    pub fn new(capacity: [usize; Priority::COUNT]) -> Self {
+        Self {
+            queues: Default::default(),
+            capacity,
+            stats: QueueStats::default(),
+        }
+    }
+
+    pub fn push(&mut self, frame: Frame) -> Result<(), QueueError> {
+        let i = frame.priority.index();
+
+        if self.queues[i].len() >= self.capacity[i] {
+            self.stats.dropped[i] = self.stats.dropped[i].saturating_add(1);
+            return Err(QueueError::Full(frame.priority));
+        }
+
+        self.queues[i].push_back(frame);
+        self.stats.queued[i] = self.queues[i].len();
+        self.stats.high_water[i] = self.stats.high_water[i].max(self.queues[i].len());
+
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<Frame> {
+        for priority in Priority::ALL {
+            let i = priority.index();
+            if let Some(frame) = self.queues[i].pop_front() {
+                self.stats.queued[i] = self.queues[i].len();
+                return Some(frame);
+            }
+        }
+
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of additional frames that can be queued at `priority`.
+    pub fn available(&self, priority: Priority) -> usize {
+        let i = priority.index();
+        self.capacity[i] - self.queues[i].len()
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.stats
+    }
+}
+
+/// Cloneable handle to a [`TxQueue`] shared between producers and the transmit thread.
+#[derive(Clone)]
+pub struct DataSender {
+    inner: Arc<(Mutex<TxQueue>, Condvar)>,
+}
+
+impl DataSender {
+    pub fn new(capacity: [usize; Priority::COUNT]) -> Self {
+        Self {
+            inner: Arc::new((Mutex::new(TxQueue::new(capacity)), Condvar::new())),
+        }
+    }
+
+    /// Queue `data` for `peer_addr`.  Never blocks; a full queue is reported
+    /// back to the caller so it can slow down or drop its own data.
+    pub fn send(
+        &self,
+        peer_addr: MacAddr,
+        data: Vec<u8>,
+        priority: Priority,
+    ) -> Result<(), QueueError> {
+        let (queue, available) = &*self.inner;
+        queue.lock().unwrap().push(Frame {
+            peer_addr,
+            data,
+            priority,
+        })?;
+        available.notify_one();
+        Ok(())
+    }
+
+    /// Take the highest priority frame, waiting up to `timeout` for one to arrive.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Frame> {
+        let (queue, available) = &*self.inner;
+        let guard = queue.lock().unwrap();
+        let (mut guard, _) = available
+            .wait_timeout_while(guard, timeout, |queue| queue.is_empty())
+            .unwrap();
+        guard.pop()
+    }
+
+    pub fn available(&self, priority: Priority) -> usize {
+        self.inner.0.lock().unwrap().available(priority)
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.inner.0.lock().unwrap().stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(priority: Priority, tag: u8) -> Frame {
+        Frame {
+            peer_addr: [tag; 6],
+            data: vec![tag],
+            priority,
+        }
+    }
+
+    #[test]
+    fn pops_highest_priority_first() {
+        let mut queue = TxQueue::new([4, 4, 4]);
+
+        queue.push(frame(Priority::Bulk, 1)).unwrap();
+        queue.push(frame(Priority::Telemetry, 2)).unwrap();
+        queue.push(frame(Priority::Control, 3)).unwrap();
+        queue.push(frame(Priority::Bulk, 4)).unwrap();
+
+        let order: Vec<u8> = std::iter::from_fn(|| queue.pop())
+            .map(|f| f.data[0])
+            .collect();
+
+        assert_eq!(order, vec![3, 2, 1, 4]);
+    }
+
+    #[test]
+    fn full_queue_rejects_and_counts_drops() {
+        let mut queue = TxQueue::new([1, 1, 2]);
+
+        queue.push(frame(Priority::Bulk, 1)).unwrap();
+        queue.push(frame(Priority::Bulk, 2)).unwrap();
+        assert_eq!(
+            queue.push(frame(Priority::Bulk, 3)),
+            Err(QueueError::Full(Priority::Bulk))
+        );
+
+        // bulk backlog does not affect control frames
+        queue.push(frame(Priority::Control, 4)).unwrap();
+
+        let stats = queue.stats();
+        assert_eq!(stats.dropped, [0, 0, 1]);
+        assert_eq!(stats.queued, [1, 0, 2]);
+        assert_eq!(stats.high_water, [1, 0, 2]);
+        assert_eq!(queue.available(Priority::Bulk), 0);
+        assert_eq!(queue.available(Priority::Telemetry), 1);
+    }
+
+    #[test]
+    fn sender_wakes_receiver() {
+        let sender = DataSender::new([2, 2, 2]);
+        let receiver = sender.clone();
+
+        let handle = std::thread::spawn(move || receiver.recv_timeout(Duration::from_secs(5)));
+
+        sender
+            .send([7; 6], vec![1, 2, 3], Priority::Control)
+            .unwrap();
+
+        let frame = handle.join().unwrap().expect("frame should arrive");
+        assert_eq!(frame.peer_addr, [7; 6]);
+        assert_eq!(frame.data, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn recv_times_out_when_empty() {
+        let sender = DataSender::new([1, 1, 1]);
+        assert_eq!(sender.recv_timeout(Duration::from_millis(1)), None);
+    }
+}
@@ -0,0 +1,727 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use bytes::{Buf, BufMut, BytesMut};
+
+use super::{
+    packet::{PacketKind, LEGACY_FRAME_SIZE, MAX_FRAME_SIZE},
+    MacAddr,
+};
+
+// kind, message id, fragment index, fragment count
+const FRAGMENT_HEADER_SIZE: usize = 7;
+// kind, message id, number of missing fragments listed
+const ACK_HEADER_SIZE: usize = 5;
+// completed messages remembered so a lost final ack can be repeated
+const COMPLETED_HISTORY: usize = 16;
+
+#[derive(Copy, Clone, Debug)]
+pub struct TransportConfig {
+    pub max_frame_size: usize,
+    /// Silence after which the sender probes and the receiver reports gaps
+    pub ack_timeout: Duration,
+    /// Silence after which a partially received message is discarded
+    pub reassembly_timeout: Duration,
+    /// Probes without progress before a message is abandoned
+    pub max_retries: u8,
+    /// Messages being reassembled at once; the oldest is dropped beyond this
+    pub max_incoming: usize,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            max_frame_size: MAX_FRAME_SIZE,
+            ack_timeout: Duration::from_millis(200),
+            reassembly_timeout: Duration::from_secs(5),
+            max_retries: 10,
+            max_incoming: 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransportEvent {
+    /// A complete message arrived from `peer`
+    Delivered { peer: MacAddr, message: Vec<u8> },
+    /// `peer` confirmed it received every fragment of a sent message
+    Acknowledged { peer: MacAddr, message_id: u16 },
+    /// A sent message was abandoned after `max_retries` probes
+    Failed { peer: MacAddr, message_id: u16 },
+    /// A partially received message timed out
+    Expired { peer: MacAddr, message_id: u16 },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TransportError {
+    /// The message needs more than `u16::MAX` fragments
+    TooLarge,
+    /// The frame is not a well formed transport frame
+    Malformed,
+}
+
+struct Outgoing {
+    peer: MacAddr,
+    message_id: u16,
+    frames: Vec<Vec<u8>>,
+    last_sent: Instant,
+    retries: u8,
+}
+
+struct Incoming {
+    peer: MacAddr,
+    message_id: u16,
+    fragments: Vec<Option<Vec<u8>>>,
+    received: usize,
+    last_activity: Instant,
+    last_ack: Instant,
+}
+
+impl Incoming {
+    fn missing(&self) -> impl Iterator<Item = u16> + '_ {
+        self.fragments
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.is_none())
+            .map(|(i, _)| i as u16)
+    }
+}
+
+/// Splits messages larger than one frame into fragments and puts them back
+/// together on the other side, re-sending only the fragments that were lost.
+///
+/// The transport does no I/O.  Frames to transmit are collected with
+/// [`Transport::pop_frame`], received frames are handed to
+/// [`Transport::receive`] and [`Transport::poll`] drives the timers.
+pub struct Transport {
+    config: TransportConfig,
+    next_message_id: u16,
+    outgoing: Vec<Outgoing>,
+    incoming: Vec<Incoming>,
+    completed: VecDeque<(MacAddr, u16)>,
+    outbox: VecDeque<(MacAddr, Vec<u8>)>,
+    events: VecDeque<TransportEvent>,
+}
+
+fn encode_fragment(message_id: u16, index: u16, count: u16, payload: &[u8]) -> Vec<u8> {
+    let mut buf = BytesMut::with_capacity(FRAGMENT_HEADER_SIZE + payload.len());
+
+    buf.put_u8(PacketKind::Fragment as u8);
+    buf.put_u16_le(message_id);
+    buf.put_u16_le(index);
+    buf.put_u16_le(count);
+    buf.put_slice(payload);
+
+    buf.to_vec()
+}
+
+fn encode_ack(message_id: u16, mut missing: &[u16]) -> Vec<u8> {
+    if ACK_HEADER_SIZE + missing.len() * 2 == LEGACY_FRAME_SIZE {
+        // the last gap is reported next time
+        missing = &missing[..missing.len() - 1];
+    }
+
+    let mut buf = BytesMut::with_capacity(ACK_HEADER_SIZE + missing.len() * 2);
+
+    buf.put_u8(PacketKind::FragmentAck as u8);
+    buf.put_u16_le(message_id);
+    buf.put_u16_le(missing.len() as u16);
+    for index in missing {
+        buf.put_u16_le(*index);
+    }
+
+    buf.to_vec()
+}
+
+impl Transport {
+    pub fn new(config: TransportConfig) -> Self {
+        Self {
+            config,
+            next_message_id: 0,
+            outgoing: Vec::new(),
+            incoming: Vec::new(),
+            completed: VecDeque::with_capacity(COMPLETED_HISTORY),
+            outbox: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    fn max_payload(&self) -> usize {
+        self.config.max_frame_size - FRAGMENT_HEADER_SIZE
+    }
+
+    fn max_missing(&self) -> usize {
+        (self.config.max_frame_size - ACK_HEADER_SIZE) / 2
+    }
+
+    /// Splits `message` into payloads for fragments no larger than a frame,
+    /// and never [`LEGACY_FRAME_SIZE`], which would be read as untagged
+    /// telemetry.
+    fn chunks<'a>(&self, mut message: &'a [u8]) -> Vec<&'a [u8]> {
+        let mut chunks = Vec::new();
+
+        loop {
+            let mut size = message.len().min(self.max_payload());
+            if FRAGMENT_HEADER_SIZE + size == LEGACY_FRAME_SIZE {
+                size -= 1;
+            }
+
+            let (chunk, rest) = message.split_at(size);
+            chunks.push(chunk);
+            message = rest;
+
+            if message.is_empty() {
+                return chunks;
+            }
+        }
+    }
+
+    /// Fragment `message` for `peer` and queue every fragment for transmission.
+    pub fn send(
+        &mut self,
+        peer: MacAddr,
+        message: &[u8],
+        now: Instant,
+    ) -> Result<u16, TransportError> {
+        let chunks = self.chunks(message);
+        let count = u16::try_from(chunks.len()).map_err(|_| TransportError::TooLarge)?;
+
+        let message_id = self.next_message_id;
+        self.next_message_id = self.next_message_id.wrapping_add(1);
+
+        let frames: Vec<Vec<u8>> = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| encode_fragment(message_id, i as u16, count, chunk))
+            .collect();
+
+        for frame in frames.iter() {
+            self.outbox.push_back((peer, frame.clone()));
+        }
+
+        self.outgoing.push(Outgoing {
+            peer,
+            message_id,
+            frames,
+            last_sent: now,
+            retries: 0,
+        });
+
+        Ok(message_id)
+    }
+
+    /// Handle a frame received from `peer`.
+    pub fn receive(
+        &mut self,
+        peer: MacAddr,
+        frame: &[u8],
+        now: Instant,
+    ) -> Result<(), TransportError> {
+        match PacketKind::of(frame) {
+            Some(PacketKind::Fragment) => self.receive_fragment(peer, frame, now),
+            Some(PacketKind::FragmentAck) => self.receive_ack(peer, frame, now),
+            _ => Err(TransportError::Malformed),
+        }
+    }
+
+    fn receive_fragment(
+        &mut self,
+        peer: MacAddr,
+        frame: &[u8],
+        now: Instant,
+    ) -> Result<(), TransportError> {
+        if frame.len() < FRAGMENT_HEADER_SIZE {
+            return Err(TransportError::Malformed);
+        }
+
+        let max_missing = self.max_missing();
+
+        let mut buf = &frame[1..];
+        let message_id = buf.get_u16_le();
+        let index = buf.get_u16_le();
+        let count = buf.get_u16_le();
+        let payload = buf;
+
+        if count == 0 || index >= count {
+            return Err(TransportError::Malformed);
+        }
+
+        if self.completed.contains(&(peer, message_id)) {
+            // our final ack was lost, repeat it
+            self.outbox.push_back((peer, encode_ack(message_id, &[])));
+            return Ok(());
+        }
+
+        let position = self
+            .incoming
+            .iter()
+            .position(|m| m.peer == peer && m.message_id == message_id);
+
+        let position = match position {
+            Some(position) => position,
+            None => {
+                if self.incoming.len() >= self.config.max_incoming {
+                    let oldest = self.incoming.remove(0);
+                    self.events.push_back(TransportEvent::Expired {
+                        peer: oldest.peer,
+                        message_id: oldest.message_id,
+                    });
+                }
+                self.incoming.push(Incoming {
+                    peer,
+                    message_id,
+                    fragments: vec![None; count as usize],
+                    received: 0,
+                    last_activity: now,
+                    last_ack: now,
+                });
+                self.incoming.len() - 1
+            }
+        };
+
+        let incoming = &mut self.incoming[position];
+
+        if incoming.fragments.len() != count as usize {
+            return Err(TransportError::Malformed);
+        }
+
+        incoming.last_activity = now;
+
+        let slot = &mut incoming.fragments[index as usize];
+        if slot.is_none() {
+            *slot = Some(payload.to_vec());
+            incoming.received += 1;
+        }
+
+        if incoming.received == incoming.fragments.len() {
+            let incoming = self.incoming.remove(position);
+            let message = incoming.fragments.into_iter().flatten().flatten().collect();
+
+            if self.completed.len() >= COMPLETED_HISTORY {
+                self.completed.pop_front();
+            }
+            self.completed.push_back((peer, message_id));
+
+            self.outbox.push_back((peer, encode_ack(message_id, &[])));
+            self.events
+                .push_back(TransportEvent::Delivered { peer, message });
+        } else if index == count - 1 {
+            // the tail arrived (first pass or a sender probe), report the gaps
+            let missing: Vec<u16> = incoming.missing().take(max_missing).collect();
+            incoming.last_ack = now;
+            self.outbox
+                .push_back((peer, encode_ack(message_id, &missing)));
+        }
+
+        Ok(())
+    }
+
+    fn receive_ack(
+        &mut self,
+        peer: MacAddr,
+        frame: &[u8],
+        now: Instant,
+    ) -> Result<(), TransportError> {
+        if frame.len() < ACK_HEADER_SIZE {
+            return Err(TransportError::Malformed);
+        }
+
+        let mut buf = &frame[1..];
+        let message_id = buf.get_u16_le();
+        let missing = buf.get_u16_le() as usize;
+
+        if buf.remaining() < missing * 2 {
+            return Err(TransportError::Malformed);
+        }
+
+        let Some(position) = self
+            .outgoing
+            .iter()
+            .position(|m| m.peer == peer && m.message_id == message_id)
+        else {
+            // duplicate ack for a message we already finished with
+            return Ok(());
+        };
+
+        if missing == 0 {
+            self.outgoing.remove(position);
+            self.events
+                .push_back(TransportEvent::Acknowledged { peer, message_id });
+            return Ok(());
+        }
+
+        let outgoing = &mut self.outgoing[position];
+        outgoing.last_sent = now;
+        outgoing.retries = 0;
+
+        for _ in 0..missing {
+            let index = buf.get_u16_le() as usize;
+            if let Some(frame) = outgoing.frames.get(index) {
+                self.outbox.push_back((peer, frame.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run timers: probe unacknowledged messages, report gaps in stalled
+    /// messages and discard ones that have gone quiet.
+    pub fn poll(&mut self, now: Instant) {
+        let config = self.config;
+
+        let mut i = 0;
+        while i < self.outgoing.len() {
+            let outgoing = &mut self.outgoing[i];
+
+            if now.duration_since(outgoing.last_sent) < config.ack_timeout {
+                i += 1;
+                continue;
+            }
+
+            if outgoing.retries >= config.max_retries {
+                let outgoing = self.outgoing.remove(i);
+                self.events.push_back(TransportEvent::Failed {
+                    peer: outgoing.peer,
+                    message_id: outgoing.message_id,
+                });
+                continue;
+            }
+
+            // resending the last fragment makes the receiver report what it lacks
+            outgoing.retries += 1;
+            outgoing.last_sent = now;
+            let last = outgoing
+                .frames
+                .last()
+                .expect("messages have a fragment")
+                .clone();
+            self.outbox.push_back((outgoing.peer, last));
+            i += 1;
+        }
+
+        let max_missing = self.max_missing();
+
+        let mut i = 0;
+        while i < self.incoming.len() {
+            let incoming = &mut self.incoming[i];
+
+            if now.duration_since(incoming.last_activity) >= config.reassembly_timeout {
+                let incoming = self.incoming.remove(i);
+                self.events.push_back(TransportEvent::Expired {
+                    peer: incoming.peer,
+                    message_id: incoming.message_id,
+                });
+                continue;
+            }
+
+            if now.duration_since(incoming.last_activity) >= config.ack_timeout
+                && now.duration_since(incoming.last_ack) >= config.ack_timeout
+            {
+                let missing: Vec<u16> = incoming.missing().take(max_missing).collect();
+                incoming.last_ack = now;
+                self.outbox
+                    .push_back((incoming.peer, encode_ack(incoming.message_id, &missing)));
+            }
+
+            i += 1;
+        }
+    }
+
+    /// Next frame to hand to the link.
+    pub fn pop_frame(&mut self) -> Option<(MacAddr, Vec<u8>)> {
+        self.outbox.pop_front()
+    }
+
+    pub fn pop_event(&mut self) -> Option<TransportEvent> {
+        self.events.pop_front()
+    }
+
+    /// Messages sent but not yet acknowledged.
+    pub fn in_flight(&self) -> usize {
+        self.outgoing.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: MacAddr = [0xA; 6];
+    const B: MacAddr = [0xB; 6];
+
+    /// Deterministic link that drops roughly `loss` percent of frames.
+    struct LossyLink {
+        state: u32,
+        loss: u32,
+    }
+
+    impl LossyLink {
+        fn new(seed: u32, loss: u32) -> Self {
+            Self { state: seed, loss }
+        }
+
+        fn delivers(&mut self) -> bool {
+            // xorshift32
+            self.state ^= self.state << 13;
+            self.state ^= self.state >> 17;
+            self.state ^= self.state << 5;
+            self.state % 100 >= self.loss
+        }
+    }
+
+    /// Move frames between the two transports until both are idle, advancing
+    /// time by the ack timeout whenever nothing is left in flight on the wire.
+    fn pump(a: &mut Transport, b: &mut Transport, link: &mut LossyLink, start: Instant) -> Instant {
+        let mut now = start;
+
+        for _ in 0..1000 {
+            let mut moved = false;
+
+            while let Some((to, frame)) = a.pop_frame() {
+                assert_eq!(to, B);
+                moved = true;
+                if link.delivers() {
+                    b.receive(A, &frame, now).unwrap();
+                }
+            }
+
+            while let Some((to, frame)) = b.pop_frame() {
+                assert_eq!(to, A);
+                moved = true;
+                if link.delivers() {
+                    a.receive(B, &frame, now).unwrap();
+                }
+            }
+
+            if !moved {
+                if a.in_flight() == 0 {
+                    return now;
+                }
+                now += TransportConfig::default().ack_timeout;
+                a.poll(now);
+                b.poll(now);
+            }
+        }
+
+        panic!("transfer did not finish");
+    }
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn small_message_is_a_single_frame() {
+        let mut a = Transport::new(TransportConfig::default());
+        let now = Instant::now();
+
+        a.send(B, b"hello", now).unwrap();
+
+        let (_, frame) = a.pop_frame().unwrap();
+        assert_eq!(PacketKind::of(&frame), Some(PacketKind::Fragment));
+        assert_eq!(frame.len(), FRAGMENT_HEADER_SIZE + 5);
+        assert_eq!(a.pop_frame(), None);
+    }
+
+    #[test]
+    fn fragments_respect_frame_size() {
+        let mut a = Transport::new(TransportConfig::default());
+        a.send(B, &message(1000), Instant::now()).unwrap();
+
+        let frames: Vec<_> = std::iter::from_fn(|| a.pop_frame()).collect();
+        assert_eq!(frames.len(), 5);
+        assert!(frames.iter().all(|(_, f)| f.len() <= MAX_FRAME_SIZE));
+    }
+
+    #[test]
+    fn delivers_over_perfect_link() {
+        let mut a = Transport::new(TransportConfig::default());
+        let mut b = Transport::new(TransportConfig::default());
+        let mut link = LossyLink::new(1, 0);
+        let now = Instant::now();
+        let sent = message(4000);
+
+        let id = a.send(B, &sent, now).unwrap();
+        pump(&mut a, &mut b, &mut link, now);
+
+        assert_eq!(
+            b.pop_event(),
+            Some(TransportEvent::Delivered {
+                peer: A,
+                message: sent
+            })
+        );
+        assert_eq!(
+            a.pop_event(),
+            Some(TransportEvent::Acknowledged {
+                peer: B,
+                message_id: id
+            })
+        );
+    }
+
+    #[test]
+    fn recovers_lost_fragments_over_lossy_link() {
+        for seed in 1..20 {
+            let mut a = Transport::new(TransportConfig::default());
+            let mut b = Transport::new(TransportConfig::default());
+            let mut link = LossyLink::new(seed, 30);
+            let now = Instant::now();
+            let sent = message(10_000);
+
+            a.send(B, &sent, now).unwrap();
+            pump(&mut a, &mut b, &mut link, now);
+
+            let delivered: Vec<_> = std::iter::from_fn(|| b.pop_event()).collect();
+            assert_eq!(
+                delivered,
+                vec![TransportEvent::Delivered {
+                    peer: A,
+                    message: sent
+                }],
+                "seed {}",
+                seed
+            );
+            assert!(matches!(
+                a.pop_event(),
+                Some(TransportEvent::Acknowledged { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn reassembles_out_of_order() {
+        let mut a = Transport::new(TransportConfig::default());
+        let mut b = Transport::new(TransportConfig::default());
+        let now = Instant::now();
+        let sent = message(600);
+
+        a.send(B, &sent, now).unwrap();
+        let mut frames: Vec<_> = std::iter::from_fn(|| a.pop_frame()).collect();
+        frames.reverse();
+
+        for (_, frame) in frames {
+            b.receive(A, &frame, now).unwrap();
+        }
+
+        assert_eq!(
+            b.pop_event(),
+            Some(TransportEvent::Delivered {
+                peer: A,
+                message: sent
+            })
+        );
+    }
+
+    #[test]
+    fn gives_up_when_peer_is_silent() {
+        let config = TransportConfig::default();
+        let mut a = Transport::new(config);
+        let mut now = Instant::now();
+
+        let id = a.send(B, &message(10), now).unwrap();
+
+        for _ in 0..=config.max_retries {
+            now += config.ack_timeout;
+            a.poll(now);
+        }
+
+        assert_eq!(
+            a.pop_event(),
+            Some(TransportEvent::Failed {
+                peer: B,
+                message_id: id
+            })
+        );
+        assert_eq!(a.in_flight(), 0);
+    }
+
+    #[test]
+    fn expires_incomplete_message() {
+        let config = TransportConfig::default();
+        let mut a = Transport::new(config);
+        let mut b = Transport::new(config);
+        let now = Instant::now();
+
+        let id = a.send(B, &message(1000), now).unwrap();
+        let (_, first) = a.pop_frame().unwrap();
+        b.receive(A, &first, now).unwrap();
+
+        b.poll(now + config.reassembly_timeout);
+
+        assert_eq!(
+            b.pop_event(),
+            Some(TransportEvent::Expired {
+                peer: A,
+                message_id: id
+            })
+        );
+    }
+
+    #[test]
+    fn duplicate_after_completion_is_acked_not_redelivered() {
+        let mut a = Transport::new(TransportConfig::default());
+        let mut b = Transport::new(TransportConfig::default());
+        let now = Instant::now();
+
+        a.send(B, b"once", now).unwrap();
+        let (_, frame) = a.pop_frame().unwrap();
+
+        b.receive(A, &frame, now).unwrap();
+        b.receive(A, &frame, now).unwrap();
+
+        assert!(matches!(
+            b.pop_event(),
+            Some(TransportEvent::Delivered { .. })
+        ));
+        assert_eq!(b.pop_event(), None);
+        assert_eq!(b.pop_frame(), Some((A, encode_ack(0, &[]))));
+        assert_eq!(b.pop_frame(), Some((A, encode_ack(0, &[]))));
+    }
+
+    #[test]
+    fn frames_are_never_legacy_sized() {
+        let mut a = Transport::new(TransportConfig::default());
+        let mut b = Transport::new(TransportConfig::default());
+        let now = Instant::now();
+
+        for len in 0..600 {
+            let sent = message(len);
+            a.send(B, &sent, now).unwrap();
+
+            for (_, frame) in std::iter::from_fn(|| a.pop_frame()) {
+                assert_ne!(frame.len(), LEGACY_FRAME_SIZE, "message of {}", len);
+                b.receive(A, &frame, now).unwrap();
+            }
+            assert_eq!(
+                b.pop_event(),
+                Some(TransportEvent::Delivered {
+                    peer: A,
+                    message: sent
+                })
+            );
+            while b.pop_frame().is_some() {}
+        }
+
+        let missing: Vec<u16> = (0..14).collect();
+        assert_ne!(encode_ack(0, &missing).len(), LEGACY_FRAME_SIZE);
+    }
+
+    #[test]
+    fn rejects_malformed_frames() {
+        let mut b = Transport::new(TransportConfig::default());
+        let now = Instant::now();
+
+        assert_eq!(b.receive(A, b"ton", now), Err(TransportError::Malformed));
+        assert_eq!(
+            b.receive(A, &[PacketKind::Fragment as u8, 0, 0], now),
+            Err(TransportError::Malformed)
+        );
+        assert_eq!(
+            b.receive(A, &encode_fragment(0, 2, 2, &[]), now),
+            Err(TransportError::Malformed)
+        );
+    }
+}
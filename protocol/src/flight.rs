@@ -0,0 +1,161 @@
+/// Stage of the flight, judged from altitude and vertical velocity.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FlightPhase {
+    #[default]
+    Pad = 0,
+    Ascent = 1,
+    Descent = 2,
+    Landed = 3,
+}
+
+impl FlightPhase {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FlightPhase::Pad => "PAD",
+            FlightPhase::Ascent => "ASC",
+            FlightPhase::Descent => "DSC",
+            FlightPhase::Landed => "LND",
+        }
+    }
+}
+
+impl TryFrom<u8> for FlightPhase {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(FlightPhase::Pad),
+            1 => Ok(FlightPhase::Ascent),
+            2 => Ok(FlightPhase::Descent),
+            3 => Ok(FlightPhase::Landed),
+            _ => Err(()),
+        }
+    }
+}
+
+// Thresholds are in feet and feet per second, the altimeter's units.
+const LAUNCH_HEIGHT: f32 = 30.0;
+const LAUNCH_VELOCITY: f32 = 15.0;
+const APOGEE_DROP: f32 = 10.0;
+const LANDED_VELOCITY: f32 = 3.0;
+const LANDED_SAMPLES: u32 = 10;
+
+/// Tracks the flight phase from successive altitude samples.
+///
+/// Phases only move forward; call [`PhaseDetector::reset`] before the next flight.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PhaseDetector {
+    phase: FlightPhase,
+    ground: Option<f32>,
+    maximum: f32,
+    still_samples: u32,
+}
+
+impl PhaseDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn phase(&self) -> FlightPhase {
+        self.phase
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn update(&mut self, altitude: f32, vertical_velocity: f32) -> FlightPhase {
+        let ground = *self.ground.get_or_insert(altitude);
+        self.maximum = self.maximum.max(altitude);
+
+        match self.phase {
+            FlightPhase::Pad => {
+                // follow slow drift of the pad altitude until launch
+                self.ground = Some(ground.min(altitude));
+                self.maximum = altitude;
+
+                if altitude - ground > LAUNCH_HEIGHT && vertical_velocity > LAUNCH_VELOCITY {
+                    self.phase = FlightPhase::Ascent;
+                }
+            }
+            FlightPhase::Ascent => {
+                if altitude < self.maximum - APOGEE_DROP {
+                    self.phase = FlightPhase::Descent;
+                }
+            }
+            FlightPhase::Descent => {
+                if vertical_velocity.abs() < LANDED_VELOCITY {
+                    self.still_samples += 1;
+                } else {
+                    self.still_samples = 0;
+                }
+
+                if self.still_samples >= LANDED_SAMPLES {
+                    self.phase = FlightPhase::Landed;
+                }
+            }
+            FlightPhase::Landed => (),
+        }
+
+        self.phase
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_a_simple_flight() {
+        let mut detector = PhaseDetector::new();
+        let mut phases = Vec::new();
+
+        // (altitude, vertical velocity) at 5 Hz
+        let profile = [(2.0, 0.5); 10]
+            .into_iter()
+            .chain((1..=20).map(|i| (i as f32 * 40.0, 200.0)))
+            .chain((1..=20).map(|i| (800.0 - i as f32 * 30.0, -150.0)))
+            .chain((1..=20).map(|_| (200.0, 0.0)));
+
+        for (altitude, velocity) in profile {
+            let phase = detector.update(altitude, velocity);
+            if phases.last() != Some(&phase) {
+                phases.push(phase);
+            }
+        }
+
+        assert_eq!(
+            phases,
+            vec![
+                FlightPhase::Pad,
+                FlightPhase::Ascent,
+                FlightPhase::Descent,
+                FlightPhase::Landed
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_noise_on_the_pad() {
+        let mut detector = PhaseDetector::new();
+
+        for i in 0..100 {
+            let noise = if i % 2 == 0 { 5.0 } else { -5.0 };
+            detector.update(1000.0 + noise, noise * 4.0);
+        }
+
+        assert_eq!(detector.phase(), FlightPhase::Pad);
+    }
+
+    #[test]
+    fn reset_returns_to_pad() {
+        let mut detector = PhaseDetector::new();
+        detector.update(0.0, 0.0);
+        detector.update(100.0, 100.0);
+        assert_eq!(detector.phase(), FlightPhase::Ascent);
+
+        detector.reset();
+        assert_eq!(detector.phase(), FlightPhase::Pad);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod command;
+pub mod config;
+pub mod datalink;
+pub mod flight;
+pub mod link;
+pub mod serial;
+pub mod telemetry;
@@ -0,0 +1,155 @@
+use std::time::{Duration, Instant};
+
+use bytes::{Buf, BufMut};
+
+use crate::{datalink::packet::PacketKind, telemetry::Telemetry};
+
+/// How often the basestation reports a [`LinkStatus`] to its clients.
+pub const LINK_STATUS_PERIOD: Duration = Duration::from_secs(1);
+/// Longest gap between samples before the link is reported lost.
+pub const LINK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Encoded size of a [`LinkStatus`] frame.
+const LINK_STATUS_SIZE: usize = 14;
+
+/// How the basestation is hearing from the rocket.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LinkStatus {
+    /// A sample arrived within [`LINK_TIMEOUT`]
+    pub receiving: bool,
+    /// Since the last sample, if there has been one.  Sent in ms, saturating
+    /// at about 49 days.
+    pub last_sample_age: Option<Duration>,
+    /// Samples received since the basestation started
+    pub received: u32,
+    /// Samples missing from the sequence numbers received
+    pub missed: u32,
+}
+
+impl LinkStatus {
+    pub fn to_frame(&self) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(LINK_STATUS_SIZE);
+        frame.put_u8(PacketKind::LinkStatus as u8);
+        frame.put_u8(self.receiving as u8);
+        // u32::MAX for never
+        frame.put_u32_le(self.last_sample_age.map_or(u32::MAX, |age| {
+            age.as_millis().min(u32::MAX as u128 - 1) as u32
+        }));
+        frame.put_u32_le(self.received);
+        frame.put_u32_le(self.missed);
+        frame
+    }
+
+    pub fn from_frame(frame: &[u8]) -> Option<LinkStatus> {
+        if PacketKind::of(frame) != Some(PacketKind::LinkStatus) || frame.len() != LINK_STATUS_SIZE
+        {
+            return None;
+        }
+
+        let mut payload = &frame[1..];
+        let receiving = payload.get_u8() != 0;
+        let age = payload.get_u32_le();
+
+        Some(LinkStatus {
+            receiving,
+            last_sample_age: (age != u32::MAX).then(|| Duration::from_millis(age as u64)),
+            received: payload.get_u32_le(),
+            missed: payload.get_u32_le(),
+        })
+    }
+}
+
+/// Keeps track of the samples received, for [`LinkStatus`].
+#[derive(Clone, Debug, Default)]
+pub struct LinkMonitor {
+    last_sample: Option<Instant>,
+    last_sequence: Option<u32>,
+    received: u32,
+    missed: u32,
+}
+
+impl LinkMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, telemetry: &Telemetry, now: Instant) {
+        // a sequence going backwards is a rocket restart, not a loss
+        if let Some(last) = self.last_sequence {
+            if telemetry.sequence > last {
+                self.missed = self.missed.saturating_add(telemetry.sequence - last - 1);
+            }
+        }
+
+        self.last_sample = Some(now);
+        self.last_sequence = Some(telemetry.sequence);
+        self.received = self.received.saturating_add(1);
+    }
+
+    pub fn status(&self, now: Instant) -> LinkStatus {
+        let last_sample_age = self.last_sample.map(|last| now.duration_since(last));
+
+        LinkStatus {
+            receiving: last_sample_age.is_some_and(|age| age < LINK_TIMEOUT),
+            last_sample_age,
+            received: self.received,
+            missed: self.missed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(sequence: u32) -> Telemetry {
+        Telemetry {
+            sequence,
+            ..Telemetry::default()
+        }
+    }
+
+    #[test]
+    fn counts_missed_samples() {
+        let start = Instant::now();
+        let mut monitor = LinkMonitor::new();
+        assert_eq!(monitor.status(start), LinkStatus::default());
+
+        for sequence in [10, 11, 14, 15] {
+            monitor.record(&sample(sequence), start);
+        }
+        // restarted
+        monitor.record(&sample(0), start);
+
+        let status = monitor.status(start + Duration::from_millis(500));
+        assert_eq!(
+            status,
+            LinkStatus {
+                receiving: true,
+                last_sample_age: Some(Duration::from_millis(500)),
+                received: 5,
+                missed: 2,
+            }
+        );
+        assert!(!monitor.status(start + LINK_TIMEOUT).receiving);
+    }
+
+    #[test]
+    fn round_trips_link_status() {
+        let status = LinkStatus {
+            receiving: true,
+            last_sample_age: Some(Duration::from_millis(1250)),
+            received: 4000,
+            missed: 12,
+        };
+        let frame = status.to_frame();
+
+        assert_eq!(frame[0], PacketKind::LinkStatus as u8);
+        assert_eq!(LinkStatus::from_frame(&frame), Some(status));
+        assert_eq!(
+            LinkStatus::from_frame(&LinkStatus::default().to_frame()),
+            Some(LinkStatus::default())
+        );
+        assert_eq!(LinkStatus::from_frame(&frame[..4]), None);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::telemetry::export::write_json_string;
+
+/// A line the basestation writes on its serial console in machine mode,
+/// one JSON object to a line:
+///
+/// - `{"ok":true,"reply":"sent"}` or `{"ok":false,"error":"..."}` answers a
+///   command line
+/// - `{"event":"rocket psl = 101325"}` is news between lines, such as an ack
+///   from the rocket
+/// - `{"frame":"e003..."}` is a telemetry or link status frame, in hex, as
+///   sent to dashboard clients
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SerialLine {
+    Reply(Result<String, String>),
+    Event(String),
+    Frame(Vec<u8>),
+}
+
+impl SerialLine {
+    /// Encode as a line, with its `\r\n` ending.
+    pub fn to_line(&self) -> String {
+        let mut line = String::new();
+
+        match self {
+            SerialLine::Reply(Ok(reply)) => {
+                line.push_str("{\"ok\":true,\"reply\":");
+                write_json_string(&mut line, reply).unwrap();
+            }
+            SerialLine::Reply(Err(e)) => {
+                line.push_str("{\"ok\":false,\"error\":");
+                write_json_string(&mut line, e).unwrap();
+            }
+            SerialLine::Event(text) => {
+                line.push_str("{\"event\":");
+                write_json_string(&mut line, text).unwrap();
+            }
+            SerialLine::Frame(frame) => {
+                line.push_str("{\"frame\":\"");
+                for byte in frame {
+                    line.push_str(&format!("{:02x}", byte));
+                }
+                line.push('"');
+            }
+        }
+
+        line.push_str("}\r\n");
+        line
+    }
+
+    /// Decode a line written by [`Self::to_line`], with or without its
+    /// ending.  Anything else on the console, such as log messages, is
+    /// `None`.
+    pub fn from_line(line: &str) -> Option<SerialLine> {
+        let mut members = Members::new(line.trim_end_matches(['\r', '\n']))?;
+        let (name, value) = members.next()?;
+
+        let line = match (name.as_str(), value) {
+            ("ok", Value::Bool(ok)) => {
+                let (name, value) = members.next()?;
+                match (ok, name.as_str(), value) {
+                    (true, "reply", Value::Text(reply)) => SerialLine::Reply(Ok(reply)),
+                    (false, "error", Value::Text(e)) => SerialLine::Reply(Err(e)),
+                    _ => return None,
+                }
+            }
+            ("event", Value::Text(text)) => SerialLine::Event(text),
+            ("frame", Value::Text(hex)) => SerialLine::Frame(from_hex(&hex)?),
+            _ => return None,
+        };
+
+        members.end().then_some(line)
+    }
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+enum Value {
+    Bool(bool),
+    Text(String),
+}
+
+/// Reads the members of a flat JSON object of strings and booleans, the
+/// only values [`SerialLine`] uses.
+struct Members<'a> {
+    rest: &'a str,
+    first: bool,
+}
+
+impl<'a> Members<'a> {
+    fn new(line: &'a str) -> Option<Self> {
+        Some(Self {
+            rest: line.trim().strip_prefix('{')?,
+            first: true,
+        })
+    }
+
+    fn next(&mut self) -> Option<(String, Value)> {
+        if !std::mem::replace(&mut self.first, false) {
+            self.rest = self.rest.trim_start().strip_prefix(',')?;
+        }
+
+        let name = self.string()?;
+        self.rest = self.rest.trim_start().strip_prefix(':')?.trim_start();
+
+        let value = if let Some(rest) = self.rest.strip_prefix("true") {
+            self.rest = rest;
+            Value::Bool(true)
+        } else if let Some(rest) = self.rest.strip_prefix("false") {
+            self.rest = rest;
+            Value::Bool(false)
+        } else {
+            Value::Text(self.string()?)
+        };
+
+        Some((name, value))
+    }
+
+    /// Whether the object closes with nothing after it.
+    fn end(&self) -> bool {
+        self.rest.trim() == "}"
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let mut chars = self.rest.trim_start().strip_prefix('"')?.chars();
+        let mut text = String::new();
+
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => text.push(match chars.next()? {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'u' => {
+                        let hex: String = chars.by_ref().take(4).collect();
+                        char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+                    }
+                    c => c,
+                }),
+                c => text.push(c),
+            }
+        }
+
+        self.rest = chars.as_str();
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::{ProtocolVersion, Telemetry};
+
+    #[test]
+    fn round_trips_lines() {
+        let frame = Telemetry::default().to_frame(ProtocolVersion::Extended);
+        let lines = [
+            SerialLine::Reply(Ok("psl = 101325\nsaved".to_string())),
+            SerialLine::Reply(Err("say \"help\"".to_string())),
+            SerialLine::Event("rocket batch = 4".to_string()),
+            SerialLine::Frame(frame),
+        ];
+
+        for line in lines {
+            let text = line.to_line();
+            assert!(text.ends_with("}\r\n"));
+            assert_eq!(SerialLine::from_line(&text), Some(line));
+        }
+
+        assert_eq!(
+            SerialLine::Reply(Ok("sent".to_string())).to_line(),
+            "{\"ok\":true,\"reply\":\"sent\"}\r\n"
+        );
+        assert_eq!(
+            SerialLine::Frame(vec![0xE0, 0x03, 0x7F]).to_line(),
+            "{\"frame\":\"e0037f\"}\r\n"
+        );
+    }
+
+    #[test]
+    fn ignores_other_lines() {
+        for line in [
+            "I (1234) basestation: started",
+            "> ",
+            "{\"frame\":\"e00\"}",
+            "{\"ok\":true,\"error\":\"no\"}",
+            "{\"event\":\"a\"} trailing",
+            "{\"event\":\"unterminated}",
+        ] {
+            assert_eq!(SerialLine::from_line(line), None, "{}", line);
+        }
+    }
+}
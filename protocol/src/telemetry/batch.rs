@@ -0,0 +1,591 @@
+use std::time::{Duration, Instant};
+
+use bytes::{Buf, BufMut, BytesMut};
+
+use crate::datalink::{
+    packet::{PacketKind, MAX_FRAME_SIZE},
+    ByteSerialize,
+};
+
+use super::{
+    compact::{CompactTelemetry, COMPACT_TELEMETRY_SIZE},
+    extended::{ExtendedTelemetry, EXTENDED_TELEMETRY_SIZE},
+    ProtocolVersion, Status, Telemetry,
+};
+
+// kind, protocol version, sample count
+const HEADER_SIZE: usize = 3;
+// delta of each compact field from the previous sample
+const COMPACT_DELTA_SIZE: usize = 10;
+// compact delta plus sequence and maximum altitude deltas, phase, status,
+// vertical velocity and filter variance
+const EXTENDED_DELTA_SIZE: usize = COMPACT_DELTA_SIZE + 11;
+
+fn sample_sizes(version: ProtocolVersion) -> (usize, usize) {
+    match version {
+        ProtocolVersion::Extended => (EXTENDED_TELEMETRY_SIZE, EXTENDED_DELTA_SIZE),
+        _ => (COMPACT_TELEMETRY_SIZE, COMPACT_DELTA_SIZE),
+    }
+}
+
+/// Most samples of `version` that fit in one ESP-NOW frame.
+pub fn max_batch_samples(version: ProtocolVersion) -> usize {
+    let (first, delta) = sample_sizes(version);
+    (MAX_FRAME_SIZE - HEADER_SIZE - first) / delta + 1
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Delta {
+    time: u16,
+    altitude: i16,
+    pressure: i16,
+    temperature: i16,
+    battery_voltage: i16,
+    sequence: u8,
+    maximum_altitude: i16,
+}
+
+impl Delta {
+    /// Difference between two samples, or `None` if it does not fit the delta encoding.
+    fn between(
+        previous: &ExtendedTelemetry,
+        next: &ExtendedTelemetry,
+        version: ProtocolVersion,
+    ) -> Option<Delta> {
+        let (p, n) = (&previous.base, &next.base);
+
+        let mut delta = Delta {
+            time: u16::try_from(n.time.checked_sub(p.time)?).ok()?,
+            altitude: i16::try_from(n.altitude_dm as i64 - p.altitude_dm as i64).ok()?,
+            pressure: i16::try_from(n.pressure_dpa as i64 - p.pressure_dpa as i64).ok()?,
+            temperature: i16::try_from(n.temperature_dc as i32 - p.temperature_dc as i32).ok()?,
+            battery_voltage: i16::try_from(n.battery_mv as i32 - p.battery_mv as i32).ok()?,
+            sequence: 0,
+            maximum_altitude: 0,
+        };
+
+        if version == ProtocolVersion::Extended {
+            delta.sequence = u8::try_from(next.sequence.checked_sub(previous.sequence)?).ok()?;
+            delta.maximum_altitude = i16::try_from(
+                next.maximum_altitude_dm as i64 - previous.maximum_altitude_dm as i64,
+            )
+            .ok()?;
+        }
+
+        Some(delta)
+    }
+
+    fn apply(&self, previous: &ExtendedTelemetry) -> ExtendedTelemetry {
+        let p = &previous.base;
+
+        ExtendedTelemetry {
+            base: CompactTelemetry {
+                time: p.time.wrapping_add(self.time as u32),
+                altitude_dm: p.altitude_dm.wrapping_add(self.altitude as i32),
+                pressure_dpa: p.pressure_dpa.wrapping_add_signed(self.pressure as i32),
+                temperature_dc: p.temperature_dc.wrapping_add(self.temperature),
+                battery_mv: p.battery_mv.wrapping_add_signed(self.battery_voltage),
+            },
+            sequence: previous.sequence.wrapping_add(self.sequence as u32),
+            maximum_altitude_dm: previous
+                .maximum_altitude_dm
+                .wrapping_add(self.maximum_altitude as i32),
+            ..*previous
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BatchError {
+    /// The batch already holds [`max_batch_samples`]
+    Full,
+    /// The sample is too far from the previous one to be delta encoded
+    Discontinuity,
+    /// The frame is not a well formed batch
+    Malformed,
+}
+
+/// Several telemetry samples sent in a single frame.
+///
+/// The first sample is sent in full and every following sample as the
+/// difference from the one before it, which lets a frame carry up to
+/// [`max_batch_samples`] samples.  Samples are sent at the resolution of
+/// [`CompactTelemetry`], or [`ExtendedTelemetry`] for
+/// [`ProtocolVersion::Extended`] batches.  Float samples cannot be delta
+/// encoded so [`ProtocolVersion::Float`] batches are sent as compact ones.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TelemetryBatch {
+    version: ProtocolVersion,
+    samples: Vec<ExtendedTelemetry>,
+}
+
+impl TelemetryBatch {
+    pub fn new(version: ProtocolVersion) -> Self {
+        let version = match version {
+            ProtocolVersion::Float => ProtocolVersion::Compact,
+            version => version,
+        };
+
+        Self {
+            version,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    /// Whether `telemetry` can be added without starting a new batch.
+    pub fn accepts(&self, telemetry: &Telemetry) -> bool {
+        self.check(&self.encode(telemetry)).is_ok()
+    }
+
+    /// `telemetry` at the resolution this batch sends it.
+    fn encode(&self, telemetry: &Telemetry) -> ExtendedTelemetry {
+        let sample = ExtendedTelemetry::from(telemetry);
+
+        match self.version {
+            ProtocolVersion::Extended => sample,
+            _ => ExtendedTelemetry {
+                base: sample.base,
+                ..ExtendedTelemetry::default()
+            },
+        }
+    }
+
+    fn check(&self, sample: &ExtendedTelemetry) -> Result<(), BatchError> {
+        match self.samples.last() {
+            None => Ok(()),
+            Some(_) if self.samples.len() >= max_batch_samples(self.version) => {
+                Err(BatchError::Full)
+            }
+            Some(last) => Delta::between(last, sample, self.version)
+                .map(|_| ())
+                .ok_or(BatchError::Discontinuity),
+        }
+    }
+
+    /// Add a sample.  Fails if the batch is full or the sample cannot be
+    /// delta encoded, e.g. time went backwards or the altitude jumped.
+    pub fn push(&mut self, telemetry: &Telemetry) -> Result<(), BatchError> {
+        let sample = self.encode(telemetry);
+        self.check(&sample)?;
+        self.samples.push(sample);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Samples as they will be seen by the receiver.
+    pub fn samples(&self) -> impl Iterator<Item = Telemetry> + '_ {
+        self.samples.iter().map(|s| Telemetry::from(*s))
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let (first_size, delta_size) = sample_sizes(self.version);
+        let mut buf =
+            BytesMut::with_capacity(HEADER_SIZE + first_size + delta_size * self.samples.len());
+
+        buf.put_u8(PacketKind::TelemetryBatch as u8);
+        buf.put_u8(self.version as u8);
+        buf.put_u8(self.samples.len() as u8);
+
+        let mut previous: Option<&ExtendedTelemetry> = None;
+
+        for sample in self.samples.iter() {
+            match previous {
+                None => {
+                    let mut first = [0u8; EXTENDED_TELEMETRY_SIZE];
+                    match self.version {
+                        ProtocolVersion::Extended => sample.as_bytes(&mut first),
+                        _ => sample.base.as_bytes(&mut first),
+                    }
+                    .expect("buffer is sized for a sample");
+                    buf.put_slice(&first[..first_size]);
+                }
+                Some(previous) => {
+                    let delta =
+                        Delta::between(previous, sample, self.version).expect("checked on push");
+                    buf.put_u16_le(delta.time);
+                    buf.put_i16_le(delta.altitude);
+                    buf.put_i16_le(delta.pressure);
+                    buf.put_i16_le(delta.temperature);
+                    buf.put_i16_le(delta.battery_voltage);
+
+                    if self.version == ProtocolVersion::Extended {
+                        buf.put_u8(delta.sequence);
+                        buf.put_i16_le(delta.maximum_altitude);
+                        buf.put_u8(sample.phase as u8);
+                        buf.put_u8(sample.status.0);
+                        buf.put_i16_le(sample.vertical_velocity_dms);
+                        buf.put_f32_le(sample.filter_variance);
+                    }
+                }
+            }
+            previous = Some(sample);
+        }
+
+        buf.to_vec()
+    }
+
+    pub fn from_bytes(buffer: &[u8]) -> Result<TelemetryBatch, BatchError> {
+        if PacketKind::of(buffer) != Some(PacketKind::TelemetryBatch) || buffer.len() < HEADER_SIZE
+        {
+            return Err(BatchError::Malformed);
+        }
+
+        let mut buf = &buffer[1..];
+        let version = match ProtocolVersion::try_from(buf.get_u8()) {
+            Ok(ProtocolVersion::Float) | Err(_) => return Err(BatchError::Malformed),
+            Ok(version) => version,
+        };
+        let count = buf.get_u8() as usize;
+
+        let mut batch = TelemetryBatch::new(version);
+
+        if count == 0 {
+            return Ok(batch);
+        }
+
+        let (first_size, delta_size) = sample_sizes(version);
+
+        if buf.remaining() < first_size + delta_size * (count - 1) {
+            return Err(BatchError::Malformed);
+        }
+
+        let mut previous = match version {
+            ProtocolVersion::Extended => ExtendedTelemetry::from_bytes(buf),
+            _ => CompactTelemetry::from_bytes(buf).map(|base| ExtendedTelemetry {
+                base,
+                ..ExtendedTelemetry::default()
+            }),
+        }
+        .map_err(|_| BatchError::Malformed)?;
+        buf.advance(first_size);
+        batch.samples.push(previous);
+
+        for _ in 1..count {
+            let mut delta = Delta {
+                time: buf.get_u16_le(),
+                altitude: buf.get_i16_le(),
+                pressure: buf.get_i16_le(),
+                temperature: buf.get_i16_le(),
+                battery_voltage: buf.get_i16_le(),
+                sequence: 0,
+                maximum_altitude: 0,
+            };
+
+            let mut sample;
+
+            if version == ProtocolVersion::Extended {
+                delta.sequence = buf.get_u8();
+                delta.maximum_altitude = buf.get_i16_le();
+                sample = delta.apply(&previous);
+                sample.phase = buf.get_u8().try_into().map_err(|_| BatchError::Malformed)?;
+                sample.status = Status(buf.get_u8());
+                sample.vertical_velocity_dms = buf.get_i16_le();
+                sample.filter_variance = buf.get_f32_le();
+            } else {
+                sample = delta.apply(&previous);
+            }
+
+            batch.samples.push(sample);
+            previous = sample;
+        }
+
+        Ok(batch)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct BatchConfig {
+    /// Encoding of the samples in each batch
+    pub version: ProtocolVersion,
+    /// Samples collected before a batch is sent, at most [`max_batch_samples`]
+    pub max_samples: usize,
+    /// Longest a sample may wait in a partial batch
+    pub max_latency: Duration,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            version: ProtocolVersion::Extended,
+            max_samples: 5,
+            max_latency: Duration::from_millis(250),
+        }
+    }
+}
+
+/// Collects samples into batches, handing back an encoded frame whenever a
+/// batch fills up or its oldest sample reaches the latency bound.
+pub struct TelemetryBatcher {
+    config: BatchConfig,
+    batch: TelemetryBatch,
+    started: Option<Instant>,
+}
+
+impl TelemetryBatcher {
+    pub fn new(config: BatchConfig) -> Self {
+        Self {
+            config: BatchConfig {
+                max_samples: config
+                    .max_samples
+                    .clamp(1, max_batch_samples(config.version)),
+                ..config
+            },
+            batch: TelemetryBatch::new(config.version),
+            started: None,
+        }
+    }
+
+    /// Add a sample.  Returns a frame to send if a batch was completed.
+    ///
+    /// A sample that cannot be delta encoded against the current batch
+    /// completes that batch and starts the next one; call
+    /// [`TelemetryBatcher::poll`] regularly so that one is sent on time.
+    pub fn push(&mut self, telemetry: Telemetry, now: Instant) -> Option<Vec<u8>> {
+        if !self.batch.accepts(&telemetry) {
+            let frame = self.flush();
+            self.batch
+                .push(&telemetry)
+                .expect("empty batch accepts any sample");
+            self.started = Some(now);
+            return frame;
+        }
+
+        self.batch.push(&telemetry).expect("checked by accepts");
+        self.started.get_or_insert(now);
+
+        if self.batch.len() >= self.config.max_samples {
+            self.flush()
+        } else {
+            self.poll(now)
+        }
+    }
+
+    /// Returns a frame if the pending batch has waited `max_latency`.
+    pub fn poll(&mut self, now: Instant) -> Option<Vec<u8>> {
+        match self.started {
+            Some(started) if now.duration_since(started) >= self.config.max_latency => self.flush(),
+            _ => None,
+        }
+    }
+
+    /// Encode whatever is pending, if anything.
+    pub fn flush(&mut self) -> Option<Vec<u8>> {
+        self.started = None;
+
+        if self.batch.is_empty() {
+            return None;
+        }
+
+        let frame = self.batch.as_bytes();
+        self.batch.clear();
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flight::FlightPhase;
+
+    fn sample(i: u32) -> Telemetry {
+        Telemetry {
+            time: 1000 + i * 50,
+            altitude: 12.3 + i as f32 * 4.1,
+            pressure: 101325.4 - i as f32 * 15.2,
+            temperature: 21.5 + i as f32 * 0.1,
+            battery_voltage: 3.912 - i as f32 * 0.001,
+            sequence: i,
+            phase: if i < 5 {
+                FlightPhase::Pad
+            } else {
+                FlightPhase::Ascent
+            },
+            vertical_velocity: i as f32 * 20.5,
+            maximum_altitude: 12.3 + i as f32 * 4.1,
+            filter_variance: 0.25 / (i + 1) as f32,
+            status: Status::CHARGING,
+        }
+    }
+
+    fn assert_close(a: &Telemetry, b: &Telemetry) {
+        assert_eq!(a.time, b.time);
+        // half a decimeter, in feet
+        assert!((a.altitude - b.altitude).abs() <= 0.165);
+        assert!((a.pressure - b.pressure).abs() <= 0.05 + 1e-2);
+        assert!((a.temperature - b.temperature).abs() <= 0.05 + 1e-3);
+        assert!((a.battery_voltage - b.battery_voltage).abs() <= 0.0005 + 1e-5);
+    }
+
+    fn round_trip(version: ProtocolVersion) -> Vec<Telemetry> {
+        let mut batch = TelemetryBatch::new(version);
+        let sent: Vec<Telemetry> = (0..10).map(sample).collect();
+
+        for s in sent.iter() {
+            batch.push(s).unwrap();
+        }
+
+        let bytes = batch.as_bytes();
+        let (first_size, delta_size) = sample_sizes(version);
+        assert_eq!(bytes.len(), HEADER_SIZE + first_size + 9 * delta_size);
+
+        let decoded = TelemetryBatch::from_bytes(&bytes).unwrap();
+        let received: Vec<Telemetry> = decoded.samples().collect();
+
+        assert_eq!(decoded, batch);
+        assert_eq!(received.len(), sent.len());
+        for (a, b) in sent.iter().zip(received.iter()) {
+            assert_close(a, b);
+        }
+
+        received
+    }
+
+    #[test]
+    fn round_trips_within_resolution() {
+        for r in round_trip(ProtocolVersion::Compact) {
+            assert_eq!(r.sequence, 0);
+            assert_eq!(r.status, Status::default());
+        }
+
+        let sent = (0..10).map(sample);
+        for (s, r) in sent.zip(round_trip(ProtocolVersion::Extended)) {
+            assert_eq!(r.sequence, s.sequence);
+            assert_eq!(r.phase, s.phase);
+            assert_eq!(r.status, s.status);
+            assert_eq!(r.filter_variance, s.filter_variance);
+            assert!((r.vertical_velocity - s.vertical_velocity).abs() <= 0.165);
+            assert!((r.maximum_altitude - s.maximum_altitude).abs() <= 0.165);
+        }
+    }
+
+    #[test]
+    fn float_batches_are_sent_compact() {
+        let batch = TelemetryBatch::new(ProtocolVersion::Float);
+        assert_eq!(batch.version(), ProtocolVersion::Compact);
+    }
+
+    #[test]
+    fn full_batch_fits_one_frame() {
+        for version in [ProtocolVersion::Compact, ProtocolVersion::Extended] {
+            let mut batch = TelemetryBatch::new(version);
+            let max = max_batch_samples(version) as u32;
+
+            for i in 0..max {
+                batch.push(&sample(i)).unwrap();
+            }
+
+            assert_eq!(batch.push(&sample(max)), Err(BatchError::Full));
+            assert!(batch.as_bytes().len() <= MAX_FRAME_SIZE);
+        }
+    }
+
+    #[test]
+    fn rejects_samples_that_do_not_delta_encode() {
+        let mut batch = TelemetryBatch::new(ProtocolVersion::Extended);
+        batch.push(&sample(1)).unwrap();
+
+        // time going backwards
+        assert_eq!(batch.push(&sample(0)), Err(BatchError::Discontinuity));
+
+        // altitude jump larger than an i16 delta
+        let mut jump = sample(2);
+        jump.altitude += 20_000.0;
+        assert_eq!(batch.push(&jump), Err(BatchError::Discontinuity));
+
+        // sequence going backwards
+        let mut restarted = sample(2);
+        restarted.sequence = 0;
+        assert_eq!(batch.push(&restarted), Err(BatchError::Discontinuity));
+
+        assert_eq!(batch.len(), 1);
+    }
+
+    #[test]
+    fn rejects_truncated_frames() {
+        let mut batch = TelemetryBatch::new(ProtocolVersion::Extended);
+        batch.push(&sample(0)).unwrap();
+        batch.push(&sample(1)).unwrap();
+        let bytes = batch.as_bytes();
+
+        assert_eq!(
+            TelemetryBatch::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BatchError::Malformed)
+        );
+        assert_eq!(
+            TelemetryBatch::from_bytes(&bytes[1..]),
+            Err(BatchError::Malformed)
+        );
+
+        let mut float = bytes.clone();
+        float[1] = ProtocolVersion::Float as u8;
+        assert_eq!(
+            TelemetryBatch::from_bytes(&float),
+            Err(BatchError::Malformed)
+        );
+    }
+
+    #[test]
+    fn batcher_flushes_on_size() {
+        let mut batcher = TelemetryBatcher::new(BatchConfig {
+            max_samples: 3,
+            max_latency: Duration::from_secs(10),
+            ..BatchConfig::default()
+        });
+        let now = Instant::now();
+
+        assert!(batcher.push(sample(0), now).is_none());
+        assert!(batcher.push(sample(1), now).is_none());
+        let frame = batcher.push(sample(2), now).expect("batch is full");
+
+        assert_eq!(TelemetryBatch::from_bytes(&frame).unwrap().len(), 3);
+        assert!(batcher.flush().is_none());
+    }
+
+    #[test]
+    fn batcher_flushes_on_latency() {
+        let config = BatchConfig {
+            max_samples: 10,
+            max_latency: Duration::from_millis(100),
+            ..BatchConfig::default()
+        };
+        let mut batcher = TelemetryBatcher::new(config);
+        let now = Instant::now();
+
+        assert!(batcher.push(sample(0), now).is_none());
+        assert!(batcher.poll(now + Duration::from_millis(50)).is_none());
+
+        let frame = batcher
+            .push(sample(1), now + config.max_latency)
+            .expect("latency bound reached");
+        assert_eq!(TelemetryBatch::from_bytes(&frame).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn batcher_starts_new_batch_on_discontinuity() {
+        let mut batcher = TelemetryBatcher::new(BatchConfig::default());
+        let now = Instant::now();
+
+        batcher.push(sample(5), now);
+        let frame = batcher.push(sample(0), now).expect("old batch is sent");
+
+        assert_eq!(TelemetryBatch::from_bytes(&frame).unwrap().len(), 1);
+        let rest = batcher.flush().unwrap();
+        let rest = TelemetryBatch::from_bytes(&rest).unwrap();
+        assert_eq!(rest.samples().next().unwrap().time, sample(0).time);
+    }
+}
@@ -0,0 +1,215 @@
+use bytes::{Buf, BufMut};
+
+use crate::datalink::ByteSerialize;
+
+use super::Telemetry;
+
+/// Encoded size of a [`CompactTelemetry`], versus 20 bytes for the float form.
+pub const COMPACT_TELEMETRY_SIZE: usize = 16;
+
+const FEET_PER_METER: f64 = 1.0 / 0.3048;
+
+pub(crate) fn feet_to_dm(feet: f32) -> i32 {
+    (feet as f64 / FEET_PER_METER * 10.0).round() as i32
+}
+
+pub(crate) fn dm_to_feet(dm: i32) -> f32 {
+    (dm as f64 / 10.0 * FEET_PER_METER) as f32
+}
+
+/// Telemetry sample as fixed-point scaled integers.
+///
+/// | field           | unit    | range                     |
+/// |-----------------|---------|---------------------------|
+/// | time            | 1 ms    | 0 to 49.7 days            |
+/// | altitude        | 1 dm    | ±214,748 km               |
+/// | pressure        | 0.1 Pa  | 0 to 429 MPa              |
+/// | temperature     | 0.1 °C  | -3276.8 to 3276.7 °C      |
+/// | battery voltage | 1 mV    | 0 to 65.535 V             |
+///
+/// [`Telemetry`] reports altitude in feet; it is converted to decimeters here
+/// and back again on decode.  Readings outside a range saturate at its ends.
+///
+/// Converting to [`Telemetry`] and back is lossless while every field is
+/// within ±2^22 units (altitude within ±419 km, pressure below 419 kPa),
+/// past which the `f32` fields of [`Telemetry`] can no longer hold every step.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompactTelemetry {
+    pub time: u32,
+    pub altitude_dm: i32,
+    pub pressure_dpa: u32,
+    pub temperature_dc: i16,
+    pub battery_mv: u16,
+}
+
+impl From<&Telemetry> for CompactTelemetry {
+    fn from(value: &Telemetry) -> Self {
+        // float to int casts saturate, so out of range readings clamp rather than wrap
+        Self {
+            time: value.time,
+            altitude_dm: feet_to_dm(value.altitude),
+            pressure_dpa: (value.pressure as f64 * 10.0).round() as u32,
+            temperature_dc: (value.temperature as f64 * 10.0).round() as i16,
+            battery_mv: (value.battery_voltage as f64 * 1000.0).round() as u16,
+        }
+    }
+}
+
+impl From<CompactTelemetry> for Telemetry {
+    fn from(value: CompactTelemetry) -> Self {
+        Self {
+            time: value.time,
+            altitude: dm_to_feet(value.altitude_dm),
+            pressure: (value.pressure_dpa as f64 / 10.0) as f32,
+            temperature: (value.temperature_dc as f64 / 10.0) as f32,
+            battery_voltage: (value.battery_mv as f64 / 1000.0) as f32,
+            ..Telemetry::default()
+        }
+    }
+}
+
+impl ByteSerialize<CompactTelemetry> for CompactTelemetry {
+    fn as_bytes(&self, buffer: &mut [u8]) -> Result<(), ()> {
+        if buffer.len() < COMPACT_TELEMETRY_SIZE {
+            return Err(());
+        }
+
+        let mut buf = &mut buffer[..];
+
+        buf.put_u32_le(self.time);
+        buf.put_i32_le(self.altitude_dm);
+        buf.put_u32_le(self.pressure_dpa);
+        buf.put_i16_le(self.temperature_dc);
+        buf.put_u16_le(self.battery_mv);
+
+        Ok(())
+    }
+
+    fn from_bytes(buffer: &[u8]) -> Result<CompactTelemetry, ()> {
+        if buffer.len() < COMPACT_TELEMETRY_SIZE {
+            return Err(());
+        }
+
+        let mut buf = buffer;
+
+        Ok(CompactTelemetry {
+            time: buf.get_u32_le(),
+            altitude_dm: buf.get_i32_le(),
+            pressure_dpa: buf.get_u32_le(),
+            temperature_dc: buf.get_i16_le(),
+            battery_mv: buf.get_u16_le(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: i32 = 1 << 22;
+
+    fn round_trip(compact: CompactTelemetry) {
+        let telemetry = Telemetry::from(compact);
+        assert_eq!(
+            CompactTelemetry::from(&telemetry),
+            compact,
+            "{:?}",
+            telemetry
+        );
+
+        let mut buffer = [0u8; COMPACT_TELEMETRY_SIZE];
+        compact.as_bytes(&mut buffer).unwrap();
+        assert_eq!(CompactTelemetry::from_bytes(&buffer), Ok(compact));
+    }
+
+    #[test]
+    fn lossless_through_telemetry_within_documented_range() {
+        for altitude_dm in (-LIMIT..=LIMIT)
+            .step_by(997)
+            .chain([-LIMIT, -1, 0, 1, LIMIT])
+        {
+            round_trip(CompactTelemetry {
+                altitude_dm,
+                ..Default::default()
+            });
+        }
+
+        for pressure_dpa in (0..=LIMIT as u32).step_by(991).chain([1, LIMIT as u32]) {
+            round_trip(CompactTelemetry {
+                pressure_dpa,
+                ..Default::default()
+            });
+        }
+
+        for temperature_dc in i16::MIN..=i16::MAX {
+            round_trip(CompactTelemetry {
+                temperature_dc,
+                ..Default::default()
+            });
+        }
+
+        for battery_mv in u16::MIN..=u16::MAX {
+            round_trip(CompactTelemetry {
+                battery_mv,
+                ..Default::default()
+            });
+        }
+
+        round_trip(CompactTelemetry {
+            time: u32::MAX,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn encodes_typical_flight_values() {
+        let telemetry = Telemetry {
+            time: 12345,
+            altitude: 1000.0,
+            pressure: 101325.25,
+            temperature: -12.34,
+            battery_voltage: 3.7004,
+            ..Telemetry::default()
+        };
+
+        let compact = CompactTelemetry::from(&telemetry);
+
+        assert_eq!(
+            compact,
+            CompactTelemetry {
+                time: 12345,
+                altitude_dm: 3048,
+                pressure_dpa: 1013253,
+                temperature_dc: -123,
+                battery_mv: 3700,
+            }
+        );
+    }
+
+    #[test]
+    fn saturates_out_of_range_readings() {
+        let telemetry = Telemetry {
+            time: 0,
+            altitude: f32::MAX,
+            pressure: -5.0,
+            temperature: 10_000.0,
+            battery_voltage: 100.0,
+            ..Telemetry::default()
+        };
+
+        let compact = CompactTelemetry::from(&telemetry);
+
+        assert_eq!(compact.altitude_dm, i32::MAX);
+        assert_eq!(compact.pressure_dpa, 0);
+        assert_eq!(compact.temperature_dc, i16::MAX);
+        assert_eq!(compact.battery_mv, u16::MAX);
+    }
+
+    #[test]
+    fn rejects_short_buffers() {
+        let mut buffer = [0u8; COMPACT_TELEMETRY_SIZE - 1];
+
+        assert!(CompactTelemetry::default().as_bytes(&mut buffer).is_err());
+        assert!(CompactTelemetry::from_bytes(&buffer).is_err());
+    }
+}
@@ -0,0 +1,340 @@
+use core::fmt::{self, Write};
+
+use crate::flight::FlightPhase;
+
+use super::{Status, Telemetry, TelemetryError};
+
+/// Name and unit of each field, in the order of CSV columns and JSON members.
+pub const FIELDS: [&str; 11] = [
+    "time_ms",
+    "sequence",
+    "phase",
+    "status",
+    "altitude_ft",
+    "pressure_pa",
+    "temperature_c",
+    "battery_v",
+    "vertical_velocity_fps",
+    "maximum_altitude_ft",
+    "filter_variance_pa2",
+];
+
+/// First line of a CSV export.
+pub const CSV_HEADER: &str = "time_ms,sequence,phase,status,altitude_ft,pressure_pa,temperature_c,battery_v,vertical_velocity_fps,maximum_altitude_ft,filter_variance_pa2";
+
+enum Value {
+    Integer(u32),
+    Label(&'static str),
+    /// Written in the fewest digits that read back the same `f32`
+    Real(f32),
+}
+
+fn values(telemetry: &Telemetry) -> [Value; 11] {
+    [
+        Value::Integer(telemetry.time),
+        Value::Integer(telemetry.sequence),
+        Value::Label(telemetry.phase.label()),
+        Value::Integer(telemetry.status.0 as u32),
+        Value::Real(telemetry.altitude),
+        Value::Real(telemetry.pressure),
+        Value::Real(telemetry.temperature),
+        Value::Real(telemetry.battery_voltage),
+        Value::Real(telemetry.vertical_velocity),
+        Value::Real(telemetry.maximum_altitude),
+        Value::Real(telemetry.filter_variance),
+    ]
+}
+
+/// Set field `index` of [`FIELDS`] from its text, with an empty or `null`
+/// real read as NaN.
+fn set_field(telemetry: &mut Telemetry, index: usize, text: &str) -> Result<(), TelemetryError> {
+    fn integer<T: core::str::FromStr>(text: &str) -> Result<T, TelemetryError> {
+        text.parse().map_err(|_| TelemetryError::Malformed)
+    }
+
+    fn real(text: &str) -> Result<f32, TelemetryError> {
+        match text {
+            "" | "null" => Ok(f32::NAN),
+            text => text.parse().map_err(|_| TelemetryError::Malformed),
+        }
+    }
+
+    match index {
+        0 => telemetry.time = integer(text)?,
+        1 => telemetry.sequence = integer(text)?,
+        2 => {
+            telemetry.phase = (0..=u8::MAX)
+                .map_while(|value| FlightPhase::try_from(value).ok())
+                .find(|phase| phase.label() == text)
+                .ok_or(TelemetryError::Malformed)?
+        }
+        3 => telemetry.status = Status(integer(text)?),
+        4 => telemetry.altitude = real(text)?,
+        5 => telemetry.pressure = real(text)?,
+        6 => telemetry.temperature = real(text)?,
+        7 => telemetry.battery_voltage = real(text)?,
+        8 => telemetry.vertical_velocity = real(text)?,
+        9 => telemetry.maximum_altitude = real(text)?,
+        _ => telemetry.filter_variance = real(text)?,
+    }
+
+    Ok(())
+}
+
+/// Write `telemetry` as a JSON object with a member for each of [`FIELDS`].
+/// NaN and infinite readings, which JSON cannot hold, are `null`.
+pub fn write_json<W: Write>(w: &mut W, telemetry: &Telemetry) -> fmt::Result {
+    w.write_char('{')?;
+    for (i, (name, value)) in FIELDS.iter().zip(values(telemetry)).enumerate() {
+        if i > 0 {
+            w.write_char(',')?;
+        }
+        write!(w, "\"{}\":", name)?;
+        match value {
+            Value::Integer(value) => write!(w, "{}", value)?,
+            Value::Label(label) => write!(w, "\"{}\"", label)?,
+            Value::Real(value) if value.is_finite() => write!(w, "{}", value)?,
+            Value::Real(_) => w.write_str("null")?,
+        }
+    }
+    w.write_char('}')
+}
+
+/// Write `telemetry` as a CSV row under [`CSV_HEADER`], ending the line.
+/// NaN and infinite readings are left empty.
+pub fn write_csv<W: Write>(w: &mut W, telemetry: &Telemetry) -> fmt::Result {
+    for (i, value) in values(telemetry).into_iter().enumerate() {
+        if i > 0 {
+            w.write_char(',')?;
+        }
+        match value {
+            Value::Integer(value) => write!(w, "{}", value)?,
+            Value::Label(label) => w.write_str(label)?,
+            Value::Real(value) if value.is_finite() => write!(w, "{}", value)?,
+            Value::Real(_) => (),
+        }
+    }
+    w.write_char('\n')
+}
+
+/// Read an object written by [`write_json`].  Members may come in any
+/// order, but each must be there once.
+pub fn from_json(text: &str) -> Result<Telemetry, TelemetryError> {
+    let members = text
+        .trim()
+        .strip_prefix('{')
+        .and_then(|text| text.strip_suffix('}'))
+        .ok_or(TelemetryError::Malformed)?;
+
+    let mut telemetry = Telemetry::default();
+    let mut seen = 0_u16;
+    for member in members.split(',') {
+        let (name, value) = member.split_once(':').ok_or(TelemetryError::Malformed)?;
+        let name = name.trim().trim_matches('"');
+        let index = FIELDS
+            .iter()
+            .position(|field| *field == name)
+            .ok_or(TelemetryError::Malformed)?;
+        if seen & (1 << index) != 0 {
+            return Err(TelemetryError::Malformed);
+        }
+        seen |= 1 << index;
+
+        set_field(&mut telemetry, index, value.trim().trim_matches('"'))?;
+    }
+
+    if seen.count_ones() as usize != FIELDS.len() {
+        return Err(TelemetryError::Malformed);
+    }
+    Ok(telemetry)
+}
+
+/// Read a row written by [`write_csv`], with or without its line ending.
+pub fn from_csv(row: &str) -> Result<Telemetry, TelemetryError> {
+    let mut telemetry = Telemetry::default();
+    let mut columns = 0;
+
+    for (index, text) in row.trim_end_matches(['\r', '\n']).split(',').enumerate() {
+        if index >= FIELDS.len() {
+            return Err(TelemetryError::Malformed);
+        }
+        set_field(&mut telemetry, index, text)?;
+        columns += 1;
+    }
+
+    if columns != FIELDS.len() {
+        return Err(TelemetryError::Malformed);
+    }
+    Ok(telemetry)
+}
+
+/// Write `text` as a JSON string, quoted and escaped.
+pub fn write_json_string<W: Write>(w: &mut W, text: &str) -> fmt::Result {
+    w.write_char('"')?;
+    for c in text.chars() {
+        match c {
+            '"' => w.write_str("\\\"")?,
+            '\\' => w.write_str("\\\\")?,
+            c if c.is_control() => write!(w, "\\u{:04x}", c as u32)?,
+            c => w.write_char(c)?,
+        }
+    }
+    w.write_char('"')
+}
+
+/// Encoding of a downloaded flight log.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// An array of [`write_json`] objects
+    Json,
+    /// [`CSV_HEADER`] and a [`write_csv`] row for each sample
+    Csv,
+}
+
+impl LogFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            LogFormat::Json => "application/json",
+            LogFormat::Csv => "text/csv",
+        }
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            LogFormat::Json => "flight.json",
+            LogFormat::Csv => "flight.csv",
+        }
+    }
+
+    /// Write what comes before the first sample.
+    pub fn write_start<W: Write>(&self, w: &mut W) -> fmt::Result {
+        match self {
+            LogFormat::Json => w.write_char('['),
+            LogFormat::Csv => writeln!(w, "{}", CSV_HEADER),
+        }
+    }
+
+    /// Write the sample at `index` in the log, so a log can be written a
+    /// sample at a time.
+    pub fn write_sample<W: Write>(
+        &self,
+        w: &mut W,
+        index: usize,
+        telemetry: &Telemetry,
+    ) -> fmt::Result {
+        match self {
+            LogFormat::Json if index > 0 => {
+                w.write_char(',')?;
+                write_json(w, telemetry)
+            }
+            LogFormat::Json => write_json(w, telemetry),
+            LogFormat::Csv => write_csv(w, telemetry),
+        }
+    }
+
+    pub fn write_end<W: Write>(&self, w: &mut W) -> fmt::Result {
+        match self {
+            LogFormat::Json => w.write_char(']'),
+            LogFormat::Csv => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Telemetry {
+        Telemetry {
+            time: 4200,
+            altitude: 321.5,
+            pressure: 100123.4,
+            temperature: -1.25,
+            battery_voltage: 3.987,
+            sequence: 17,
+            phase: FlightPhase::Descent,
+            vertical_velocity: -42.125,
+            maximum_altitude: 1234.5,
+            filter_variance: 1.0e-7,
+            status: Status(Status::CHARGING.0 | Status::SENSOR_FAULT.0),
+        }
+    }
+
+    // Telemetry has no PartialEq, and NaN would not equal itself anyway
+    fn assert_same(a: &Telemetry, b: &Telemetry) {
+        assert_eq!(format!("{:?}", a), format!("{:?}", b));
+    }
+
+    #[test]
+    fn round_trips_json() {
+        let mut json = String::new();
+        write_json(&mut json, &sample()).unwrap();
+
+        assert!(
+            json.starts_with("{\"time_ms\":4200,\"sequence\":17,\"phase\":\"DSC\",\"status\":5,")
+        );
+        assert_same(&from_json(&json).unwrap(), &sample());
+
+        let unknown = Telemetry {
+            filter_variance: f32::NAN,
+            ..sample()
+        };
+        json.clear();
+        write_json(&mut json, &unknown).unwrap();
+        assert!(json.ends_with("\"filter_variance_pa2\":null}"));
+        assert_same(&from_json(&json).unwrap(), &unknown);
+
+        assert!(from_json("{\"time_ms\":1}").is_err());
+        assert!(from_json(&json.replace("DSC", "UP")).is_err());
+    }
+
+    #[test]
+    fn round_trips_csv() {
+        let mut csv = String::new();
+        write_csv(&mut csv, &sample()).unwrap();
+
+        assert_eq!(
+            csv,
+            "4200,17,DSC,5,321.5,100123.4,-1.25,3.987,-42.125,1234.5,0.0000001\n"
+        );
+        assert_same(&from_csv(&csv).unwrap(), &sample());
+        assert_eq!(CSV_HEADER, FIELDS.join(","));
+
+        assert!(from_csv("4200,17,DSC").is_err());
+        assert!(from_csv(&format!("{},1", csv.trim_end())).is_err());
+    }
+
+    #[test]
+    fn writes_logs() {
+        let samples = [sample(), Telemetry::default()];
+
+        for format in [LogFormat::Json, LogFormat::Csv] {
+            let mut log = String::new();
+            format.write_start(&mut log).unwrap();
+            for (i, telemetry) in samples.iter().enumerate() {
+                format.write_sample(&mut log, i, telemetry).unwrap();
+            }
+            format.write_end(&mut log).unwrap();
+
+            let read: Vec<Telemetry> = match format {
+                LogFormat::Json => log
+                    .strip_prefix('[')
+                    .and_then(|log| log.strip_suffix(']'))
+                    .unwrap()
+                    .split_inclusive('}')
+                    .map(|object| from_json(object.trim_start_matches(',')).unwrap())
+                    .collect(),
+                LogFormat::Csv => {
+                    let mut lines = log.lines();
+                    assert_eq!(lines.next(), Some(CSV_HEADER));
+                    lines.map(|row| from_csv(row).unwrap()).collect()
+                }
+            };
+
+            assert_eq!(read.len(), samples.len());
+            for (read, sample) in read.iter().zip(&samples) {
+                assert_same(read, sample);
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use bytes::{Buf, BufMut};
+
+use crate::{datalink::ByteSerialize, flight::FlightPhase};
+
+use super::{
+    compact::{dm_to_feet, feet_to_dm, CompactTelemetry, COMPACT_TELEMETRY_SIZE},
+    Status, Telemetry,
+};
+
+/// Encoded size of an [`ExtendedTelemetry`].
+pub const EXTENDED_TELEMETRY_SIZE: usize = COMPACT_TELEMETRY_SIZE + 16;
+
+/// [`CompactTelemetry`] followed by the flight state.
+///
+/// | field             | unit    | range                   |
+/// |-------------------|---------|-------------------------|
+/// | sequence          | sample  | 0 to 2^32 - 1           |
+/// | phase             |         | see [`FlightPhase`]     |
+/// | status            |         | see [`Status`]          |
+/// | vertical velocity | 1 dm/s  | ±3276.7 m/s             |
+/// | maximum altitude  | 1 dm    | ±214,748 km             |
+/// | filter variance   | Pa²     | `f32`                   |
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ExtendedTelemetry {
+    pub base: CompactTelemetry,
+    pub sequence: u32,
+    pub phase: FlightPhase,
+    pub status: Status,
+    pub vertical_velocity_dms: i16,
+    pub maximum_altitude_dm: i32,
+    pub filter_variance: f32,
+}
+
+impl From<&Telemetry> for ExtendedTelemetry {
+    fn from(value: &Telemetry) -> Self {
+        Self {
+            base: CompactTelemetry::from(value),
+            sequence: value.sequence,
+            phase: value.phase,
+            status: value.status,
+            vertical_velocity_dms: feet_to_dm(value.vertical_velocity)
+                .clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+            maximum_altitude_dm: feet_to_dm(value.maximum_altitude),
+            filter_variance: value.filter_variance,
+        }
+    }
+}
+
+impl From<ExtendedTelemetry> for Telemetry {
+    fn from(value: ExtendedTelemetry) -> Self {
+        Self {
+            sequence: value.sequence,
+            phase: value.phase,
+            status: value.status,
+            vertical_velocity: dm_to_feet(value.vertical_velocity_dms as i32),
+            maximum_altitude: dm_to_feet(value.maximum_altitude_dm),
+            filter_variance: value.filter_variance,
+            ..Telemetry::from(value.base)
+        }
+    }
+}
+
+impl ByteSerialize<ExtendedTelemetry> for ExtendedTelemetry {
+    fn as_bytes(&self, buffer: &mut [u8]) -> Result<(), ()> {
+        if buffer.len() < EXTENDED_TELEMETRY_SIZE {
+            return Err(());
+        }
+
+        self.base.as_bytes(buffer)?;
+
+        let mut buf = &mut buffer[COMPACT_TELEMETRY_SIZE..];
+
+        buf.put_u32_le(self.sequence);
+        buf.put_u8(self.phase as u8);
+        buf.put_u8(self.status.0);
+        buf.put_i16_le(self.vertical_velocity_dms);
+        buf.put_i32_le(self.maximum_altitude_dm);
+        buf.put_f32_le(self.filter_variance);
+
+        Ok(())
+    }
+
+    fn from_bytes(buffer: &[u8]) -> Result<ExtendedTelemetry, ()> {
+        if buffer.len() < EXTENDED_TELEMETRY_SIZE {
+            return Err(());
+        }
+
+        let base = CompactTelemetry::from_bytes(buffer)?;
+
+        let mut buf = &buffer[COMPACT_TELEMETRY_SIZE..];
+
+        Ok(ExtendedTelemetry {
+            base,
+            sequence: buf.get_u32_le(),
+            phase: FlightPhase::try_from(buf.get_u8())?,
+            status: Status(buf.get_u8()),
+            vertical_velocity_dms: buf.get_i16_le(),
+            maximum_altitude_dm: buf.get_i32_le(),
+            filter_variance: buf.get_f32_le(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lossless_through_telemetry() {
+        let extended = ExtendedTelemetry {
+            base: CompactTelemetry {
+                time: 90210,
+                altitude_dm: 15240,
+                pressure_dpa: 843210,
+                temperature_dc: -45,
+                battery_mv: 3987,
+            },
+            sequence: 1234,
+            phase: FlightPhase::Descent,
+            status: Status(Status::CHARGING.0 | Status::SENSOR_FAULT.0),
+            vertical_velocity_dms: -253,
+            maximum_altitude_dm: 16002,
+            filter_variance: 0.0523,
+        };
+
+        let telemetry = Telemetry::from(extended);
+        assert_eq!(ExtendedTelemetry::from(&telemetry), extended);
+
+        let mut buffer = [0u8; EXTENDED_TELEMETRY_SIZE];
+        extended.as_bytes(&mut buffer).unwrap();
+        assert_eq!(ExtendedTelemetry::from_bytes(&buffer), Ok(extended));
+    }
+
+    #[test]
+    fn velocity_saturates() {
+        let telemetry = Telemetry {
+            vertical_velocity: -1.0e6,
+            ..Telemetry::default()
+        };
+
+        assert_eq!(
+            ExtendedTelemetry::from(&telemetry).vertical_velocity_dms,
+            i16::MIN
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_phase() {
+        let mut buffer = [0u8; EXTENDED_TELEMETRY_SIZE];
+        ExtendedTelemetry::default().as_bytes(&mut buffer).unwrap();
+        buffer[COMPACT_TELEMETRY_SIZE + 4] = 42;
+
+        assert!(ExtendedTelemetry::from_bytes(&buffer).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    datalink::{packet::PacketKind, ByteSerialize},
+    flight::FlightPhase,
+};
+
+pub mod batch;
+pub mod compact;
+pub mod export;
+pub mod extended;
+
+use compact::{CompactTelemetry, COMPACT_TELEMETRY_SIZE};
+use extended::{ExtendedTelemetry, EXTENDED_TELEMETRY_SIZE};
+
+/// Encoded size of the float form, also used by untagged frames.
+pub const FLOAT_TELEMETRY_SIZE: usize = 20;
+
+/// Encoding of the sample in a [`PacketKind::Telemetry`] frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ProtocolVersion {
+    /// Four `f32` values, the same layout as untagged frames
+    Float = 1,
+    /// Fixed-point integers, see [`CompactTelemetry`]
+    Compact = 2,
+    /// Compact sample plus flight state, see [`ExtendedTelemetry`]
+    Extended = 3,
+}
+
+impl TryFrom<u8> for ProtocolVersion {
+    type Error = TelemetryError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(ProtocolVersion::Float),
+            2 => Ok(ProtocolVersion::Compact),
+            3 => Ok(ProtocolVersion::Extended),
+            v => Err(TelemetryError::UnknownVersion(v)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TelemetryError {
+    UnknownVersion(u8),
+    Malformed,
+}
+
+/// Bitfield of conditions reported with each sample.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Status(pub u8);
+
+impl Status {
+    /// The battery is on charge
+    pub const CHARGING: Status = Status(1 << 0);
+    /// The battery is below [`LOW_BATTERY_VOLTAGE`]
+    pub const LOW_BATTERY: Status = Status(1 << 1);
+    /// The altimeter failed to read since the previous sample
+    pub const SENSOR_FAULT: Status = Status(1 << 2);
+
+    pub fn contains(&self, flag: Status) -> bool {
+        self.0 & flag.0 == flag.0
+    }
+
+    pub fn set(&mut self, flag: Status, value: bool) {
+        if value {
+            self.0 |= flag.0;
+        } else {
+            self.0 &= !flag.0;
+        }
+    }
+}
+
+pub const LOW_BATTERY_VOLTAGE: f32 = 3.5;
+
+/// One altimeter sample.  Altitudes are in feet and velocity in feet per second.
+///
+/// The fields after `battery_voltage` are only carried by
+/// [`ProtocolVersion::Extended`]; older encodings decode them as defaults.
+#[derive(Debug, Clone, Copy)]
+pub struct Telemetry {
+    pub time: u32,
+    pub altitude: f32,
+    pub pressure: f32,
+    pub temperature: f32,
+    pub battery_voltage: f32,
+
+    pub sequence: u32,
+    pub phase: FlightPhase,
+    pub vertical_velocity: f32,
+    pub maximum_altitude: f32,
+    pub filter_variance: f32,
+    pub status: Status,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Telemetry {
+            time: 0,
+            pressure: 0f32,
+            altitude: 0f32,
+            temperature: 0f32,
+            battery_voltage: 0f32,
+            sequence: 0,
+            phase: FlightPhase::Pad,
+            vertical_velocity: 0f32,
+            maximum_altitude: 0f32,
+            filter_variance: 0f32,
+            status: Status::default(),
+        }
+    }
+}
+
+impl ByteSerialize<Telemetry> for Telemetry {
+    fn as_bytes(&self, buffer: &mut [u8]) -> Result<(), ()> {
+        let mut buf = BytesMut::with_capacity(FLOAT_TELEMETRY_SIZE);
+
+        buf.put_u32_le(self.time);
+        buf.put_f32_le(self.pressure);
+        buf.put_f32_le(self.altitude);
+        buf.put_f32_le(self.temperature);
+        buf.put_f32_le(self.battery_voltage);
+
+        buffer[..buf.len()].copy_from_slice(&buf);
+
+        Ok(())
+    }
+
+    fn from_bytes(buffer: &[u8]) -> Result<Telemetry, ()> {
+        if buffer.len() < FLOAT_TELEMETRY_SIZE {
+            return Err(());
+        }
+
+        let mut buf = Bytes::copy_from_slice(buffer);
+
+        Ok::<Telemetry, ()>(Telemetry {
+            time: buf.get_u32_le(),
+            pressure: buf.get_f32_le(),
+            altitude: buf.get_f32_le(),
+            temperature: buf.get_f32_le(),
+            battery_voltage: buf.get_f32_le(),
+            ..Telemetry::default()
+        })
+    }
+}
+
+impl Telemetry {
+    /// Encode as a tagged frame using the given protocol version.
+    pub fn to_frame(&self, version: ProtocolVersion) -> Vec<u8> {
+        let size = match version {
+            ProtocolVersion::Float => FLOAT_TELEMETRY_SIZE,
+            ProtocolVersion::Compact => COMPACT_TELEMETRY_SIZE,
+            ProtocolVersion::Extended => EXTENDED_TELEMETRY_SIZE,
+        };
+
+        let mut frame = vec![0u8; 2 + size];
+        frame[0] = PacketKind::Telemetry as u8;
+        frame[1] = version as u8;
+
+        match version {
+            ProtocolVersion::Float => self.as_bytes(&mut frame[2..]),
+            ProtocolVersion::Compact => CompactTelemetry::from(self).as_bytes(&mut frame[2..]),
+            ProtocolVersion::Extended => ExtendedTelemetry::from(self).as_bytes(&mut frame[2..]),
+        }
+        .expect("frame is sized for the encoding");
+
+        frame
+    }
+
+    /// Decode a tagged frame of any protocol version, or an untagged float
+    /// frame of [`LEGACY_FRAME_SIZE`] as sent by firmware that predates
+    /// protocol versions.
+    ///
+    /// [`LEGACY_FRAME_SIZE`]: crate::datalink::packet::LEGACY_FRAME_SIZE
+    pub fn from_frame(frame: &[u8]) -> Result<Telemetry, TelemetryError> {
+        match PacketKind::of(frame) {
+            None => Telemetry::from_bytes(frame).map_err(|_| TelemetryError::Malformed),
+            Some(PacketKind::Telemetry) if frame.len() >= 2 => {
+                let payload = &frame[2..];
+                match ProtocolVersion::try_from(frame[1])? {
+                    ProtocolVersion::Float => Telemetry::from_bytes(payload),
+                    ProtocolVersion::Compact => {
+                        CompactTelemetry::from_bytes(payload).map(Telemetry::from)
+                    }
+                    ProtocolVersion::Extended => {
+                        ExtendedTelemetry::from_bytes(payload).map(Telemetry::from)
+                    }
+                }
+                .map_err(|_| TelemetryError::Malformed)
+            }
+            Some(_) => Err(TelemetryError::Malformed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::datalink::packet::LEGACY_FRAME_SIZE;
+
+    use super::*;
+    use batch::TelemetryBatch;
+
+    fn sample() -> Telemetry {
+        Telemetry {
+            time: 4200,
+            altitude: 321.5,
+            pressure: 100123.4,
+            temperature: 18.7,
+            battery_voltage: 3.81,
+            sequence: 77,
+            phase: FlightPhase::Ascent,
+            vertical_velocity: 120.5,
+            maximum_altitude: 330.0,
+            filter_variance: 0.052,
+            status: Status::CHARGING,
+        }
+    }
+
+    #[test]
+    fn both_versions_decode() {
+        let telemetry = sample();
+
+        let float = Telemetry::from_frame(&telemetry.to_frame(ProtocolVersion::Float)).unwrap();
+        assert_eq!(float.time, telemetry.time);
+        assert_eq!(float.altitude, telemetry.altitude);
+        assert_eq!(float.pressure, telemetry.pressure);
+
+        let compact_frame = telemetry.to_frame(ProtocolVersion::Compact);
+        assert_eq!(compact_frame.len(), 2 + COMPACT_TELEMETRY_SIZE);
+        let compact = Telemetry::from_frame(&compact_frame).unwrap();
+        assert_eq!(compact.time, telemetry.time);
+        assert!((compact.altitude - telemetry.altitude).abs() < 0.2);
+        assert!((compact.pressure - telemetry.pressure).abs() < 0.05 + 1e-2);
+        // compact frames do not carry flight state
+        assert_eq!(compact.sequence, 0);
+        assert_eq!(compact.phase, FlightPhase::Pad);
+
+        let extended =
+            Telemetry::from_frame(&telemetry.to_frame(ProtocolVersion::Extended)).unwrap();
+        assert_eq!(extended.sequence, telemetry.sequence);
+        assert_eq!(extended.phase, telemetry.phase);
+        assert_eq!(extended.status, telemetry.status);
+        assert_eq!(extended.filter_variance, telemetry.filter_variance);
+        assert!((extended.vertical_velocity - telemetry.vertical_velocity).abs() < 0.2);
+        assert!((extended.maximum_altitude - telemetry.maximum_altitude).abs() < 0.2);
+    }
+
+    #[test]
+    fn status_flags() {
+        let mut status = Status::default();
+        status.set(Status::CHARGING, true);
+        status.set(Status::SENSOR_FAULT, true);
+        status.set(Status::CHARGING, false);
+
+        assert!(!status.contains(Status::CHARGING));
+        assert!(status.contains(Status::SENSOR_FAULT));
+        assert_eq!(status, Status::SENSOR_FAULT);
+    }
+
+    #[test]
+    fn decodes_untagged_legacy_frames() {
+        let telemetry = sample();
+        let mut buffer = [0u8; LEGACY_FRAME_SIZE];
+        telemetry.as_bytes(&mut buffer).unwrap();
+
+        let decoded = Telemetry::from_frame(&buffer).unwrap();
+        assert_eq!(decoded.time, telemetry.time);
+        assert_eq!(decoded.battery_voltage, telemetry.battery_voltage);
+    }
+
+    #[test]
+    fn legacy_time_is_not_read_as_a_tag() {
+        for tag in [PacketKind::Telemetry, PacketKind::TelemetryBatch] {
+            let telemetry = Telemetry {
+                time: 0x1200 | tag as u32,
+                ..sample()
+            };
+            let mut buffer = [0u8; LEGACY_FRAME_SIZE];
+            telemetry.as_bytes(&mut buffer).unwrap();
+            assert_eq!(buffer[0], tag as u8);
+
+            assert_eq!(PacketKind::of(&buffer), None);
+            let decoded = Telemetry::from_frame(&buffer).unwrap();
+            assert_eq!(decoded.time, telemetry.time);
+            assert_eq!(decoded.altitude, telemetry.altitude);
+        }
+    }
+
+    #[test]
+    fn tagged_frames_are_never_legacy_sized() {
+        for version in [
+            ProtocolVersion::Float,
+            ProtocolVersion::Compact,
+            ProtocolVersion::Extended,
+        ] {
+            assert_ne!(sample().to_frame(version).len(), LEGACY_FRAME_SIZE);
+        }
+
+        for version in [ProtocolVersion::Compact, ProtocolVersion::Extended] {
+            let mut batch = TelemetryBatch::new(version);
+            for sequence in 0..batch::max_batch_samples(version) as u32 {
+                assert_ne!(batch.as_bytes().len(), LEGACY_FRAME_SIZE);
+                batch
+                    .push(&Telemetry {
+                        sequence,
+                        time: sequence * 100,
+                        ..sample()
+                    })
+                    .unwrap();
+            }
+            assert_ne!(batch.as_bytes().len(), LEGACY_FRAME_SIZE);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let mut frame = sample().to_frame(ProtocolVersion::Compact);
+        frame[1] = 99;
+
+        assert_eq!(
+            Telemetry::from_frame(&frame).unwrap_err(),
+            TelemetryError::UnknownVersion(99)
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use bmp390::{
@@ -9,6 +9,8 @@ use bmp390::{
 };
 use embedded_hal::i2c::I2c;
 
+use crate::config::{FilterNoise, DEFAULT_SEA_LEVEL_PRESSURE};
+
 #[derive(Copy, Clone, Debug)]
 pub struct KalmanState {
     n: u32,
@@ -46,6 +48,9 @@ impl KalmanState {
     }
 }
 
+// weight of each new reading in the vertical velocity average
+const VELOCITY_SMOOTHING: f64 = 0.3;
+
 #[derive(Copy, Clone, Debug)]
 pub struct AltimeterStats {
     pub maximum_altitude: f64,
@@ -57,9 +62,24 @@ pub struct AltimeterStats {
     pub altitude: f64,
     pub temperature: f64,
     pub pressure: f64,
+    /// Feet per second, positive when climbing
+    pub vertical_velocity: f64,
 
     pub filtered_pressure: f64,
     kalman_state: KalmanState,
+    updated: Option<Instant>,
+}
+
+impl AltimeterStats {
+    /// Samples taken since the stats were last reset.
+    pub fn sample_count(&self) -> u32 {
+        self.kalman_state.n
+    }
+
+    /// Variance of the filtered pressure estimate.
+    pub fn filter_variance(&self) -> f64 {
+        self.kalman_state.p
+    }
 }
 
 impl Default for AltimeterStats {
@@ -74,9 +94,11 @@ impl Default for AltimeterStats {
             altitude: 0.0f64,
             temperature: 0.0f64,
             pressure: 0.0f64,
+            vertical_velocity: 0.0f64,
             filtered_pressure: 0.0f64,
 
             kalman_state: KalmanState::new(102178.0, 2500.0),
+            updated: None,
         }
     }
 }
@@ -85,6 +107,7 @@ pub struct Altimeter<I2C> {
     sensor: bmp390::BMP390<I2C>,
     pub stats: Arc<Mutex<AltimeterStats>>,
     sea_level_pressure: Arc<Mutex<f64>>,
+    filter_noise: Arc<Mutex<FilterNoise>>,
 }
 
 impl<I2C> Clone for Altimeter<I2C> {
@@ -93,6 +116,7 @@ impl<I2C> Clone for Altimeter<I2C> {
             sensor: self.sensor.clone(),
             stats: self.stats.clone(),
             sea_level_pressure: self.sea_level_pressure.clone(),
+            filter_noise: self.filter_noise.clone(),
         }
     }
 }
@@ -122,14 +146,24 @@ where
         Ok(Altimeter {
             sensor,
             stats,
-            sea_level_pressure: Arc::new(Mutex::new(102030.0)),
+            sea_level_pressure: Arc::new(Mutex::new(DEFAULT_SEA_LEVEL_PRESSURE)),
+            filter_noise: Arc::new(Mutex::new(FilterNoise::default())),
         })
     }
 
-    pub fn sea_level_pressure(&mut self, sea_level_pressure: f64) {
+    /// Pressure at sea level altitudes are calculated from, in Pa.
+    pub fn sea_level_pressure(&self) -> f64 {
+        *self.sea_level_pressure.lock().unwrap()
+    }
+
+    pub fn set_sea_level_pressure(&mut self, sea_level_pressure: f64) {
         *self.sea_level_pressure.lock().unwrap() = sea_level_pressure;
     }
 
+    pub fn set_filter_noise(&mut self, filter_noise: FilterNoise) {
+        *self.filter_noise.lock().unwrap() = filter_noise;
+    }
+
     pub fn reset_stats(&mut self) {
         let mut stats = self.stats.lock().expect("mutex is never closed");
         *stats = AltimeterStats::default();
@@ -169,12 +203,15 @@ where
 
         // Update stats and filter pressure
 
+        let noise = *self.filter_noise.lock().unwrap();
         let mut stats = self.stats.lock().unwrap();
 
         stats.temperature = temperature;
         stats.pressure = pressure;
 
-        stats.kalman_state.update(0.16f64, pressure, 0.025f64);
+        stats
+            .kalman_state
+            .update(noise.measurement, pressure, noise.process);
         stats.filtered_pressure = stats.kalman_state.x;
 
         let altitude = calc_altitude(
@@ -182,6 +219,18 @@ where
             *self.sea_level_pressure.lock().unwrap(),
         );
 
+        // difference of filtered altitudes, smoothed to damp sensor noise
+        let now = Instant::now();
+        if let Some(updated) = stats.updated {
+            let elapsed = now.duration_since(updated).as_secs_f64();
+            if elapsed > 0.0 {
+                let velocity = (altitude - stats.altitude) / elapsed;
+                stats.vertical_velocity +=
+                    VELOCITY_SMOOTHING * (velocity - stats.vertical_velocity);
+            }
+        }
+        stats.updated = Some(now);
+
         stats.altitude = altitude;
 
         stats.maximum_temperature = stats.maximum_temperature.max(temperature);
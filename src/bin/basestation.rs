@@ -1,38 +1,34 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     error::Error,
     rc::Rc,
     str::FromStr,
     sync::{
-        atomic::{AtomicBool, Ordering},
         mpsc::{self, Receiver, RecvTimeoutError, Sender},
         Arc, Mutex,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use embedded_graphics::{
-    geometry::Point,
-    mono_font::{ascii::FONT_6X9, MonoTextStyle},
-    pixelcolor::{Rgb565, RgbColor},
-    prelude::*,
-    primitives::{Line, PrimitiveStyle, Rectangle},
-    text::Text,
-};
+use embedded_graphics::{geometry::Point, prelude::*, primitives::Rectangle};
 
 use esp_idf_hal::{
-    delay::NON_BLOCK,
+    delay::{BLOCK, NON_BLOCK},
     gpio::{Gpio0, Gpio1, Gpio3},
     peripherals::Peripherals,
-    sys::EspError,
-    uart::{config::Config, UartDriver, UART0},
+    sys::{EspError, ESP_ERR_INVALID_SIZE},
+    uart::{config::Config, UartDriver, UartRxDriver, UartTxDriver, UART0},
     units::Hertz,
 };
 use esp_idf_svc::{
     espnow::PeerInfo,
     eventloop::EspSystemEventLoop,
-    http::server::EspHttpServer,
-    nvs::EspDefaultNvsPartition,
+    http::{
+        server::{EspHttpConnection, EspHttpServer, Request},
+        Method,
+    },
+    io::Write,
+    nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault},
     wifi::{
         AccessPointConfiguration, AuthMethod, BlockingWifi, ClientConfiguration, Configuration,
         EspWifi,
@@ -42,16 +38,62 @@ use esp_idf_svc::{
 
 use ez_cyd_rs::CydDisplay;
 use rocket::{
-    altimeter::calc_altitude,
+    command::{
+        Ack, AckStatus, Command, CommandOutcome, PendingCommands, PASCALS_PER_INHG,
+        SEA_LEVEL_PRESSURE_MAX, SEA_LEVEL_PRESSURE_MIN,
+    },
+    config::{
+        load_sea_level_pressure, save_sea_level_pressure, BasestationConfig, FlightConfig,
+        FlightSetting, DEFAULT_SEA_LEVEL_PRESSURE,
+    },
+    console::{parse, Console, ConsoleCommand, ConsoleMode},
     control_panel::init_control_panel,
-    datalink::ByteSerialize,
-    keypad::init_keypad,
-    telemetry::Telemetry,
-    ui::{text::Text as UiText, ui::Ui},
+    dashboard::{
+        api::{
+            authorize, request_secret, route, sent_json, status_json, ApiAction, ApiError,
+            ApiMethod, WsSessions, API_PREFIX,
+        },
+        FlightLog, LinkMonitor, LinkStatus, INDEX_HTML, INDEX_PATH, LINK_STATUS_PERIOD,
+        TELEMETRY_PATH,
+    },
+    datalink::packet::PacketKind,
+    keypad::{KeypadConfig, KeypadScreen},
+    settings::{init_settings, ConfigScreen},
+    telemetry::{batch::TelemetryBatch, export::LogFormat, ProtocolVersion, Status, Telemetry},
+    ui::{
+        calibration::{CalibrationScreen, TouchCalibration},
+        chart::{Chart, ChartData},
+        gesture::{SwipeDirection, Z_THRESHOLD},
+        readout::Readout,
+        screen::{Navigation, Navigator, Screen},
+        text::Text as UiText,
+        theme::{self, Theme},
+        ui::Ui,
+    },
 };
 
-const STACK_SIZE: usize = 10240;
-const WEB_SERVICES_ON: bool = false;
+/// Area between the readouts and the control panel buttons
+const CHART_BOUNDS: Rectangle = Rectangle::new(Point::new(0, 56), Size::new(320, 156));
+/// Samples kept per chart series, a little over a minute at 5 Hz
+const CHART_HISTORY: usize = 320;
+/// Height of each line of readouts above the chart
+const READOUT_HEIGHT: i32 = 14;
+const READOUT_WIDTH: u32 = 150;
+/// How often drawing time is logged
+const FRAME_STATS_PERIOD: Duration = Duration::from_secs(10);
+/// NVS namespace for basestation settings
+const NVS_NAMESPACE: &str = "basestation";
+/// Samples of a touch held at boot to ignore the stored touch calibration,
+/// taken [`CALIBRATION_HOLD_INTERVAL`] apart
+const CALIBRATION_HOLD_SAMPLES: usize = 20;
+const CALIBRATION_HOLD_INTERVAL: Duration = Duration::from_millis(50);
+/// Wait after the console UART fails to read before trying again
+const CONSOLE_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+/// Longest text message accepted on the telemetry WebSocket
+const WS_MESSAGE_SIZE: usize = 128;
+const JSON_CONTENT: (&str, &str) = ("Content-Type", "application/json");
+
+type Store = Rc<RefCell<EspNvs<NvsDefault>>>;
 
 #[derive(Clone)]
 struct ClientConnection {
@@ -61,44 +103,226 @@ struct ClientConnection {
 #[derive(Clone)]
 struct ClientConnectionList {
     clients: Arc<Mutex<Vec<ClientConnection>>>,
+    link: Arc<Mutex<LinkMonitor>>,
+    log: Arc<Mutex<FlightLog>>,
 }
 
 impl ClientConnectionList {
     fn new() -> Self {
         ClientConnectionList {
             clients: Arc::new(Mutex::new(Vec::new())),
+            link: Arc::new(Mutex::new(LinkMonitor::new())),
+            // until the rocket says how many it records
+            log: Arc::new(Mutex::new(FlightLog::new(
+                FlightConfig::default().recording_capacity as usize,
+            ))),
         }
     }
 
+    /// Keep as many samples as the rocket records.
+    fn set_log_capacity(&self, capacity: usize) {
+        self.log.lock().unwrap().set_capacity(capacity);
+    }
+
+    /// A copy of the log, so it is not locked while sent.
+    fn log_samples(&self) -> Vec<Telemetry> {
+        self.log.lock().unwrap().samples().copied().collect()
+    }
+
+    fn link_status(&self) -> LinkStatus {
+        self.link.lock().unwrap().status(Instant::now())
+    }
+
     fn add_client(&self) -> Receiver<Telemetry> {
         let mut guard = self.clients.lock().unwrap();
         let (sender, receiver) = mpsc::channel();
         guard.push(ClientConnection { sender });
         receiver
     }
+
+    /// Send a sample to every client, dropping clients that have gone away.
+    fn publish(&self, telemetry: Telemetry) {
+        self.link.lock().unwrap().record(&telemetry, Instant::now());
+        self.log.lock().unwrap().push(telemetry);
+
+        let mut guard = self.clients.lock().unwrap();
+
+        let mut i = 0;
+
+        while i < guard.len() {
+            if guard.get(i).unwrap().sender.send(telemetry).is_err() {
+                guard.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+/// The command API, over HTTP and on the telemetry WebSocket.
+struct WebApi {
+    config: BasestationConfig,
+    command_sender: Mutex<Sender<Command>>,
+    client_connections: ClientConnectionList,
+    sessions: Mutex<WsSessions>,
+}
+
+impl WebApi {
+    fn run(&self, action: ApiAction) -> String {
+        match action {
+            ApiAction::Send(command) => {
+                let json = sent_json(&command);
+                self.command_sender.lock().unwrap().send(command).unwrap();
+                json
+            }
+            ApiAction::Status => status_json(&self.client_connections.link_status()),
+            // streamed by serve instead
+            ApiAction::Log(_) => ApiError::NotFound.to_json(),
+        }
+    }
+
+    fn serve(
+        &self,
+        request: Request<&mut EspHttpConnection>,
+        method: ApiMethod,
+    ) -> Result<(), Box<dyn Error>> {
+        let uri = request.uri().to_string();
+        let secret = request_secret(&uri, request.header("Authorization"));
+
+        let (status, json) =
+            match authorize(&self.config, secret.as_deref()).and_then(|()| route(method, &uri)) {
+                Ok(ApiAction::Log(format)) => return self.send_log(request, format),
+                Ok(action) => (200, self.run(action)),
+                Err(e) => (e.status(), e.to_json()),
+            };
+
+        request
+            .into_response(status, None, &[JSON_CONTENT])?
+            .write_all(json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Send the flight log as a file, a sample at a time as it is too big
+    /// to build in memory.
+    fn send_log(
+        &self,
+        request: Request<&mut EspHttpConnection>,
+        format: LogFormat,
+    ) -> Result<(), Box<dyn Error>> {
+        let samples = self.client_connections.log_samples();
+        let disposition = format!("attachment; filename=\"{}\"", format.file_name());
+        let mut response = request.into_response(
+            200,
+            None,
+            &[
+                ("Content-Type", format.content_type()),
+                ("Content-Disposition", &disposition),
+            ],
+        )?;
+
+        let mut chunk = String::new();
+        format.write_start(&mut chunk)?;
+        for (i, telemetry) in samples.iter().enumerate() {
+            format.write_sample(&mut chunk, i, telemetry)?;
+            response.write_all(chunk.as_bytes())?;
+            chunk.clear();
+        }
+        format.write_end(&mut chunk)?;
+        response.write_all(chunk.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Reply to a text message from WebSocket `session`.
+    fn ws_message(&self, session: i32, text: &str) -> String {
+        let request = self
+            .sessions
+            .lock()
+            .unwrap()
+            .message(&self.config, session, text);
+
+        match request {
+            Ok(Some(action)) => self.run(action),
+            Ok(None) => "{\"ok\":true}".to_string(),
+            Err(e) => e.to_json(),
+        }
+    }
+
+    fn end_session(&self, session: i32) {
+        self.sessions.lock().unwrap().end(session);
+    }
 }
 
-fn read_input(uart_driver: &Arc<Mutex<UartDriver>>) -> String {
-    let mut result = String::new();
+/// Feed bytes from the UART to the console, echoing them and passing on the
+/// lines entered.
+fn read_console(
+    uart_rx: UartRxDriver<'static>,
+    uart_tx: &Mutex<UartTxDriver<'static>>,
+    console: &Mutex<Console>,
+    console_sender: Sender<String>,
+) {
+    let mut buf = [0_u8; 64];
 
     loop {
-        let mut buf = [0_u8; 1];
-        let c = {
-            let uart_driver = uart_driver.lock().unwrap();
-            uart_driver.read(&mut buf, NON_BLOCK).unwrap()
+        // wait for a byte, then take whatever else has arrived with it
+        let len = match uart_rx.read(&mut buf[..1], BLOCK) {
+            Ok(len) => len + uart_rx.read(&mut buf[1..], NON_BLOCK).unwrap_or(0),
+            Err(e) => {
+                log::warn!("unable to read console: {}", e);
+                // rather than spin on a UART that keeps failing
+                std::thread::sleep(CONSOLE_RETRY_INTERVAL);
+                continue;
+            }
         };
 
-        let s = std::str::from_utf8(&buf).unwrap();
-
-        if s == "\n" || s == "\r" {
-            return result;
+        let (echo, lines) = console.lock().unwrap().feed(&buf[..len]);
+        write_console(uart_tx, &echo);
+        for line in lines {
+            console_sender.send(line).unwrap();
         }
+    }
+}
 
-        if c != 0 {
-            result.push_str(s);
+fn write_console(uart_tx: &Mutex<UartTxDriver<'static>>, text: &str) {
+    let mut bytes = text.as_bytes();
+
+    while !bytes.is_empty() {
+        match uart_tx.lock().unwrap().write(bytes) {
+            Ok(written) => bytes = &bytes[written..],
+            Err(e) => {
+                log::warn!("unable to write console: {}", e);
+                return;
+            }
         }
+    }
+}
+
+/// Write the frames dashboard clients are sent to the console, for a program
+/// reading it in machine mode.
+fn forward_telemetry(
+    client_connections: &ClientConnectionList,
+    uart_tx: &Mutex<UartTxDriver<'static>>,
+    console: &Mutex<Console>,
+) {
+    let telemetry_receiver = client_connections.add_client();
+    let mut link_status_sent: Option<Instant> = None;
 
-        std::thread::sleep(Duration::from_millis(50));
+    loop {
+        let frame = if link_status_sent.map_or(true, |sent| sent.elapsed() >= LINK_STATUS_PERIOD) {
+            link_status_sent = Some(Instant::now());
+            client_connections.link_status().to_frame()
+        } else {
+            match telemetry_receiver.recv_timeout(Duration::from_millis(50)) {
+                Ok(telemetry) => telemetry.to_frame(ProtocolVersion::Extended),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        };
+
+        let line = console.lock().unwrap().frame(&frame);
+        if let Some(line) = line {
+            write_console(uart_tx, &line);
+        }
     }
 }
 
@@ -115,37 +339,331 @@ fn make_uart_driver(uart0: UART0, gpio1: Gpio1, gpio3: Gpio3) -> UartDriver<'sta
     .unwrap()
 }
 
-fn draw_telemetry(telemetry: &Telemetry, display: &mut CydDisplay) {
-    let style = MonoTextStyle::new(&FONT_6X9, Rgb565::GREEN);
-    Rectangle::new((25, 0).into(), Size::new(80, 13))
-        .into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
-        .draw(display)
-        .unwrap();
-    let text = format!("Alt: {:.2}", telemetry.altitude);
-    Text::new(&text, Point::new(5, 12), style)
-        .draw(display)
-        .map_err(|_| Box::<dyn Error>::from("draw hello"))
-        .unwrap();
+/// Latest telemetry, shown above the chart.
+struct TelemetryReadouts {
+    altitude: Rc<Cell<Option<f32>>>,
+    battery_voltage: Rc<Cell<Option<f32>>>,
+    pressure: Rc<Cell<Option<f32>>>,
+    maximum_altitude: Rc<Cell<Option<f32>>>,
+    vertical_velocity: Rc<Cell<Option<f32>>>,
+    phase: Rc<RefCell<String>>,
+    flags: Rc<RefCell<String>>,
+    psl: Rc<RefCell<String>>,
+}
 
-    Rectangle::new((25, 14).into(), Size::new(50, 13))
-        .into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
-        .draw(display)
-        .unwrap();
-    let text = format!(" V+: {:.2}", telemetry.battery_voltage);
-    Text::new(&text, Point::new(5, 26), style)
-        .draw(display)
-        .map_err(|_| Box::<dyn Error>::from("draw hello"))
-        .unwrap();
+impl TelemetryReadouts {
+    fn new(ui: &mut Ui<CydDisplay>) -> Self {
+        let mut readout = |column: i32, row: i32, label, unit, precision| {
+            let readout = Readout::new(
+                Rectangle::new(
+                    Point::new(column, row * READOUT_HEIGHT),
+                    Size::new(READOUT_WIDTH, READOUT_HEIGHT as u32 - 1),
+                ),
+                label,
+                unit,
+                precision,
+            );
+            let value = readout.value_ref();
+            ui.add_element(Box::new(readout));
+            value
+        };
 
-    Rectangle::new((25, 28).into(), Size::new(100, 13))
-        .into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
-        .draw(display)
-        .unwrap();
-    let text = format!("Prs: {:.2}", telemetry.pressure);
-    Text::new(&text, Point::new(5, 40), style)
-        .draw(display)
-        .map_err(|_| Box::<dyn Error>::from("draw hello"))
-        .unwrap();
+        let altitude = readout(5, 0, "ALT", "ft", 2);
+        let battery_voltage = readout(5, 1, "V+", "V", 2);
+        let pressure = readout(5, 2, "PRS", "Pa", 0);
+        let maximum_altitude = readout(170, 0, "MAX", "ft", 2);
+        let vertical_velocity = readout(170, 1, "VEL", "ft/s", 2);
+
+        // flight state, only sent by extended telemetry
+        let mut text = |column: i32, row: i32| {
+            let text = UiText::new(
+                String::new(),
+                Point::new(column, (row + 1) * READOUT_HEIGHT - 2),
+            );
+            let value = text.text_ref();
+            ui.add_element(Box::new(text));
+            value
+        };
+
+        Self {
+            altitude,
+            battery_voltage,
+            pressure,
+            maximum_altitude,
+            vertical_velocity,
+            phase: text(170, 2),
+            flags: text(170, 3),
+            psl: text(5, 3),
+        }
+    }
+
+    fn show(&self, telemetry: &Telemetry) {
+        self.altitude.set(Some(telemetry.altitude));
+        self.battery_voltage.set(Some(telemetry.battery_voltage));
+        self.pressure.set(Some(telemetry.pressure));
+        self.maximum_altitude.set(Some(telemetry.maximum_altitude));
+        self.vertical_velocity
+            .set(Some(telemetry.vertical_velocity));
+
+        *self.phase.borrow_mut() = format!("{} #{}", telemetry.phase.label(), telemetry.sequence);
+
+        let status = telemetry.status;
+        *self.flags.borrow_mut() = [
+            (Status::CHARGING, "CHG"),
+            (Status::LOW_BATTERY, "LOW"),
+            (Status::SENSOR_FAULT, "FLT"),
+        ]
+        .iter()
+        .filter(|(flag, _)| status.contains(*flag))
+        .map(|(_, label)| *label)
+        .collect::<Vec<_>>()
+        .join(" ");
+    }
+
+    /// Sea level pressure here and on the rocket, both in inHg.
+    fn show_psl(&self, psl: f64, rocket_psl: RocketPsl) {
+        let rocket = match rocket_psl {
+            RocketPsl::Unknown => "---".to_string(),
+            RocketPsl::Pending => "...".to_string(),
+            RocketPsl::Set(pascals) => format!("{:.2}", pascals as f64 / PASCALS_PER_INHG),
+            RocketPsl::Rejected(pascals) => {
+                format!("{:.2} REJ", pascals as f64 / PASCALS_PER_INHG)
+            }
+            RocketPsl::NoAck => "NO ACK".to_string(),
+        };
+
+        *self.psl.borrow_mut() = format!("PSL {:.2} RKT {}", psl / PASCALS_PER_INHG, rocket);
+    }
+}
+
+/// The rocket's sea level pressure, as far as the basestation knows.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum RocketPsl {
+    Unknown,
+    /// Sent, waiting for an ack
+    Pending,
+    /// In use by the rocket, in Pa
+    Set(f32),
+    /// Refused by the rocket, which is still using this, in Pa
+    Rejected(f32),
+    NoAck,
+}
+
+impl RocketPsl {
+    /// What `outcome` says about the rocket's pressure, if anything.
+    fn from_outcome(outcome: &CommandOutcome) -> Option<Self> {
+        match outcome {
+            CommandOutcome::Acked(Command::SetSeaLevelPressure(_), ack)
+            | CommandOutcome::Acked(Command::SetConfig(FlightSetting::SeaLevelPressure, _), ack) => {
+                Some(match ack.status {
+                    AckStatus::Accepted => RocketPsl::Set(ack.value),
+                    AckStatus::Rejected => RocketPsl::Rejected(ack.value),
+                })
+            }
+            CommandOutcome::Acked(Command::GetConfig(FlightSetting::SeaLevelPressure), ack) => {
+                Some(RocketPsl::Set(ack.value))
+            }
+            CommandOutcome::TimedOut(
+                Command::SetSeaLevelPressure(_)
+                | Command::SetConfig(FlightSetting::SeaLevelPressure, _),
+            ) => Some(RocketPsl::NoAck),
+            // asked again once telemetry shows the link is up
+            CommandOutcome::TimedOut(Command::GetConfig(FlightSetting::SeaLevelPressure)) => {
+                Some(RocketPsl::Unknown)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Ask the rocket for its sea level pressure, unless it is known or asked for.
+fn query_rocket_psl(rocket_psl: &Cell<RocketPsl>, command_sender: &Sender<Command>) {
+    if rocket_psl.get() == RocketPsl::Unknown {
+        rocket_psl.set(RocketPsl::Pending);
+        command_sender
+            .send(Command::GetConfig(FlightSetting::SeaLevelPressure))
+            .unwrap();
+    }
+}
+
+/// Color the chart series to suit the selected theme.
+fn apply_theme(chart_data: &RefCell<ChartData>) {
+    let mut chart_data = chart_data.borrow_mut();
+    let series = chart_data.series().len();
+
+    for (i, color) in theme::current().series.iter().enumerate().take(series) {
+        chart_data.set_color(i, *color);
+    }
+}
+
+/// Telemetry readouts, control panel and altitude chart.
+struct HomeScreen {
+    command_sender: Sender<Command>,
+    telemetry: Rc<Cell<Option<Telemetry>>>,
+    readouts: Option<TelemetryReadouts>,
+    chart_data: Rc<RefCell<ChartData>>,
+    psl: Rc<RefCell<f64>>,
+    rocket_psl: Rc<Cell<RocketPsl>>,
+    config: Rc<RefCell<BasestationConfig>>,
+    store: Store,
+}
+
+impl HomeScreen {
+    fn settings(&self) -> SettingsScreen {
+        SettingsScreen {
+            chart_data: self.chart_data.clone(),
+            config: self.config.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+impl Screen<CydDisplay> for HomeScreen {
+    fn build(&mut self, ui: &mut Ui<CydDisplay>, navigation: &Navigation<CydDisplay>) {
+        self.readouts = Some(TelemetryReadouts::new(ui));
+
+        let chart_data = self.chart_data.clone();
+        let clear_navigation = navigation.clone();
+        let psl_screen = PslScreen {
+            psl: self.psl.clone(),
+            rocket_psl: self.rocket_psl.clone(),
+            command_sender: self.command_sender.clone(),
+            store: self.store.clone(),
+        };
+        let psl_navigation = navigation.clone();
+        let settings = self.settings();
+        let settings_navigation = navigation.clone();
+
+        init_control_panel(
+            self.command_sender.clone(),
+            ui,
+            navigation,
+            Box::new(move || {
+                chart_data.borrow_mut().clear();
+                clear_navigation.redraw();
+            }),
+            Box::new(move || psl_navigation.push(psl_screen.keypad())),
+            Box::new(move || settings_navigation.push(settings.clone())),
+        );
+
+        ui.add_element(Box::new(Chart::new(CHART_BOUNDS, self.chart_data.clone())));
+    }
+
+    fn update(&mut self, _ui: &mut Ui<CydDisplay>) {
+        if let Some(readouts) = &self.readouts {
+            if let Some(telemetry) = self.telemetry.get() {
+                readouts.show(&telemetry);
+            }
+            readouts.show_psl(*self.psl.borrow(), self.rocket_psl.get());
+        }
+    }
+
+    fn on_swipe(&mut self, direction: SwipeDirection, navigation: &Navigation<CydDisplay>) {
+        if direction == SwipeDirection::Left {
+            navigation.push(self.settings());
+        }
+    }
+}
+
+/// Sea level pressure, entered in inHg as on weather reports, saved here and
+/// sent to the rocket.
+#[derive(Clone)]
+struct PslScreen {
+    psl: Rc<RefCell<f64>>,
+    rocket_psl: Rc<Cell<RocketPsl>>,
+    command_sender: Sender<Command>,
+    store: Store,
+}
+
+impl PslScreen {
+    fn keypad(&self) -> KeypadScreen {
+        let config = KeypadConfig::new("SEA LEVEL PRESSURE", "inHg")
+            .range(
+                SEA_LEVEL_PRESSURE_MIN as f64 / PASCALS_PER_INHG,
+                SEA_LEVEL_PRESSURE_MAX as f64 / PASCALS_PER_INHG,
+            )
+            .precision(2)
+            .initial(*self.psl.borrow() / PASCALS_PER_INHG);
+        let screen = self.clone();
+
+        KeypadScreen::new(
+            config,
+            Box::new(move |inhg| {
+                let pascals = inhg * PASCALS_PER_INHG;
+                log::info!("setting PSL to {:.2} inHg, {:.0} Pa", inhg, pascals);
+
+                *screen.psl.borrow_mut() = pascals;
+                if let Err(e) = save_sea_level_pressure(&mut *screen.store.borrow_mut(), pascals) {
+                    log::warn!("unable to save PSL: {:?}", e);
+                }
+                screen.rocket_psl.set(RocketPsl::Pending);
+                screen
+                    .command_sender
+                    .send(Command::SetSeaLevelPressure(pascals as f32))
+                    .unwrap();
+            }),
+        )
+    }
+}
+
+/// Touch calibration, saved to NVS when done.
+fn calibration_screen(store: &Store) -> CalibrationScreen {
+    let store = store.clone();
+
+    CalibrationScreen::new(
+        320,
+        240,
+        Box::new(move |calibration| {
+            if let Err(e) = calibration.save(&mut *store.borrow_mut()) {
+                log::error!("unable to save touch calibration: {:?}", e);
+            }
+        }),
+    )
+}
+
+/// Theme selection, touch calibration and the basestation config, saved
+/// to NVS.
+#[derive(Clone)]
+struct SettingsScreen {
+    chart_data: Rc<RefCell<ChartData>>,
+    config: Rc<RefCell<BasestationConfig>>,
+    store: Store,
+}
+
+impl Screen<CydDisplay> for SettingsScreen {
+    fn build(&mut self, ui: &mut Ui<CydDisplay>, navigation: &Navigation<CydDisplay>) {
+        let chart_data = self.chart_data.clone();
+        let store = self.store.clone();
+        let theme_navigation = navigation.clone();
+        let calibrate_store = self.store.clone();
+        let calibrate_navigation = navigation.clone();
+        let (config, config_store) = (self.config.clone(), self.store.clone());
+        let config_navigation = navigation.clone();
+        let exit_navigation = navigation.clone();
+
+        init_settings(
+            ui,
+            Box::new(move |theme: &'static Theme| {
+                log::info!("theme {}", theme.name);
+                if let Err(e) = theme::save(&mut *store.borrow_mut()) {
+                    log::error!("unable to save theme: {:?}", e);
+                }
+                apply_theme(&chart_data);
+                theme_navigation.redraw();
+            }),
+            Box::new(move || calibrate_navigation.push(calibration_screen(&calibrate_store))),
+            Box::new(move || {
+                config_navigation.push(ConfigScreen::new(config.clone(), config_store.clone()))
+            }),
+            Box::new(move || exit_navigation.pop()),
+        );
+    }
+
+    fn on_swipe(&mut self, direction: SwipeDirection, navigation: &Navigation<CydDisplay>) {
+        if direction == SwipeDirection::Right {
+            navigation.pop();
+        }
+    }
 }
 
 fn main() {
@@ -155,56 +673,147 @@ fn main() {
     esp_idf_svc::log::EspLogger::initialize_default();
 
     let (command_sender, command_receiver) = mpsc::channel();
+    let (outcome_sender, outcome_receiver) = mpsc::channel();
+    let (console_sender, console_receiver) = mpsc::channel();
 
     let peripherals = Peripherals::take().unwrap();
 
     let (mut cyd, peripherals) = ez_cyd_rs::Cyd::new(peripherals).unwrap();
 
-    let touch_calibration = cyd.calibrate_touch();
-
-    // let uart_driver = make_uart_driver(
-    //     peripherals.uart0,
-    //     peripherals.pins.gpio1,
-    //     peripherals.pins.gpio3,
-    // );
-    // let uart_driver = Arc::new(Mutex::new(uart_driver));
-    // spawn thread to read commands from UART
-    // {
-    //     let uart_driver = uart_driver.clone();
-    //     std::thread::spawn(move || loop {
-    //         let s = read_input(&uart_driver);
-    //         command_sender.send(s).unwrap();
-    //     });
-    // }
+    let uart_driver = make_uart_driver(
+        peripherals.uart0,
+        peripherals.pins.gpio1,
+        peripherals.pins.gpio3,
+    );
+    let (uart_tx, uart_rx) = uart_driver.into_split();
+    let uart_tx = Arc::new(Mutex::new(uart_tx));
+    let console = Arc::new(Mutex::new(Console::new()));
+    write_console(&uart_tx, &console.lock().unwrap().banner());
+    // spawn thread to edit console lines from UART, run on the main loop
+    {
+        let uart_tx = uart_tx.clone();
+        let console = console.clone();
+        std::thread::spawn(move || read_console(uart_rx, &uart_tx, &console, console_sender));
+    }
 
     let client_connections = ClientConnectionList::new();
+    // in machine mode the console also carries what dashboard clients see
+    {
+        let uart_tx = uart_tx.clone();
+        let console = console.clone();
+        let client_connections = client_connections.clone();
+        std::thread::spawn(move || forward_telemetry(&client_connections, &uart_tx, &console));
+    }
+
+    let nvs = EspDefaultNvsPartition::take().unwrap();
+    let store: Store = Rc::new(RefCell::new(
+        EspNvs::new(nvs.clone(), NVS_NAMESPACE, true).unwrap(),
+    ));
 
-    let http_server = wifi_thread(
+    if let Err(e) = theme::load(&*store.borrow()) {
+        log::warn!("unable to load theme: {:?}", e);
+    }
+
+    let config = match BasestationConfig::load(&*store.borrow()) {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => {
+            log::warn!("unable to load config, using defaults: {:?}", e);
+            BasestationConfig::default()
+        }
+    };
+
+    let mut http_server = wifi_thread(
         peripherals.modem,
+        nvs,
+        &config,
         client_connections.clone(),
         command_receiver,
+        outcome_sender,
     );
 
     let draw_client = client_connections.add_client();
 
-    if WEB_SERVICES_ON {
-        http_server
+    // only there with web services on, and kept until the end of main as
+    // dropping the server stops it
+    if let Some(server) = http_server.as_mut() {
+        let api = Arc::new(WebApi {
+            config: config.clone(),
+            command_sender: Mutex::new(command_sender.clone()),
+            client_connections: client_connections.clone(),
+            sessions: Mutex::new(WsSessions::new()),
+        });
+        let api_path = format!("{}*", API_PREFIX);
+        let (get_api, post_api, ws_api) = (api.clone(), api.clone(), api);
+
+        server
+            .fn_handler(INDEX_PATH, Method::Get, |request| {
+                request
+                    .into_response(200, None, &[("Content-Type", "text/html; charset=utf-8")])?
+                    .write_all(INDEX_HTML.as_bytes())?;
+                Ok::<(), Box<dyn Error>>(())
+            })
             .unwrap()
-            .ws_handler("/ws/telemetry", move |ws| {
-                if ws.is_new() {
+            .fn_handler(&api_path, Method::Get, move |request| {
+                get_api.serve(request, ApiMethod::Get)
+            })
+            .unwrap()
+            .fn_handler(&api_path, Method::Post, move |request| {
+                post_api.serve(request, ApiMethod::Post)
+            })
+            .unwrap()
+            .ws_handler(TELEMETRY_PATH, move |ws| {
+                if ws.is_closed() {
+                    ws_api.end_session(ws.session());
+                } else if !ws.is_new() {
+                    // the length first, as a frame too long for the buffer
+                    // fails to receive
+                    let (frame_type, len) = ws.recv(&mut [])?;
+                    if len > WS_MESSAGE_SIZE {
+                        let reply = ApiError::BadRequest("message too long").to_json();
+                        ws.send(FrameType::Text(false), reply.as_bytes())?;
+                        // the rest of the frame can't be skipped, so the
+                        // connection ends
+                        ws.send(FrameType::Close, &[])?;
+                        return Err(EspError::from_infallible::<ESP_ERR_INVALID_SIZE>());
+                    }
+
+                    let mut buffer = [0_u8; WS_MESSAGE_SIZE];
+                    ws.recv(&mut buffer)?;
+                    let reply = match frame_type {
+                        FrameType::Text(_) => match std::str::from_utf8(&buffer[..len]) {
+                            Ok(text) => {
+                                ws_api.ws_message(ws.session(), text.trim_end_matches('\0'))
+                            }
+                            Err(_) => ApiError::BadRequest("not UTF-8").to_json(),
+                        },
+                        _ => return Ok(()),
+                    };
+                    ws.send(FrameType::Text(false), reply.as_bytes())?;
+                } else {
                     println!("new ws connection");
                     let mut ws = ws.create_detached_sender().unwrap();
-                    let telemetry_receiver = client_connections.clone().add_client();
+                    let client_connections = client_connections.clone();
+                    let telemetry_receiver = client_connections.add_client();
                     std::thread::spawn(move || {
+                        let mut link_status_sent: Option<Instant> = None;
+
                         loop {
                             if ws.is_closed() {
                                 break;
                             }
 
+                            if link_status_sent
+                                .map_or(true, |sent| sent.elapsed() >= LINK_STATUS_PERIOD)
+                            {
+                                let frame = client_connections.link_status().to_frame();
+                                if ws.send(FrameType::Binary(false), &frame).is_err() {
+                                    break;
+                                }
+                                link_status_sent = Some(Instant::now());
+                            }
+
                             let telemetry =
                                 telemetry_receiver.recv_timeout(Duration::from_millis(50));
-                            let mut buffer = [0u8; std::mem::size_of::<Telemetry>()];
-
                             if ws.is_closed() {
                                 break;
                             }
@@ -221,11 +830,9 @@ fn main() {
                                 Ok(telemetry) => telemetry,
                             };
 
-                            telemetry
-                                .as_bytes(&mut buffer)
-                                .expect("serialize should work");
+                            let frame = telemetry.to_frame(ProtocolVersion::Extended);
 
-                            if ws.send(FrameType::Binary(false), &buffer).is_err() {
+                            if ws.send(FrameType::Binary(false), &frame).is_err() {
                                 break;
                             }
                         }
@@ -238,100 +845,143 @@ fn main() {
     }
 
     cyd.display
-        .clear(Rgb565::BLACK)
+        .clear(theme::current().background)
         .map_err(|_| Box::<dyn Error>::from("clear display"))
         .unwrap();
 
-    let mut chart_x = 0;
-    let mut chart_y = 0;
-
-    let mut ui = Ui::new(320, 240);
-
-    ui.touch_calibration(touch_calibration.unwrap());
-    ui.add_element(Box::new(UiText::new("0".to_string(), Point::new(0, 0))));
+    let chart_data = Rc::new(RefCell::new(ChartData::new(CHART_HISTORY)));
+    let series_colors = theme::current().series;
+    let altitude_series = chart_data
+        .borrow_mut()
+        .add_series("ALT", series_colors[0], true);
+    let velocity_series = chart_data
+        .borrow_mut()
+        .add_series("VEL", series_colors[1], false);
+
+    let psl = match load_sea_level_pressure(&*store.borrow()) {
+        Ok(psl) => psl.unwrap_or(DEFAULT_SEA_LEVEL_PRESSURE),
+        Err(e) => {
+            log::warn!("unable to load PSL, using the default: {:?}", e);
+            DEFAULT_SEA_LEVEL_PRESSURE
+        }
+    };
+    let psl = Rc::new(RefCell::new(psl));
+    // what the rocket uses is asked for now, and again whenever it is
+    // unknown while telemetry is arriving
+    let rocket_psl = Rc::new(Cell::new(RocketPsl::Unknown));
+    query_rocket_psl(&rocket_psl, &command_sender);
+    // and how many samples it records, for the log downloaded
+    let mut recording_capacity_asked = false;
+
+    let latest_telemetry = Rc::new(Cell::new(None));
+    let config = Rc::new(RefCell::new(config));
+
+    let started = Instant::now();
+    let mut navigator = Navigator::new(320, 240);
+    navigator.navigation().push(HomeScreen {
+        command_sender: command_sender.clone(),
+        telemetry: latest_telemetry.clone(),
+        readouts: None,
+        chart_data: chart_data.clone(),
+        psl: psl.clone(),
+        rocket_psl: rocket_psl.clone(),
+        config: config.clone(),
+        store: store.clone(),
+    });
 
-    let (mut clear_flag, mut psl_flag) = init_control_panel(command_sender.clone(), &mut ui);
-    let psl = Rc::new(RefCell::new(101230.0));
+    // calibrate on first boot, after which it can be redone from settings,
+    // or by holding a touch at boot should a bad calibration make settings
+    // unreachable
+    let held = (0..CALIBRATION_HOLD_SAMPLES).all(|_| {
+        std::thread::sleep(CALIBRATION_HOLD_INTERVAL);
+        cyd.try_touch().is_ok_and(|touch| touch.2 >= Z_THRESHOLD)
+    });
+    if held {
+        log::warn!("touch held at boot, ignoring touch calibration");
+        navigator.navigation().push(calibration_screen(&store));
+    } else {
+        match TouchCalibration::load(&*store.borrow()) {
+            Ok(Some(touch_calibration)) => navigator.touch_calibration(touch_calibration),
+            Ok(None) => navigator.navigation().push(calibration_screen(&store)),
+            Err(e) => {
+                log::warn!("unable to load touch calibration: {:?}", e);
+                navigator.navigation().push(calibration_screen(&store));
+            }
+        }
+    }
 
-    let psl_set_flag = Rc::new(RefCell::new(false));
+    let mut frame_stats_taken = Instant::now();
 
     loop {
-        if psl_flag.load(Ordering::Relaxed) {
-            // show psl keypad
-            ui.clear();
-            let psl_set_flag1 = psl_set_flag.clone();
-            let psl_set_flag2 = psl_set_flag.clone();
-            let psl = psl.clone();
-            init_keypad(
-                &mut ui,
-                Box::new(move |psl_str: &str| {
-                    *psl_set_flag1.borrow_mut() = true;
-                    if let Ok(psl_val) = f64::from_str(psl_str) {
-                        println!("setting PSL to {}", psl_str);
-                        *psl.borrow_mut() = psl_val;
-                    } else {
-                        println!("PSL Format error");
-                    }
-                }),
-                Box::new(move || {
-                    *psl_set_flag2.borrow_mut() = true;
-                }),
-            );
-            ui.dirty_all();
-            cyd.display.clear(Rgb565::BLACK).unwrap();
+        let touch = cyd.try_touch().unwrap();
+        navigator.handle_touch(
+            (touch.0, touch.1, touch.2),
+            started.elapsed().as_millis() as u32,
+        );
+
+        while let Ok(line) = console_receiver.try_recv() {
+            let reply = run_console(&line, &console, &config, &store, &command_sender);
+            write_console(&uart_tx, &reply);
         }
 
-        if *psl_set_flag.borrow() {
-            cyd.display.clear(Rgb565::BLACK).unwrap();
-            ui.dirty_all();
-            ui.clear();
-            let (f1, f2) = init_control_panel(command_sender.clone(), &mut ui);
-            clear_flag = f1;
-            psl_flag = f2;
+        while let Ok(outcome) = outcome_receiver.try_recv() {
+            log::info!("command outcome: {:?}", outcome);
+            if let Some(state) = RocketPsl::from_outcome(&outcome) {
+                rocket_psl.set(state);
+            }
+            match &outcome {
+                CommandOutcome::Acked(
+                    Command::GetConfig(FlightSetting::RecordingCapacity)
+                    | Command::SetConfig(FlightSetting::RecordingCapacity, _),
+                    ack,
+                ) if ack.status == AckStatus::Accepted => {
+                    client_connections.set_log_capacity(ack.value as usize)
+                }
+                CommandOutcome::TimedOut(Command::GetConfig(FlightSetting::RecordingCapacity)) => {
+                    recording_capacity_asked = false
+                }
+                _ => (),
+            }
+            if let Some(text) = rocket_outcome_text(&outcome) {
+                write_console(&uart_tx, &console.lock().unwrap().event(&text));
+            }
         }
 
-        if clear_flag.load(Ordering::Relaxed) {
-            cyd.display.clear(Rgb565::BLACK).unwrap();
-            ui.dirty_all();
+        navigator.update();
+        navigator.draw(&mut cyd.display);
+
+        if frame_stats_taken.elapsed() >= FRAME_STATS_PERIOD {
+            let stats = navigator.take_frame_stats();
+            log::debug!(
+                "{} frames, {} regions, {} px, avg {:?}, max {:?}",
+                stats.frames,
+                stats.regions,
+                stats.pixels,
+                stats.average(),
+                stats.max
+            );
+            frame_stats_taken = Instant::now();
         }
 
-        psl_flag.store(false, Ordering::Relaxed);
-        clear_flag.store(false, Ordering::Relaxed);
-        *psl_set_flag.borrow_mut() = false;
-
-        let touch = cyd.try_touch().unwrap();
-        ui.handle_touch((touch.0, touch.1, touch.2));
-
-        ui.draw(&mut cyd.display);
-
         loop {
             let telemetry = draw_client.recv_timeout(Duration::from_millis(10));
 
-            // Create text style
-            if let Ok(mut telemetry) = telemetry {
-                let altitude = calc_altitude(telemetry.pressure as f64, *psl.borrow() as f64);
-
-                telemetry.altitude = altitude as f32;
-
-                draw_telemetry(&telemetry, &mut cyd.display);
-                let altitude = altitude / 2.0;
-                Line::new(
-                    Point::new(chart_x, 210 - altitude as i32),
-                    Point::new(chart_x, 210 - chart_y),
-                )
-                .into_styled(PrimitiveStyle::with_stroke(Rgb565::GREEN, 1))
-                .draw(&mut cyd.display)
-                .map_err(|_| Box::<dyn Error>::from("draw chart"))
-                .unwrap();
-
-                chart_x = (chart_x + 1) % 320;
-                chart_y = altitude as i32;
-
-                Line::new(Point::new(chart_x, 210), Point::new(chart_x, 60))
-                    .into_styled(PrimitiveStyle::with_stroke(Rgb565::BLACK, 1))
-                    .draw(&mut cyd.display)
-                    .map_err(|_| Box::<dyn Error>::from("draw chart"))
-                    .unwrap();
+            if let Ok(telemetry) = telemetry {
+                // altitudes are left as the rocket worked them out, from the
+                // pressure shown as RKT, so ALT and MAX share one reference and
+                // match what the rocket records
+                latest_telemetry.set(Some(telemetry));
+                query_rocket_psl(&rocket_psl, &command_sender);
+                if !recording_capacity_asked {
+                    recording_capacity_asked = true;
+                    command_sender
+                        .send(Command::GetConfig(FlightSetting::RecordingCapacity))
+                        .unwrap();
+                }
+
+                let mut chart_data = chart_data.borrow_mut();
+                chart_data.push(altitude_series, telemetry.time, telemetry.altitude);
+                chart_data.push(velocity_series, telemetry.time, telemetry.vertical_velocity);
             } else {
                 break;
             }
@@ -339,13 +989,95 @@ fn main() {
     }
 }
 
+/// Run a line from the serial console, returning the reply to write:
+/// `config` commands are run here, and rocket commands join the touch UI's
+/// on their way to the rocket.
+fn run_console(
+    line: &str,
+    console: &Mutex<Console>,
+    config: &RefCell<BasestationConfig>,
+    store: &Store,
+    command_sender: &Sender<Command>,
+) -> String {
+    let result = match parse(line) {
+        Ok(ConsoleCommand::Empty) => Ok(String::new()),
+        Ok(ConsoleCommand::Help(text)) => Ok(text),
+        Ok(ConsoleCommand::Mode(mode)) => {
+            console.lock().unwrap().set_mode(mode);
+            Ok(match mode {
+                ConsoleMode::Human => "human mode".to_string(),
+                ConsoleMode::Machine => "machine mode".to_string(),
+            })
+        }
+        Ok(ConsoleCommand::Config(args)) => run_config(args, config, store),
+        Ok(ConsoleCommand::Send(command)) => {
+            command_sender.send(command).unwrap();
+            Ok("sent".to_string())
+        }
+        Err(e) => Err(e),
+    };
+
+    console.lock().unwrap().reply(result)
+}
+
+/// Run a `config` console command, saving any change.
+fn run_config(
+    args: &str,
+    config: &RefCell<BasestationConfig>,
+    store: &Store,
+) -> Result<String, String> {
+    let before = config.borrow().clone();
+    let reply = config
+        .borrow_mut()
+        .console(args)
+        .map_err(|e| e.describe())?;
+
+    if *config.borrow() == before {
+        return Ok(reply);
+    }
+    match config.borrow().save(&mut *store.borrow_mut()) {
+        Ok(()) => Ok(format!("{}\nsaved, restart to apply", reply)),
+        Err(e) => Err(format!("unable to save config: {:?}", e)),
+    }
+}
+
+/// Report the answer to a `rocket` console command.
+fn rocket_outcome_text(outcome: &CommandOutcome) -> Option<String> {
+    match outcome {
+        CommandOutcome::Acked(Command::GetConfig(setting), ack) => {
+            Some(format!("rocket {} = {}", setting.name(), ack.value))
+        }
+        CommandOutcome::Acked(Command::SetConfig(setting, value), ack) => match ack.status {
+            AckStatus::Accepted if setting.needs_restart() => Some(format!(
+                "rocket {} = {}, restart the rocket to apply",
+                setting.name(),
+                ack.value
+            )),
+            AckStatus::Accepted => Some(format!("rocket {} = {}", setting.name(), ack.value)),
+            AckStatus::Rejected => Some(format!(
+                "rocket rejected {} of {}, keeping {}",
+                setting.name(),
+                value,
+                ack.value
+            )),
+        },
+        CommandOutcome::Acked(Command::FactoryReset, _) => Some("rocket factory reset".to_string()),
+        CommandOutcome::TimedOut(
+            command @ (Command::GetConfig(_) | Command::SetConfig(..) | Command::FactoryReset),
+        ) => Some(format!("no answer from rocket to {:?}", command)),
+        _ => None,
+    }
+}
+
 fn wifi_thread(
     modem: esp_idf_hal::modem::Modem,
+    nvs: EspDefaultNvsPartition,
+    config: &BasestationConfig,
     client_connections: ClientConnectionList,
-    command_receiver: Receiver<String>,
+    command_receiver: Receiver<Command>,
+    outcome_sender: Sender<CommandOutcome>,
 ) -> Option<EspHttpServer<'static>> {
     let sys_loop = EspSystemEventLoop::take().unwrap();
-    let nvs = EspDefaultNvsPartition::take().unwrap();
     let esp_wifi = EspWifi::new(modem, sys_loop.clone(), Some(nvs)).unwrap();
 
     let mut wifi = BlockingWifi::wrap(esp_wifi, sys_loop).unwrap();
@@ -355,12 +1087,17 @@ fn wifi_thread(
         AccessPointConfiguration::default(),
     );
 
-    client_config.channel = Some(1);
+    client_config.channel = Some(config.channel);
 
-    ap_config.ssid = heapless::String::<32>::from_str("omega9").unwrap();
-    ap_config.password = heapless::String::<64>::from_str("knock it off").unwrap();
-    ap_config.channel = 1;
-    ap_config.auth_method = AuthMethod::WPA3Personal;
+    // lengths are checked when the config is set
+    ap_config.ssid = heapless::String::<32>::from_str(&config.ap_ssid).unwrap();
+    ap_config.password = heapless::String::<64>::from_str(&config.ap_password).unwrap();
+    ap_config.channel = config.channel;
+    ap_config.auth_method = if config.ap_password.is_empty() {
+        AuthMethod::None
+    } else {
+        AuthMethod::WPA3Personal
+    };
     ap_config.ssid_hidden = false;
 
     wifi.set_configuration(&Configuration::Mixed(
@@ -384,39 +1121,61 @@ fn wifi_thread(
 
     let espnow = espnow.unwrap();
 
+    // commands waiting for an ack, answered from the receive callback
+    let pending = Arc::new(Mutex::new(PendingCommands::new()));
+    let acked = pending.clone();
+    let ack_sender = outcome_sender.clone();
+
     espnow
         .register_recv_cb(move |_mac: &[u8], data: &[u8]| {
-            let data = Vec::from(data);
-
-            // only send if we have a listener or
-            let telemetry = Telemetry::from_bytes(&data).unwrap();
-            // log::info!("{:?}", telemetry);
-
-            let mut guard = client_connections.clients.lock().unwrap();
-
-            let mut i = 0;
-
-            while i < guard.len() {
-                if guard
-                    .get(i)
-                    .unwrap()
-                    .sender
-                    .send(telemetry.clone())
-                    .is_err()
-                {
-                    guard.remove(i);
-                } else {
-                    i += 1;
+            let samples: Vec<Telemetry> = match PacketKind::of(data) {
+                Some(PacketKind::Ack) => {
+                    match Ack::from_frame(data) {
+                        Ok(ack) => {
+                            if let Some(outcome) = acked.lock().unwrap().acknowledge(ack) {
+                                ack_sender.send(outcome).ok();
+                            }
+                        }
+                        Err(e) => log::warn!("bad ack: {:?}", e),
+                    }
+                    return;
                 }
+                Some(PacketKind::TelemetryBatch) => match TelemetryBatch::from_bytes(data) {
+                    Ok(batch) => batch.samples().collect(),
+                    Err(e) => {
+                        log::warn!("bad telemetry batch: {:?}", e);
+                        return;
+                    }
+                },
+                // firmware without batching sends one untagged sample per frame
+                Some(PacketKind::Telemetry) | None => match Telemetry::from_frame(data) {
+                    Ok(telemetry) => vec![telemetry],
+                    Err(e) => {
+                        log::warn!("unable to read telemetry: {:?}", e);
+                        return;
+                    }
+                },
+                Some(_) => return,
+            };
+            for telemetry in samples {
+                log::debug!(
+                    "#{} {} alt: {:.2} vel: {:.2} status: {:#04x}",
+                    telemetry.sequence,
+                    telemetry.phase.label(),
+                    telemetry.altitude,
+                    telemetry.vertical_velocity,
+                    telemetry.status.0
+                );
+                client_connections.publish(telemetry);
             }
         })
         .unwrap();
 
-    let peer: [u8; 6] = [0xD4, 0xD4, 0xDA, 0xAA, 0x27, 0x5C];
+    let peer = config.rocket_mac;
 
     let mut peer_info = PeerInfo::default();
 
-    peer_info.channel = 1;
+    peer_info.channel = config.channel;
     peer_info.peer_addr = peer;
     peer_info.encrypt = false;
 
@@ -424,23 +1183,41 @@ fn wifi_thread(
 
     std::thread::spawn(move || {
         let _wifi = wifi;
+        let send = |frame: &[u8]| {
+            if let Err(e) = espnow.send(peer, frame) {
+                log::error!("Failed to send: {}", e);
+            } else {
+                log::info!("Sent {} bytes", frame.len());
+            }
+        };
+
         loop {
-            if let Ok(s) = command_receiver.try_recv() {
-                if s.len() != 0 {
-                    if let Err(e) = espnow.send(peer, s.as_bytes()) {
-                        log::error!("Failed to send: {}", e);
-                    } else {
-                        log::info!("Sent {} bytes", s.len());
-                    }
+            let now = Instant::now();
+
+            if let Ok(command) = command_receiver.try_recv() {
+                let frame = pending.lock().unwrap().send(command, now);
+                if !frame.is_empty() {
+                    send(&frame);
                 }
             }
+
+            let (resend, timed_out) = pending.lock().unwrap().poll(now);
+            for frame in resend {
+                send(&frame);
+            }
+            for outcome in timed_out {
+                outcome_sender.send(outcome).ok();
+            }
+
             std::thread::sleep(Duration::from_millis(63));
         }
     });
 
-    if WEB_SERVICES_ON {
+    if config.web_services {
         let http_server_config = esp_idf_svc::http::server::Configuration {
-            stack_size: STACK_SIZE,
+            stack_size: config.http_stack_size as usize,
+            // for the API's paths
+            uri_match_wildcard: true,
             ..Default::default()
         };
 
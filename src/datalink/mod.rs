@@ -0,0 +1,258 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc::{Receiver, TrySendError},
+        Arc,
+    },
+    time::Duration,
+};
+
+use esp_idf_hal::modem::WifiModemPeripheral;
+use esp_idf_svc::{
+    espnow::{EspNow, PeerInfo},
+    eventloop::EspSystemEventLoop,
+    nvs::EspDefaultNvsPartition,
+    sys::EspError,
+    wifi::{BlockingWifi, ClientConfiguration, Configuration, EspWifi, WifiDeviceId},
+};
+
+pub use rocket_protocol::datalink::{packet, peers, queue, transport, ByteSerialize, MacAddr};
+
+use peers::{PeerTable, PeerUpdate, MAX_PEERS};
+pub use queue::{DataSender, Frame, Priority, QueueError, QueueStats};
+
+/// Capacity of the transmit queue for each priority (control, telemetry, bulk)
+const TX_QUEUE_CAPACITY: [usize; Priority::COUNT] = [8, 16, 32];
+/// Received frames waiting to be handled before new ones are dropped
+const RX_QUEUE_DEPTH: usize = 16;
+/// Consecutive send failures before the wifi driver is restarted
+const MAX_SEND_FAILURES: u32 = 5;
+const WIFI_RETRY_DELAY: Duration = Duration::from_millis(500);
+const TX_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct Datalink {
+    pub command_receiver: Option<Receiver<(MacAddr, Vec<u8>)>>,
+    pub data_sender: DataSender,
+    counters: Arc<LinkCounters>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum DatalinkError {
+    Queue(QueueError),
+    Peer(EspError),
+    Send(EspError),
+    Wifi(EspError),
+}
+
+#[derive(Default)]
+struct LinkCounters {
+    sent: AtomicU32,
+    send_failures: AtomicU32,
+    rx_dropped: AtomicU32,
+    wifi_restarts: AtomicU32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct DatalinkStats {
+    pub queue: QueueStats,
+    pub sent: u32,
+    pub send_failures: u32,
+    pub rx_dropped: u32,
+    pub wifi_restarts: u32,
+}
+
+fn format_mac(mac: &[u8]) -> String {
+    format!(
+        "{:X}:{:X}:{:X}:{:X}:{:X}:{:X}",
+        mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+    )
+}
+
+fn print_mac_addrs(wifi: &BlockingWifi<EspWifi<'_>>) {
+    let ap_mac = wifi
+        .wifi()
+        .get_mac(WifiDeviceId::Ap)
+        .expect("should have ap");
+    let sta_mac = wifi
+        .wifi()
+        .get_mac(WifiDeviceId::Sta)
+        .expect("should have station");
+
+    log::info!("ap mac: {}", format_mac(&ap_mac));
+    log::info!("sta mac: {}", format_mac(&sta_mac));
+}
+
+fn register_peer(espnow: &EspNow, peer_addr: MacAddr) -> Result<(), DatalinkError> {
+    // the driver may still know the peer from before a wifi restart
+    if espnow.peer_exists(peer_addr).map_err(DatalinkError::Peer)? {
+        return Ok(());
+    }
+
+    let mut peer_info = PeerInfo::default();
+    peer_info.peer_addr = peer_addr;
+    espnow.add_peer(peer_info).map_err(DatalinkError::Peer)
+}
+
+/// Register every peer in `peers` afresh after a wifi restart.  The driver
+/// may or may not have kept them, so each is deleted first to leave it in a
+/// known state.
+fn resync_peers(espnow: &EspNow, peers: &mut PeerTable) {
+    for addr in peers.addrs() {
+        // not finding the peer is expected if the driver dropped it
+        let _ = espnow.del_peer(addr);
+        if let Err(e) = register_peer(espnow, addr) {
+            log::error!("Failed to register {}: {:?}", format_mac(&addr), e);
+            peers.remove(addr);
+        }
+    }
+}
+
+fn transmit(espnow: &EspNow, peers: &mut PeerTable, frame: &Frame) -> Result<(), DatalinkError> {
+    let registered = match peers.touch(frame.peer_addr) {
+        PeerUpdate::Known => Ok(()),
+        PeerUpdate::Added => register_peer(espnow, frame.peer_addr),
+        PeerUpdate::Evicted(old) => {
+            log::info!("evicting peer {}", format_mac(&old));
+            espnow
+                .del_peer(old)
+                .map_err(DatalinkError::Peer)
+                .and_then(|_| register_peer(espnow, frame.peer_addr))
+        }
+    };
+
+    if let Err(e) = registered {
+        peers.remove(frame.peer_addr);
+        return Err(e);
+    }
+
+    espnow
+        .send(frame.peer_addr, &frame.data)
+        .map_err(DatalinkError::Send)
+}
+
+impl Datalink {
+    /// Start wifi on `modem` for ESP-NOW, with calibration data kept in `nvs`.
+    pub fn new<M: WifiModemPeripheral + 'static>(modem: M, nvs: EspDefaultNvsPartition) -> Self {
+        let mut wifi = {
+            let sys_loop = EspSystemEventLoop::take().unwrap();
+
+            let wifi = EspWifi::new(modem, sys_loop.clone(), Some(nvs)).unwrap();
+
+            let mut wifi = BlockingWifi::wrap(wifi, sys_loop).expect("Failed to create wifi");
+            let configuration = Configuration::Client(ClientConfiguration::default());
+            wifi.set_configuration(&configuration).unwrap();
+
+            wifi
+        };
+
+        print_mac_addrs(&wifi);
+
+        let counters = Arc::new(LinkCounters::default());
+
+        let (command_sender, command_receiver) = std::sync::mpsc::sync_channel(RX_QUEUE_DEPTH);
+
+        let data_sender = DataSender::new(TX_QUEUE_CAPACITY);
+
+        let espnow = esp_idf_svc::espnow::EspNow::take().unwrap();
+        let rx_counters = counters.clone();
+        espnow
+            .register_recv_cb(move |mac: &[u8], data: &[u8]| {
+                let mut mac_arr = [0u8; 6];
+                mac_arr.copy_from_slice(mac);
+                let mut vec_data = Vec::new();
+                vec_data.extend_from_slice(data);
+                // never block the wifi task; drop the frame if nobody is keeping up
+                match command_sender.try_send((mac_arr, vec_data)) {
+                    Ok(()) => (),
+                    Err(TrySendError::Full(_)) => {
+                        rx_counters.rx_dropped.fetch_add(1, Ordering::Relaxed);
+                        log::warn!("receive queue full, dropping frame");
+                    }
+                    Err(TrySendError::Disconnected(_)) => {
+                        log::error!("receive queue disconnected");
+                    }
+                }
+            })
+            .unwrap();
+
+        let data_receiver = data_sender.clone();
+        let tx_counters = counters.clone();
+
+        std::thread::spawn(move || {
+            let mut peers = PeerTable::new(MAX_PEERS);
+            let mut started = false;
+            let mut failures = 0;
+
+            loop {
+                if !started {
+                    if let Err(e) = wifi.start() {
+                        log::error!("Failed to start wifi: {}", e);
+                        std::thread::sleep(WIFI_RETRY_DELAY);
+                        continue;
+                    }
+                    started = true;
+                    resync_peers(&espnow, &mut peers);
+                }
+
+                let Some(frame) = data_receiver.recv_timeout(TX_POLL_INTERVAL) else {
+                    continue;
+                };
+
+                match transmit(&espnow, &mut peers, &frame) {
+                    Ok(()) => {
+                        failures = 0;
+                        tx_counters.sent.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => {
+                        failures += 1;
+                        tx_counters.send_failures.fetch_add(1, Ordering::Relaxed);
+                        log::error!(
+                            "Failed to send to {}: {:?}",
+                            format_mac(&frame.peer_addr),
+                            e
+                        );
+                    }
+                }
+
+                if failures >= MAX_SEND_FAILURES {
+                    log::warn!("{} consecutive send failures, restarting wifi", failures);
+                    if let Err(e) = wifi.stop() {
+                        log::error!("Failed to stop wifi: {}", e);
+                    }
+                    tx_counters.wifi_restarts.fetch_add(1, Ordering::Relaxed);
+                    started = false;
+                    failures = 0;
+                }
+            }
+        });
+
+        Datalink {
+            command_receiver: Some(command_receiver),
+            data_sender,
+            counters,
+        }
+    }
+
+    /// Queue `data` for `peer_addr`.  Returns [`DatalinkError::Queue`] when
+    /// the queue for `priority` is full.
+    pub fn send(
+        &self,
+        peer_addr: MacAddr,
+        data: Vec<u8>,
+        priority: Priority,
+    ) -> Result<(), DatalinkError> {
+        self.data_sender
+            .send(peer_addr, data, priority)
+            .map_err(DatalinkError::Queue)
+    }
+
+    pub fn stats(&self) -> DatalinkStats {
+        DatalinkStats {
+            queue: self.data_sender.stats(),
+            sent: self.counters.sent.load(Ordering::Relaxed),
+            send_failures: self.counters.send_failures.load(Ordering::Relaxed),
+            rx_dropped: self.counters.rx_dropped.load(Ordering::Relaxed),
+            wifi_restarts: self.counters.wifi_restarts.load(Ordering::Relaxed),
+        }
+    }
+}
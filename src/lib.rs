@@ -1,8 +1,10 @@
 pub mod altimeter;
 pub mod battery;
-pub mod control_panel;
 pub mod datalink;
 pub mod kalman;
-pub mod keypad;
 pub mod telemetry;
-pub mod ui;
+
+pub use rocket_core::{
+    config, console, control_panel, dashboard, keyboard, keypad, settings, storage, ui,
+};
+pub use rocket_protocol::{command, flight};
@@ -1,22 +1,36 @@
 use std::sync::{Arc, Mutex};
 
 use altimeter::Altimeter;
+use command::{Ack, AckStatus, Command};
+use config::{FlightConfig, FlightSetting};
 
 pub(crate) use buzzer::Buzzer;
-use datalink::ByteSerialize;
 use esp_idf_hal::prelude::*;
 use esp_idf_hal::{
     i2c::{I2cConfig, I2cDriver},
     peripherals::Peripherals,
 };
-use telemetry::Telemetry;
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use flight::PhaseDetector;
+use rocket_core::config;
+use rocket_protocol::{command, flight};
+use telemetry::{
+    batch::{BatchConfig, TelemetryBatcher},
+    ProtocolVersion, Status, Telemetry,
+};
+
+use crate::datalink::{Datalink, Priority};
 
-use crate::datalink::Datalink;
+/// Encoding used for single telemetry samples
+const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion::Extended;
+/// NVS namespace for flight computer settings
+const NVS_NAMESPACE: &str = "rocket";
 
 #[derive(Debug)]
 struct State {
     telemetry_addr: Option<[u8; 6]>,
     streaming: bool,
+    phase_detector: PhaseDetector,
 }
 
 // struct Rocket<I2C, C, T>
@@ -34,6 +48,7 @@ impl Default for State {
         State {
             telemetry_addr: None,
             streaming: false,
+            phase_detector: PhaseDetector::new(),
         }
     }
 }
@@ -44,7 +59,74 @@ mod buzzer;
 mod datalink;
 mod kalman;
 mod telemetry;
-mod ui;
+
+/// Put the settings that take effect straight away into use.
+fn apply_config<I2C>(config: &FlightConfig, altimeter: &mut Altimeter<I2C>, buzzer: &Buzzer)
+where
+    I2C: embedded_hal::i2c::I2c,
+{
+    altimeter.set_sea_level_pressure(config.sea_level_pressure);
+    altimeter.set_filter_noise(config.filter_noise);
+    buzzer.period(config.buzzer_period);
+}
+
+/// Carry out a settings command from the basestation, saving any change
+/// and answering with the setting now in use.
+fn configure<I2C>(
+    config: &mut FlightConfig,
+    altimeter: &mut Altimeter<I2C>,
+    buzzer: &Buzzer,
+    store: &mut EspNvs<NvsDefault>,
+    id: u8,
+    command: Command,
+) -> Ack
+where
+    I2C: embedded_hal::i2c::I2c,
+{
+    let (setting, value) = match command {
+        Command::SetSeaLevelPressure(pascals) => {
+            (Some(FlightSetting::SeaLevelPressure), Some(pascals))
+        }
+        Command::SetConfig(setting, value) => (Some(setting), Some(value)),
+        Command::GetConfig(setting) => (Some(setting), None),
+        Command::FactoryReset => {
+            log::info!("factory reset");
+            *config = FlightConfig::default();
+            if let Err(e) = FlightConfig::factory_reset(store) {
+                log::warn!("unable to forget settings: {:?}", e);
+            }
+            (None, None)
+        }
+        Command::Text(_) => (None, None),
+    };
+
+    let status = match (setting, value) {
+        (Some(setting), Some(value)) => match config.set(setting, value as f64) {
+            Ok(()) => {
+                log::info!("{} set to {}", setting.name(), value);
+                if setting.needs_restart() {
+                    log::info!("{} takes effect after a restart", setting.name());
+                }
+                if let Err(e) = config.save(store) {
+                    log::warn!("unable to save settings: {:?}", e);
+                }
+                AckStatus::Accepted
+            }
+            Err(_) => {
+                log::warn!("rejected {} of {}", setting.name(), value);
+                AckStatus::Rejected
+            }
+        },
+        _ => AckStatus::Accepted,
+    };
+    apply_config(config, altimeter, buzzer);
+
+    Ack {
+        id,
+        status,
+        value: setting.map_or(0.0, |setting| config.get(setting) as f32),
+    }
+}
 
 fn main() {
     // It is necessary to call this function once. Otherwise some patches to the runtime
@@ -67,7 +149,6 @@ fn main() {
 
     // Create buzzer driver
     let buzzer = Buzzer::new(peripherals.pins.gpio4);
-    buzzer.period(100);
     buzzer.pattern(buzzer::BuzzPattern::Beep {
         frequency: 4186,
         duration: 50,
@@ -86,22 +167,45 @@ fn main() {
     // Create altimeter driver
     let mut altimeter = Altimeter::new(Arc::new(Mutex::new(i2c_driver))).unwrap();
 
-    let mut datalink = Datalink::new(peripherals.modem);
+    let nvs = EspDefaultNvsPartition::take().unwrap();
+    let mut store = EspNvs::new(nvs.clone(), NVS_NAMESPACE, true).unwrap();
+
+    let mut flight_config = match FlightConfig::load(&store) {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => {
+            log::warn!("unable to load settings, using defaults: {:?}", e);
+            FlightConfig::default()
+        }
+    };
+    apply_config(&flight_config, &mut altimeter, &buzzer);
+
+    let mut datalink = Datalink::new(peripherals.modem, nvs);
     let command_receiver = datalink.command_receiver.take().unwrap();
 
     let altimeter_stats = altimeter.stats.clone();
     let state2 = state.clone();
     let altimeter2 = altimeter.clone();
 
-    let recording = Arc::new(Mutex::new(Vec::<Telemetry>::with_capacity(900)));
+    // the capacity is settable remotely, so one the heap can't hold falls
+    // back to the default rather than aborting every boot
+    let mut recording = Vec::<Telemetry>::new();
+    if let Err(e) = recording.try_reserve_exact(flight_config.recording_capacity as usize) {
+        let capacity = FlightConfig::default().recording_capacity;
+        log::warn!(
+            "unable to record {} samples, recording {}: {}",
+            flight_config.recording_capacity,
+            capacity,
+            e
+        );
+        recording.reserve_exact(capacity as usize);
+    }
+    let recording = Arc::new(Mutex::new(recording));
     let recording2 = recording.clone();
     let data_sender = datalink.data_sender.clone();
-
-    // poor broken screen
-    let touch_scale = (
-        (-453.85041551246536, 267.09141274238226),
-        (-476.5561372891216, 372.7632344386271),
-    );
+    let batch_config = BatchConfig {
+        max_samples: flight_config.batch_samples as usize,
+        ..BatchConfig::default()
+    };
 
     std::thread::spawn(move || {
         let mut altimeter = altimeter2;
@@ -111,12 +215,29 @@ fn main() {
         loop {
             let (mac_arr, data) = command_receiver.recv().unwrap();
 
-            let data = if let Ok(data) = String::from_utf8(data) {
-                log::info!("received command: {}", data);
-                data.trim().to_string()
-            } else {
-                log::warn!("unable to read command");
-                continue;
+            let data = match Command::from_frame(&data) {
+                Ok((_, Command::Text(data))) => {
+                    log::info!("received command: {}", data);
+                    data
+                }
+                Ok((id, command)) => {
+                    let ack = configure(
+                        &mut flight_config,
+                        &mut altimeter,
+                        &buzzer,
+                        &mut store,
+                        id,
+                        command,
+                    );
+                    if let Err(e) = data_sender.send(mac_arr, ack.to_frame(), Priority::Control) {
+                        log::warn!("unable to queue ack: {:?}", e);
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!("unable to read command: {:?}", e);
+                    continue;
+                }
             };
 
             if data.eq_ignore_ascii_case("tone") {
@@ -150,8 +271,6 @@ fn main() {
                     let num = parts[1].parse::<usize>();
 
                     if let Ok(num) = num {
-                        let mut buffer = [0u8; 33];
-
                         let telemetry = {
                             let recording = recording.lock().unwrap();
                             let telemetry_option = recording.get(num);
@@ -166,11 +285,11 @@ fn main() {
                             log::info!("retransmitting {}", num);
                             let state = state.lock().unwrap();
                             if let Some(addr) = state.telemetry_addr {
-                                telemetry.as_bytes(&mut buffer).unwrap();
-
-                                let data_vec = Vec::from(buffer);
+                                let data_vec = telemetry.to_frame(PROTOCOL_VERSION);
 
-                                data_sender.send((addr, data_vec)).unwrap();
+                                if let Err(e) = data_sender.send(addr, data_vec, Priority::Bulk) {
+                                    log::warn!("unable to queue retransmit: {:?}", e);
+                                }
                             } else {
                                 log::info!("no peer addr to retransmit to");
                             }
@@ -181,27 +300,9 @@ fn main() {
                 }
             }
 
-            if data.eq_ignore_ascii_case("inhg") {
-                let parts: Vec<&str> = data.trim().split(' ').collect();
-
-                if parts.len() < 2 {
-                    log::info!("No pressure provided");
-                } else {
-                    let sea_level_pressure = parts[1].parse::<f64>();
-
-                    if let Ok(sea_level_pressure) = sea_level_pressure {
-                        log::info!("Inhg updated");
-                        altimeter.sea_level_pressure(sea_level_pressure);
-                    } else {
-                        log::info!("Failed to parse pressure");
-                    }
-                }
-
-                log::info!("pressure not set");
-            }
-
             if data.eq_ignore_ascii_case("reset") {
                 altimeter.reset_stats();
+                state.lock().unwrap().phase_detector.reset();
             }
         }
     });
@@ -211,24 +312,40 @@ fn main() {
 
     println!("size of telemetry: {}", std::mem::size_of::<Telemetry>());
 
+    let mut batcher = TelemetryBatcher::new(batch_config);
+    // reported with the next sample sent after a failed read
+    let mut sensor_fault = false;
+
     loop {
         let update_result = altimeter.update_stats();
 
         if let Err(e) = update_result {
             log::error!("Failed to update altimeter: {:?}", e);
-            // todo send a message to base station :(
+            sensor_fault = true;
         } else {
             let stats = { altimeter_stats.lock().unwrap().clone() };
             let mut guard = state.lock().unwrap();
+            let phase = guard
+                .phase_detector
+                .update(stats.altitude as f32, stats.vertical_velocity as f32);
+
             if guard.streaming {
                 if let Some(ref mut addr) = guard.telemetry_addr {
                     let mut peer_addr = [0u8; 6];
                     peer_addr.copy_from_slice(addr);
 
-                    log::info!("altitude: {}", stats.altitude);
+                    log::info!(
+                        "altitude: {} velocity: {} phase: {}",
+                        stats.altitude,
+                        stats.vertical_velocity,
+                        phase.label()
+                    );
 
-                    let mut telemetry = Telemetry::from((stats, battery.stats().unwrap()));
+                    let mut telemetry = telemetry::from_stats(stats, battery.stats().unwrap());
                     telemetry.time = start.elapsed().as_millis() as u32;
+                    telemetry.phase = phase;
+                    telemetry.status.set(Status::SENSOR_FAULT, sensor_fault);
+                    sensor_fault = false;
 
                     if {
                         // perform scoped so as to prevent holding lock through tx.
@@ -240,15 +357,19 @@ fn main() {
                             false
                         }
                     } {
-                        let mut buffer = [0u8; 33];
+                        let now = std::time::Instant::now();
 
-                        telemetry.as_bytes(&mut buffer).unwrap();
-
-                        let data_vec = Vec::from(buffer);
-
-                        datalink.data_sender.send((peer_addr, data_vec)).ok();
+                        if let Some(data_vec) = batcher.push(telemetry, now) {
+                            if let Err(e) = datalink.send(peer_addr, data_vec, Priority::Telemetry)
+                            {
+                                log::warn!("telemetry dropped: {:?}", e);
+                            }
+                        }
                     }
                 }
+            } else {
+                // discard a partial batch rather than sending stale samples later
+                batcher.flush();
             }
         }
         // update_stats on altimeter will sleep for 100ms
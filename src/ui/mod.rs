@@ -1,3 +0,0 @@
-pub mod button;
-pub mod text;
-pub mod ui;